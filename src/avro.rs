@@ -0,0 +1,336 @@
+/// Generation for Apache Avro schemas (`.avsc`)
+///
+/// Structs become records and enums become enums, and a named type is defined in full where it
+/// is first used. `Option` members become a `["null", T]` union defaulting to `null`. Avro has no
+/// unsigned or smaller integers, nor chars: `u32`, `i64` and `u64` are `long`, which cannot hold
+/// the largest `u64` values, the smaller integers are `int`, and chars are strings.
+///
+/// A file holds the schema of the one type no other type uses, or of the `root` option, and
+/// otherwise a union of every type. JSON has no comments, so the file has no preamble.
+///
+/// Supported options:
+/// - `namespace`: the namespace of the types, none by default
+/// - `root`: the type the schema is for
+use crate::compilation_target::{CompilationError, CompilationInfo, CompilationTarget};
+use crate::parser::{ASTNode, DataType};
+use crate::schema::{Declaration, Schema};
+use serde_json::{json, Map, Value};
+
+pub struct AvroGenerator;
+
+impl CompilationTarget for AvroGenerator {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        let schema = Schema::from_ast(ast)?;
+        let mut generator = AvroSchemaGenerator::new(&schema, compilation_info.options.get("namespace"));
+        let avro_schema = generator.generate(compilation_info.options.get("root"))?;
+
+        let code = serde_json::to_string_pretty(&avro_schema).expect("JSON values should serialize");
+        Ok(format!("{}\n", code))
+    }
+
+    fn warnings(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Vec<String> {
+        let Ok(schema) = Schema::from_ast(ast) else {
+            return Vec::new();
+        };
+        let mut generator = AvroSchemaGenerator::new(&schema, compilation_info.options.get("namespace"));
+        match generator.generate(compilation_info.options.get("root")) {
+            Ok(_) => generator.warnings,
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+struct AvroSchemaGenerator<'a> {
+    schema: &'a Schema,
+    namespace: Option<&'a str>,
+    /// The named types defined so far, which are referred to by name
+    defined: Vec<&'a str>,
+    warnings: Vec<String>,
+}
+
+impl<'a> AvroSchemaGenerator<'a> {
+    fn new(schema: &'a Schema, namespace: Option<&'a str>) -> Self {
+        Self {
+            schema,
+            namespace,
+            defined: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn generate(&mut self, root: Option<&str>) -> Result<Value, CompilationError> {
+        let root = match root {
+            Some(root) => Some(self.schema.declaration(root).ok_or_else(|| {
+                CompilationError::InvalidOption(format!("root '{}' is not declared", root))
+            })?),
+            None => {
+                let mut roots = self.schema.declarations.iter().filter(|declaration| {
+                    !self.schema.structs().any(|struct_definition| {
+                        struct_definition.name != declaration.name()
+                            && struct_definition
+                                .members
+                                .iter()
+                                .any(|member| member.data_type.user_defined_types().contains(&declaration.name()))
+                    })
+                });
+                match (roots.next(), roots.next()) {
+                    // Types in a cycle are used by each other, so may not be reachable from the root
+                    (Some(root), None) if self.reachable(root).len() == self.schema.declarations.len() => Some(root),
+                    _ => None,
+                }
+            }
+        };
+        if let Some(root) = root {
+            let reachable = self.reachable(root);
+            for declaration in &self.schema.declarations {
+                if !reachable.contains(&declaration.name()) {
+                    self.warnings.push(format!("'{}' is not used by root '{}', so is left out", declaration.name(), root.name()));
+                }
+            }
+        }
+
+        match root {
+            Some(root) => self.generate_declaration(root),
+            None => {
+                let mut schemas = Vec::new();
+                for declaration in self.schema.dependency_order() {
+                    // Types referring to each other cyclically are defined within one another
+                    if !self.defined.contains(&declaration.name()) {
+                        schemas.push(self.generate_declaration(declaration)?);
+                    }
+                }
+                Ok(Value::Array(schemas))
+            }
+        }
+    }
+
+    /// The names of the declarations a declaration uses, directly or indirectly, including itself
+    fn reachable(&self, declaration: &'a Declaration) -> Vec<&'a str> {
+        let mut reachable = vec![declaration.name()];
+        let mut index = 0;
+        while let Some(name) = reachable.get(index) {
+            if let Some(Declaration::Struct(struct_definition)) = self.schema.declaration(name) {
+                for member in &struct_definition.members {
+                    for used in member.data_type.user_defined_types() {
+                        if self.schema.declaration(used).is_some() && !reachable.contains(&used) {
+                            reachable.push(used);
+                        }
+                    }
+                }
+            }
+            index += 1;
+        }
+        reachable
+    }
+
+    /// Generates the definition of a named type, or its name if it has been defined already
+    fn generate_named_type(&mut self, name: &str) -> Result<Value, CompilationError> {
+        match self.schema.declaration(name) {
+            Some(declaration) if !self.defined.contains(&declaration.name()) => self.generate_declaration(declaration),
+            _ => Ok(Value::from(self.full_name(name))),
+        }
+    }
+
+    fn full_name(&self, name: &str) -> String {
+        match self.namespace {
+            Some(namespace) => format!("{}.{}", namespace, name),
+            None => name.to_owned(),
+        }
+    }
+
+    fn generate_declaration(&mut self, declaration: &'a Declaration) -> Result<Value, CompilationError> {
+        // Defined before the fields, so that a record can refer to itself
+        self.defined.push(declaration.name());
+
+        let mut avro_schema = Map::new();
+        match declaration {
+            Declaration::Struct(struct_definition) => {
+                avro_schema.insert("type".to_owned(), json!("record"));
+                avro_schema.insert("name".to_owned(), json!(struct_definition.name));
+                if let Some(namespace) = self.namespace {
+                    avro_schema.insert("namespace".to_owned(), json!(namespace));
+                }
+                let mut fields = Vec::new();
+                for member in &struct_definition.members {
+                    let mut field = json!({
+                        "name": member.name,
+                        "type": self.generate_type(&member.name, &member.data_type)?,
+                    });
+                    if let DataType::Option(_) = member.data_type {
+                        field["default"] = Value::Null;
+                    }
+                    fields.push(field);
+                }
+                avro_schema.insert("fields".to_owned(), Value::Array(fields));
+            }
+            Declaration::Enum(enum_definition) => {
+                avro_schema.insert("type".to_owned(), json!("enum"));
+                avro_schema.insert("name".to_owned(), json!(enum_definition.name));
+                if let Some(namespace) = self.namespace {
+                    avro_schema.insert("namespace".to_owned(), json!(namespace));
+                }
+                avro_schema.insert("symbols".to_owned(), json!(enum_definition.members));
+            }
+        }
+        Ok(Value::Object(avro_schema))
+    }
+
+    fn generate_type(&mut self, member_name: &str, data_type: &DataType) -> Result<Value, CompilationError> {
+        Ok(match data_type {
+            DataType::U8 | DataType::I8 | DataType::U16 | DataType::I16 | DataType::I32 => json!("int"),
+            DataType::U32 | DataType::I64 => json!("long"),
+            DataType::U64 => {
+                self.warnings.push(format!(
+                    "'{}' is a u64, which is a signed long in Avro and cannot hold values above {}",
+                    member_name,
+                    i64::MAX
+                ));
+                json!("long")
+            }
+            DataType::F32 => json!("float"),
+            DataType::F64 => json!("double"),
+            DataType::Char | DataType::String => json!("string"),
+            DataType::Bool => json!("boolean"),
+            DataType::Option(inner_type) => {
+                if let DataType::Option(_) = inner_type.as_ref() {
+                    return Err(CompilationError::UnsupportedType(format!(
+                        "'{}' is an option of an option, which Avro unions cannot nest",
+                        member_name
+                    )));
+                }
+                json!(["null", self.generate_type(member_name, inner_type)?])
+            }
+            DataType::Array(inner_type) => json!({
+                "type": "array",
+                "items": self.generate_type(member_name, inner_type)?,
+            }),
+            DataType::UserDefined(name) => self.generate_named_type(name)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+
+    // A u64 does not fit a long, a char is a string, unions nest in arrays but not in other unions,
+    // and a record refers to itself and to types defined already by their full names
+    const TEXT: &str = "struct node { id: u64, initial: char, labels: array(option(string)), kind: enum kind { leaf, branch, }, history: array(kind), parent: option(node) }";
+
+    const GENERATED_CODE: &str = r#"{
+  "type": "record",
+  "name": "node",
+  "namespace": "com.acme",
+  "fields": [
+    {
+      "name": "id",
+      "type": "long"
+    },
+    {
+      "name": "initial",
+      "type": "string"
+    },
+    {
+      "name": "labels",
+      "type": {
+        "type": "array",
+        "items": [
+          "null",
+          "string"
+        ]
+      }
+    },
+    {
+      "name": "kind",
+      "type": {
+        "type": "enum",
+        "name": "kind",
+        "namespace": "com.acme",
+        "symbols": [
+          "leaf",
+          "branch"
+        ]
+      }
+    },
+    {
+      "name": "history",
+      "type": {
+        "type": "array",
+        "items": "com.acme.kind"
+      }
+    },
+    {
+      "name": "parent",
+      "type": [
+        "null",
+        "com.acme.node"
+      ],
+      "default": null
+    }
+  ]
+}
+"#;
+
+    #[test]
+    fn test_generate_avro() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+        let mut compilation_info = CompilationInfo::default();
+        compilation_info.options.set("namespace", "com.acme");
+
+        assert_eq!(
+            AvroGenerator
+                .generate_code(&ast, &compilation_info)
+                .expect("should generate code"),
+            GENERATED_CODE
+        );
+        assert_eq!(
+            AvroGenerator.warnings(&ast, &compilation_info),
+            ["'id' is a u64, which is a signed long in Avro and cannot hold values above 9223372036854775807"]
+        );
+
+        let ast = parse_tokens(lex_tokens("struct node { maybe: option(option(u8)) }".to_owned()).expect("should lex"))
+            .expect("should parse");
+        assert!(matches!(
+            AvroGenerator.generate_code(&ast, &compilation_info),
+            Err(CompilationError::UnsupportedType(_))
+        ));
+    }
+
+    #[test]
+    fn test_generate_avro_union_of_roots() {
+        let ast = parse_tokens(
+            lex_tokens("struct a { b: b } struct b { x: u64 } struct c { b: b }".to_owned()).expect("should lex"),
+        )
+        .expect("should parse");
+
+        let generated_code = AvroGenerator
+            .generate_code(&ast, &CompilationInfo::default())
+            .expect("should generate code");
+        let avro_schema: Value = serde_json::from_str(&generated_code).expect("should be JSON");
+        assert_eq!(avro_schema[0]["name"], "b");
+        assert_eq!(avro_schema[1]["fields"][0]["type"], "b");
+        assert_eq!(avro_schema[2]["fields"][0]["type"], "b");
+        assert_eq!(AvroGenerator.warnings(&ast, &CompilationInfo::default()).len(), 1);
+
+        // `a` and `b` use each other, so neither is used by `r` nor a root itself
+        let ast = parse_tokens(
+            lex_tokens("struct r { x: u8 } struct a { b: option(b) } struct b { a: option(a) }".to_owned())
+                .expect("should lex"),
+        )
+        .expect("should parse");
+        let generated_code = AvroGenerator
+            .generate_code(&ast, &CompilationInfo::default())
+            .expect("should generate code");
+        let avro_schema: Value = serde_json::from_str(&generated_code).expect("should be JSON");
+        let names = avro_schema
+            .as_array()
+            .expect("should be a union")
+            .iter()
+            .map(|schema| schema["name"].as_str().expect("should be named"))
+            .collect::<Vec<&str>>();
+        assert_eq!(names, ["r", "b"]);
+        assert_eq!(avro_schema[1]["fields"][0]["type"][1]["name"], "a");
+    }
+}
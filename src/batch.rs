@@ -0,0 +1,233 @@
+/// Batch compilation of several data definition files
+///
+/// Inputs may be files, directories (every `.fruko` file within them, recursively) or glob
+/// patterns. Each file is compiled into its own set of outputs, and a user defined type may refer
+/// to a type declared in any other file of the batch. Such references are resolved here, so
+/// targets can import the type from the outputs of the declaring file.
+use crate::compilation_target::{CompilationError, ExternalType};
+use crate::parser::ASTNode;
+use crate::schema::{DeclarationKind, Schema};
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+/// Extension of data definition files, used to find the inputs within a directory
+pub const SOURCE_FILE_EXTENSION: &str = "fruko";
+
+/// A parsed input file of the batch
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub ast: ASTNode,
+}
+
+/// The result of resolving the user defined types referenced by a single file
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Resolution {
+    pub external_types: Vec<ExternalType>,
+    /// Referenced types not declared anywhere in the batch
+    pub unresolved_types: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum BatchError {
+    Io(PathBuf, std::io::Error),
+    InvalidPattern(glob::PatternError),
+    NoMatchingInputs(String),
+    DuplicateModule(String),
+    AmbiguousType { name: String, modules: Vec<String> },
+    Compilation(CompilationError),
+}
+
+impl Display for BatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BatchError::Io(path, error) => write!(f, "Could not read {}: {}", path.display(), error),
+            BatchError::InvalidPattern(error) => write!(f, "Invalid input pattern: {}", error),
+            BatchError::NoMatchingInputs(input) => write!(f, "No input files found for '{}'", input),
+            BatchError::DuplicateModule(module) => {
+                write!(f, "More than one input file is named '{}', so their outputs would collide", module)
+            }
+            BatchError::AmbiguousType { name, modules } => {
+                write!(f, "Type '{}' is declared in more than one file: {}", name, modules.join(", "))
+            }
+            BatchError::Compilation(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for BatchError {}
+
+/// Expands input files, directories and glob patterns into the list of files to compile. Each file
+/// is only listed once, in the order it was first found
+pub fn expand_inputs(inputs: &[PathBuf]) -> Result<Vec<PathBuf>, BatchError> {
+    let mut files = Vec::new();
+    for input in inputs {
+        let found = if input.is_dir() {
+            source_files_in_directory(input)?
+        } else if input.is_file() {
+            vec![input.clone()]
+        } else {
+            glob::glob(&input.to_string_lossy())
+                .map_err(BatchError::InvalidPattern)?
+                .filter_map(Result::ok)
+                .filter(|path| path.is_file())
+                .collect()
+        };
+
+        if found.is_empty() {
+            return Err(BatchError::NoMatchingInputs(input.to_string_lossy().into_owned()));
+        }
+        for file in found {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Finds every data definition file within a directory and its subdirectories, in path order
+fn source_files_in_directory(directory: &Path) -> Result<Vec<PathBuf>, BatchError> {
+    let mut entries = std::fs::read_dir(directory)
+        .and_then(|entries| entries.map(|entry| entry.map(|entry| entry.path())).collect::<Result<Vec<PathBuf>, _>>())
+        .map_err(|error| BatchError::Io(directory.to_owned(), error))?;
+    entries.sort();
+
+    let mut files = Vec::new();
+    for path in entries {
+        if path.is_dir() {
+            files.append(&mut source_files_in_directory(&path)?);
+        } else if path.extension().is_some_and(|extension| extension == SOURCE_FILE_EXTENSION) {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// The module name of a file, being its file stem
+pub fn module_name(path: &Path) -> String {
+    path.file_stem().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+/// Resolves the user defined types each file references, but does not declare itself, against the
+/// declarations of the other files in the batch
+/// # Returns
+/// A resolution per file, in the same order as the files, or an error if a type is declared in
+/// more than one other file, or two files share a module name
+pub fn resolve_external_types(files: &[SourceFile]) -> Result<Vec<Resolution>, BatchError> {
+    let schemas = files
+        .iter()
+        .map(|file| Schema::from_ast(&file.ast).map_err(BatchError::Compilation))
+        .collect::<Result<Vec<Schema>, BatchError>>()?;
+
+    let mut modules = BTreeSet::new();
+    let mut declaring_modules: BTreeMap<&str, Vec<(String, DeclarationKind)>> = BTreeMap::new();
+    for (file, schema) in files.iter().zip(&schemas) {
+        let module = module_name(&file.path);
+        if !modules.insert(module.clone()) {
+            return Err(BatchError::DuplicateModule(module));
+        }
+        for declaration in &schema.declarations {
+            declaring_modules
+                .entry(declaration.name())
+                .or_default()
+                .push((module.clone(), declaration.kind()));
+        }
+    }
+
+    schemas
+        .iter()
+        .map(|schema| {
+            let mut resolution = Resolution::default();
+            for name in referenced_types(schema) {
+                if schema.declaration(&name).is_some() {
+                    continue;
+                }
+                match declaring_modules.get(name.as_str()).map(Vec::as_slice) {
+                    Some([(module, kind)]) => resolution.external_types.push(ExternalType {
+                        name,
+                        module: module.clone(),
+                        kind: *kind,
+                    }),
+                    Some(modules) => {
+                        return Err(BatchError::AmbiguousType {
+                            name,
+                            modules: modules.iter().map(|(module, _)| module.clone()).collect(),
+                        })
+                    }
+                    None => resolution.unresolved_types.push(name),
+                }
+            }
+            Ok(resolution)
+        })
+        .collect()
+}
+
+/// Every user defined type referenced by the members of a schema, each listed once
+fn referenced_types(schema: &Schema) -> BTreeSet<String> {
+    schema
+        .structs()
+        .flat_map(|struct_definition| &struct_definition.members)
+        .flat_map(|member| member.data_type.user_defined_types())
+        .map(str::to_owned)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+
+    fn source_file(path: &str, text: &str) -> SourceFile {
+        SourceFile {
+            path: PathBuf::from(path),
+            ast: parse_tokens(lex_tokens(text.to_owned()).expect("should lex")).expect("should parse"),
+        }
+    }
+
+    #[test]
+    fn test_resolve_external_types() {
+        let files = vec![
+            source_file("schema/user.fruko", "struct user { name: string, role: role, orders: array(order) }"),
+            source_file("schema/order.fruko", "struct order { id: u64, buyer: option(user), currency: currency }"),
+            source_file("schema/role.fruko", "enum role { admin, guest, }"),
+        ];
+
+        let resolutions = resolve_external_types(&files).expect("should resolve");
+        assert_eq!(
+            resolutions[0].external_types,
+            vec![
+                ExternalType { name: "order".to_owned(), module: "order".to_owned(), kind: DeclarationKind::Struct },
+                ExternalType { name: "role".to_owned(), module: "role".to_owned(), kind: DeclarationKind::Enum },
+            ]
+        );
+        assert_eq!(
+            resolutions[1],
+            Resolution {
+                external_types: vec![ExternalType {
+                    name: "user".to_owned(),
+                    module: "user".to_owned(),
+                    kind: DeclarationKind::Struct,
+                }],
+                unresolved_types: vec!["currency".to_owned()],
+            }
+        );
+        assert_eq!(resolutions[2], Resolution::default());
+    }
+
+    #[test]
+    fn test_ambiguous_type() {
+        let files = vec![
+            source_file("a.fruko", "struct a { value: shared }"),
+            source_file("b.fruko", "enum shared { one, }"),
+            source_file("c.fruko", "enum shared { two, }"),
+        ];
+
+        assert!(matches!(
+            resolve_external_types(&files),
+            Err(BatchError::AmbiguousType { .. })
+        ));
+    }
+}
@@ -0,0 +1,506 @@
+/// Generation for C11
+///
+/// The header target declares the types, and the source target defines the functions the header
+/// declares. `.h` files are C++ headers by default, so the header output must be named with
+/// `--target c=<file>`. Every struct gets a `typedef`, an `_init` function zeroing it, and a `_free` function
+/// releasing everything it owns and zeroing it again. Enum members are prefixed with the enum name
+/// in `SCREAMING_SNAKE_CASE`, as C enum members share one scope. Inline declarations are hoisted
+/// out of line.
+///
+/// Members are mapped as follows:
+/// - strings are owned, NUL terminated `char *`
+/// - arrays become a `size_t <name>_len` count and an owned pointer to the elements
+/// - an `Option` of a user defined type becomes an owned pointer, which is `NULL` when absent;
+///   any other `Option` is preceded by a `bool has_<name>` flag
+///
+/// Arrays of options or arrays, and options of options, have no representation and are errors.
+/// Members refer to user defined types as `struct x` or `enum x`, so the header compiles as C++
+/// even where a member is named like its type. Names which are C or C++ keywords get a trailing
+/// `_`.
+///
+/// Supported options of the source target:
+/// - `header`: the path the header is included from, `<module>.h` by default
+use crate::batch::module_name;
+use crate::compilation_target::{CompilationError, CompilationInfo, CompilationTarget};
+use crate::cxx::header_guard_name;
+use crate::naming::screaming_snake_case;
+use crate::parser::{ASTNode, DataType};
+use crate::schema::{Declaration, DeclarationKind, EnumDefinition, Schema, StructDefinition, StructMember};
+use std::path::Path;
+
+/// The keywords of C, and of C++ which the header is also included from, along with the macros
+/// of `<stdbool.h>`
+const KEYWORDS: [&str; 93] = [
+    "alignas", "alignof", "and", "and_eq", "asm", "auto", "bitand", "bitor", "bool", "break", "case", "catch", "char",
+    "char16_t", "char32_t", "char8_t", "class", "co_await", "co_return", "co_yield", "compl", "concept", "const",
+    "const_cast", "consteval", "constexpr", "constinit", "continue", "decltype", "default", "delete", "do", "double",
+    "dynamic_cast", "else", "enum", "explicit", "export", "extern", "false", "float", "for", "friend", "goto", "if",
+    "inline", "int", "long", "mutable", "namespace", "new", "noexcept", "not", "not_eq", "nullptr", "operator", "or",
+    "or_eq", "private", "protected", "public", "register", "reinterpret_cast", "requires", "restrict", "return",
+    "short", "signed", "sizeof", "static", "static_assert", "static_cast", "struct", "switch", "template", "this",
+    "thread_local", "throw", "true", "try", "typedef", "typeid", "typename", "union", "unsigned", "using", "virtual",
+    "void", "volatile", "wchar_t", "while", "xor", "xor_eq",
+];
+
+pub struct CHeaderGenerator;
+
+pub struct CSourceGenerator;
+
+impl CompilationTarget for CHeaderGenerator {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        let schema = Schema::from_ast(ast)?;
+        check_representable(&schema)?;
+        let guard_name = header_guard_name(&compilation_info.source_file_name);
+
+        let includes = compilation_info
+            .external_modules()
+            .keys()
+            .map(|module| format!("#include \"{}.h\"\n", module))
+            .collect::<String>();
+        let forward_declarations = schema
+            .structs()
+            .map(|struct_definition| format!("typedef struct {name} {name};\n", name = escape_keyword(&struct_definition.name)))
+            .collect::<String>();
+        let declarations = schema
+            .dependency_order()
+            .into_iter()
+            .map(|declaration| match declaration {
+                Declaration::Struct(struct_definition) => generate_struct(struct_definition, &schema, compilation_info),
+                Declaration::Enum(enum_definition) => generate_enum(enum_definition),
+            })
+            .map(|declaration| format!("\n{}", declaration))
+            .collect::<String>();
+        let prototypes = schema
+            .structs()
+            .map(|struct_definition| {
+                format!(
+                    "void {function}_init({name} *value);\nvoid {function}_free({name} *value);\n",
+                    function = struct_definition.name,
+                    name = escape_keyword(&struct_definition.name)
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let mut body = String::new();
+        if !forward_declarations.is_empty() {
+            body += &format!("\n{}", forward_declarations);
+        }
+        body += &declarations;
+        if !prototypes.is_empty() {
+            body += &format!("\n{}", prototypes);
+        }
+
+        Ok(format!(
+            "{preamble}
+#ifndef {guard}
+#define {guard}
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+{includes}
+#ifdef __cplusplus
+extern \"C\" {{
+#endif
+{body}
+#ifdef __cplusplus
+}}
+#endif
+
+#endif // {guard}
+",
+            preamble = generate_preamble(compilation_info),
+            guard = guard_name,
+            includes = includes,
+            body = body
+        ))
+    }
+}
+
+impl CompilationTarget for CSourceGenerator {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        let schema = Schema::from_ast(ast)?;
+        check_representable(&schema)?;
+        let header = match compilation_info.options.get("header") {
+            Some(header) => header.to_owned(),
+            None => format!("{}.h", module_name(Path::new(&compilation_info.source_file_name))),
+        };
+
+        let functions = schema
+            .structs()
+            .map(|struct_definition| format!("\n{}", generate_functions(struct_definition, &schema, compilation_info)))
+            .collect::<String>();
+
+        Ok(format!(
+            "{}\n#include \"{}\"\n\n#include <stdlib.h>\n#include <string.h>\n{}",
+            generate_preamble(compilation_info),
+            header,
+            functions
+        ))
+    }
+}
+
+fn generate_preamble(compilation_info: &CompilationInfo) -> String {
+    compilation_info.preamble_comments.iter()
+        .map(|preamble| format!("// {}", preamble))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Checks that every type has a representation in C, which both the header and the source require
+fn check_representable(schema: &Schema) -> Result<(), CompilationError> {
+    for declaration in &schema.declarations {
+        match declaration {
+            Declaration::Struct(struct_definition) => {
+                for member in &struct_definition.members {
+                    check_member(member)?;
+                }
+            }
+            Declaration::Enum(enum_definition) if enum_definition.members.is_empty() => {
+                return Err(CompilationError::UnsupportedType(format!(
+                    "'{}' has no members, which C enums require",
+                    enum_definition.name
+                )));
+            }
+            Declaration::Enum(_) => {}
+        }
+    }
+    Ok(())
+}
+
+fn check_member(member: &StructMember) -> Result<(), CompilationError> {
+    let check_element = |element_type: &DataType| match element_type {
+        DataType::Option(_) => Err(unsupported_type(&member.name, "an array of options")),
+        DataType::Array(_) => Err(unsupported_type(&member.name, "an array of arrays")),
+        _ => Ok(()),
+    };
+    match &member.data_type {
+        DataType::Option(inner_type) => match inner_type.as_ref() {
+            DataType::Option(_) => Err(unsupported_type(&member.name, "an option of an option")),
+            DataType::Array(element_type) => check_element(element_type),
+            _ => Ok(()),
+        },
+        DataType::Array(element_type) => check_element(element_type),
+        _ => Ok(()),
+    }
+}
+
+fn unsupported_type(name: &str, description: &str) -> CompilationError {
+    CompilationError::UnsupportedType(format!("'{}' is {}, which C cannot represent", name, description))
+}
+
+fn escape_keyword(name: &str) -> String {
+    if KEYWORDS.contains(&name) {
+        format!("{}_", name)
+    } else {
+        name.to_owned()
+    }
+}
+
+fn generate_enum(enum_definition: &EnumDefinition) -> String {
+    let prefix = screaming_snake_case(&enum_definition.name);
+    let members = enum_definition
+        .members
+        .iter()
+        .map(|member| format!("    {}_{},\n", prefix, screaming_snake_case(member)))
+        .collect::<String>();
+    format!("typedef enum {name} {{\n{}}} {name};\n", members, name = escape_keyword(&enum_definition.name))
+}
+
+/// Generates a struct definition, for the `typedef` of its forward declaration
+fn generate_struct(struct_definition: &StructDefinition, schema: &Schema, compilation_info: &CompilationInfo) -> String {
+    let fields = struct_definition
+        .members
+        .iter()
+        .map(|member| generate_fields(member, schema, compilation_info))
+        .collect::<String>();
+    format!("struct {} {{\n{}}};\n", escape_keyword(&struct_definition.name), fields)
+}
+
+/// Generates the fields representing a member, which may be more than one. The member has been
+/// checked to be representable
+fn generate_fields(member: &StructMember, schema: &Schema, compilation_info: &CompilationInfo) -> String {
+    let name = &member.name;
+    let field = escape_keyword(name);
+    let type_name = |data_type: &DataType| generate_type_name(data_type, schema, compilation_info);
+    match &member.data_type {
+        DataType::Option(inner_type) => match inner_type.as_ref() {
+            DataType::UserDefined(_) => format!("    {}*{};\n", type_name(inner_type), field),
+            DataType::Array(element_type) => format!(
+                "    bool has_{};\n    size_t {}_len;\n    {}*{};\n",
+                name,
+                name,
+                type_name(element_type),
+                field
+            ),
+            inner_type => format!("    bool has_{};\n    {}{};\n", name, type_name(inner_type), field),
+        },
+        DataType::Array(element_type) => format!("    size_t {}_len;\n    {}*{};\n", name, type_name(element_type), field),
+        data_type => format!("    {}{};\n", type_name(data_type), field),
+    }
+}
+
+/// Generates a type name, followed by a space unless it ends with a `*`, so that the member name
+/// can follow it directly
+fn generate_type_name(data_type: &DataType, schema: &Schema, compilation_info: &CompilationInfo) -> String {
+    match data_type {
+        DataType::U8 => "uint8_t ".to_owned(),
+        DataType::I8 => "int8_t ".to_owned(),
+        DataType::U16 => "uint16_t ".to_owned(),
+        DataType::I16 => "int16_t ".to_owned(),
+        DataType::U32 => "uint32_t ".to_owned(),
+        DataType::I32 => "int32_t ".to_owned(),
+        DataType::U64 => "uint64_t ".to_owned(),
+        DataType::I64 => "int64_t ".to_owned(),
+        DataType::F32 => "float ".to_owned(),
+        DataType::F64 => "double ".to_owned(),
+        DataType::Char => "char ".to_owned(),
+        DataType::String => "char *".to_owned(),
+        DataType::Bool => "bool ".to_owned(),
+        DataType::Option(inner_type) | DataType::Array(inner_type) => {
+            format!("{}*", generate_type_name(inner_type, schema, compilation_info))
+        }
+        DataType::UserDefined(name) if is_struct(name, schema, compilation_info) => {
+            format!("struct {} ", escape_keyword(name))
+        }
+        DataType::UserDefined(name) => format!("enum {} ", escape_keyword(name)),
+    }
+}
+
+/// Whether a user defined type is a struct, which owns memory, rather than an enum
+fn is_struct(name: &str, schema: &Schema, compilation_info: &CompilationInfo) -> bool {
+    schema.declaration_kind(name, &compilation_info.external_types) == DeclarationKind::Struct
+}
+
+fn generate_functions(struct_definition: &StructDefinition, schema: &Schema, compilation_info: &CompilationInfo) -> String {
+    let releases = struct_definition
+        .members
+        .iter()
+        .map(|member| generate_release(member, schema, compilation_info))
+        .collect::<String>();
+    format!(
+        "void {function}_init({name} *value) {{
+    memset(value, 0, sizeof(*value));
+}}
+
+void {function}_free({name} *value) {{
+{releases}    {function}_init(value);
+}}
+",
+        function = struct_definition.name,
+        name = escape_keyword(&struct_definition.name),
+        releases = releases
+    )
+}
+
+/// Generates the statement releasing a single value owned by a member, given as an lvalue
+fn generate_value_release(value: &str, data_type: &DataType, schema: &Schema, compilation_info: &CompilationInfo) -> Option<String> {
+    match data_type {
+        DataType::String => Some(format!("free({});", value)),
+        DataType::UserDefined(name) if is_struct(name, schema, compilation_info) => {
+            Some(format!("{}_free(&{});", name, value))
+        }
+        _ => None,
+    }
+}
+
+/// Generates the statements releasing what a member owns
+fn generate_release(member: &StructMember, schema: &Schema, compilation_info: &CompilationInfo) -> String {
+    let field = format!("value->{}", escape_keyword(&member.name));
+    let array_release = |element_type: &DataType| {
+        let elements = match generate_value_release(&format!("{}[i]", field), element_type, schema, compilation_info) {
+            Some(release) => format!(
+                "    for (size_t i = 0; i < value->{}_len; i++) {{\n        {}\n    }}\n",
+                member.name, release
+            ),
+            None => String::new(),
+        };
+        format!("{}    free({});\n", elements, field)
+    };
+
+    match &member.data_type {
+        DataType::Array(element_type) => array_release(element_type),
+        DataType::Option(inner_type) => match inner_type.as_ref() {
+            DataType::Array(element_type) => array_release(element_type),
+            DataType::UserDefined(name) if is_struct(name, schema, compilation_info) => format!(
+                "    if ({field} != NULL) {{\n        {name}_free({field});\n        free({field});\n    }}\n",
+                field = field,
+                name = name
+            ),
+            DataType::UserDefined(_) => format!("    free({});\n", field),
+            inner_type => generate_value_release(&field, inner_type, schema, compilation_info)
+                .map(|release| format!("    {}\n", release))
+                .unwrap_or_default(),
+        },
+        data_type => generate_value_release(&field, data_type, schema, compilation_info)
+            .map(|release| format!("    {}\n", release))
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+
+    // Members named like their type, and names which are keywords in C++ only
+    const TEXT: &str = "
+struct person {
+  id: u64,
+  nickname: option(string),
+  age: option(u8),
+  tags: array(string),
+  friend: option(person),
+  mood: enum mood { happy, verySad, },
+  address: address,
+  previous: array(address),
+}
+struct address { street: string, class: option(mood) }
+";
+
+    const GENERATED_HEADER: &str = "
+#ifndef PERSON_FRUKO_H
+#define PERSON_FRUKO_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern \"C\" {
+#endif
+
+typedef struct person person;
+typedef struct address address;
+
+typedef enum mood {
+    MOOD_HAPPY,
+    MOOD_VERY_SAD,
+} mood;
+
+struct address {
+    char *street;
+    enum mood *class_;
+};
+
+struct person {
+    uint64_t id;
+    bool has_nickname;
+    char *nickname;
+    bool has_age;
+    uint8_t age;
+    size_t tags_len;
+    char **tags;
+    struct person *friend_;
+    enum mood mood;
+    struct address address;
+    size_t previous_len;
+    struct address *previous;
+};
+
+void person_init(person *value);
+void person_free(person *value);
+
+void address_init(address *value);
+void address_free(address *value);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif // PERSON_FRUKO_H
+";
+
+    const GENERATED_SOURCE: &str = "
+#include \"person.h\"
+
+#include <stdlib.h>
+#include <string.h>
+
+void person_init(person *value) {
+    memset(value, 0, sizeof(*value));
+}
+
+void person_free(person *value) {
+    free(value->nickname);
+    for (size_t i = 0; i < value->tags_len; i++) {
+        free(value->tags[i]);
+    }
+    free(value->tags);
+    if (value->friend_ != NULL) {
+        person_free(value->friend_);
+        free(value->friend_);
+    }
+    address_free(&value->address);
+    for (size_t i = 0; i < value->previous_len; i++) {
+        address_free(&value->previous[i]);
+    }
+    free(value->previous);
+    person_init(value);
+}
+
+void address_init(address *value) {
+    memset(value, 0, sizeof(*value));
+}
+
+void address_free(address *value) {
+    free(value->street);
+    free(value->class_);
+    address_init(value);
+}
+";
+
+    fn compilation_info() -> CompilationInfo {
+        CompilationInfo {
+            source_file_name: "schema/person.fruko".to_owned(),
+            ..CompilationInfo::default()
+        }
+    }
+
+    #[test]
+    fn test_generate_c_header() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+
+        assert_eq!(
+            CHeaderGenerator
+                .generate_code(&ast, &compilation_info())
+                .expect("should generate code"),
+            GENERATED_HEADER
+        );
+    }
+
+    #[test]
+    fn test_generate_c_source() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+
+        assert_eq!(
+            CSourceGenerator
+                .generate_code(&ast, &compilation_info())
+                .expect("should generate code"),
+            GENERATED_SOURCE
+        );
+    }
+
+    #[test]
+    fn test_generate_c_unsupported_type() {
+        let ast = parse_tokens(lex_tokens("struct grid { rows: array(array(u8)) }".to_owned()).expect("should lex"))
+            .expect("should parse");
+
+        assert!(matches!(
+            CHeaderGenerator.generate_code(&ast, &CompilationInfo::default()),
+            Err(CompilationError::UnsupportedType(_))
+        ));
+        assert!(matches!(
+            CSourceGenerator.generate_code(&ast, &CompilationInfo::default()),
+            Err(CompilationError::UnsupportedType(_))
+        ));
+
+        let ast = parse_tokens(lex_tokens("enum empty { }".to_owned()).expect("should lex")).expect("should parse");
+        assert!(matches!(
+            CHeaderGenerator.generate_code(&ast, &CompilationInfo::default()),
+            Err(CompilationError::UnsupportedType(_))
+        ));
+    }
+}
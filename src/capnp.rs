@@ -0,0 +1,224 @@
+/// Generation for Cap'n Proto schemas (`.capnp`)
+///
+/// Structs become structs and enums become enums, with types named in `PascalCase`, and fields
+/// and enumerants in `camelCase`, as Cap'n Proto requires. Inline declarations are hoisted out of
+/// line, and types from other files are imported with `using`.
+///
+/// Fields are given ordinals (`@N`) in the order of their field numbers, so that members added
+/// with a higher field number than the existing members (`name @4: type`) keep the ordinals of
+/// the existing fields stable. `Option` members become a union of `none` and `value`, taking two
+/// ordinals. Arrays become lists, which cannot hold options. Chars are `Text`, as in the other
+/// targets.
+///
+/// A file needs a unique 64-bit ID. By default, it is derived from the source file name, so that
+/// it is stable as the file is regenerated, but changes if the file is renamed.
+///
+/// Supported options:
+/// - `file_id`: the file ID, such as `0xd1f7a2a8e3c4b5a6`
+/// - `namespace`: the C++ namespace of the types, none by default
+use crate::compilation_target::{CompilationError, CompilationInfo, CompilationTarget};
+use crate::naming::{camel_case, pascal_case};
+use crate::parser::{ASTNode, DataType};
+use crate::schema::{Declaration, EnumDefinition, Schema, StructDefinition};
+use std::collections::BTreeMap;
+
+pub struct CapnpGenerator;
+
+impl CompilationTarget for CapnpGenerator {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        let schema = Schema::from_ast(ast)?;
+        let file_id = match compilation_info.options.get("file_id") {
+            Some(file_id) => parse_file_id(file_id)?,
+            None => generate_file_id(&compilation_info.source_file_name),
+        };
+
+        let mut header = String::new();
+        if let Some(namespace) = compilation_info.options.get("namespace") {
+            header += &format!("\nusing Cxx = import \"/capnp/c++.capnp\";\n$Cxx.namespace(\"{}\");\n", namespace);
+        }
+        let imports = compilation_info
+            .external_types
+            .iter()
+            .map(|external_type| {
+                format!(
+                    "using {name} = import \"{}.capnp\".{name};\n",
+                    external_type.module,
+                    name = pascal_case(&external_type.name)
+                )
+            })
+            .collect::<String>();
+        if !imports.is_empty() {
+            header += &format!("\n{}", imports);
+        }
+        let declarations = schema
+            .declarations
+            .iter()
+            .map(|declaration| match declaration {
+                Declaration::Struct(struct_definition) => generate_struct(struct_definition),
+                Declaration::Enum(enum_definition) => Ok(generate_enum(enum_definition)),
+            })
+            .map(|declaration| declaration.map(|declaration| format!("\n{}", declaration)))
+            .collect::<Result<String, CompilationError>>()?;
+
+        Ok(format!(
+            "{}\n@{:#018x};\n{}{}",
+            generate_preamble(compilation_info),
+            file_id,
+            header,
+            declarations
+        ))
+    }
+}
+
+fn generate_preamble(compilation_info: &CompilationInfo) -> String {
+    compilation_info.preamble_comments.iter()
+        .map(|preamble| format!("# {}", preamble))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Derives a file ID from the source file name, with the FNV-1a hash. Cap'n Proto requires the
+/// highest bit of an ID to be set
+fn generate_file_id(source_file_name: &str) -> u64 {
+    let hash = source_file_name
+        .bytes()
+        .fold(0xcbf29ce484222325_u64, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3));
+    hash | 1 << 63
+}
+
+fn parse_file_id(file_id: &str) -> Result<u64, CompilationError> {
+    let invalid = || {
+        CompilationError::InvalidOption(format!(
+            "invalid file_id '{}', expected a 64-bit hexadecimal number with the highest bit set, such as 0x{:x}",
+            file_id,
+            generate_file_id(file_id)
+        ))
+    };
+    let digits = file_id.strip_prefix("0x").ok_or_else(invalid)?;
+    match u64::from_str_radix(digits, 16) {
+        Ok(id) if id >= 1 << 63 => Ok(id),
+        _ => Err(invalid()),
+    }
+}
+
+fn generate_enum(enum_definition: &EnumDefinition) -> String {
+    let enumerants = enum_definition
+        .members
+        .iter()
+        .enumerate()
+        .map(|(ordinal, member)| format!("  {} @{};\n", camel_case(member), ordinal))
+        .collect::<String>();
+    format!("enum {} {{\n{}}}\n", pascal_case(&enum_definition.name), enumerants)
+}
+
+fn generate_struct(struct_definition: &StructDefinition) -> Result<String, CompilationError> {
+    // Ordinals are assigned in the order of the field numbers; an option takes two
+    let field_numbers = struct_definition.field_numbers();
+    let mut members_by_number: Vec<(u32, usize)> =
+        field_numbers.iter().enumerate().map(|(index, number)| (*number, index)).collect();
+    members_by_number.sort();
+    let mut ordinals = BTreeMap::new();
+    let mut next_ordinal = 0;
+    for (_, index) in members_by_number {
+        ordinals.insert(index, next_ordinal);
+        next_ordinal += match struct_definition.members[index].data_type {
+            DataType::Option(_) => 2,
+            _ => 1,
+        };
+    }
+
+    let fields = struct_definition
+        .members
+        .iter()
+        .enumerate()
+        .map(|(index, member)| {
+            let name = camel_case(&member.name);
+            let ordinal = ordinals[&index];
+            match &member.data_type {
+                DataType::Option(inner_type) => Ok(format!(
+                    "  {} :union {{\n    none @{} :Void;\n    value @{} :{};\n  }}\n",
+                    name,
+                    ordinal,
+                    ordinal + 1,
+                    generate_type_name(&member.name, inner_type)?
+                )),
+                data_type => Ok(format!("  {} @{} :{};\n", name, ordinal, generate_type_name(&member.name, data_type)?)),
+            }
+        })
+        .collect::<Result<String, CompilationError>>()?;
+    Ok(format!("struct {} {{\n{}}}\n", pascal_case(&struct_definition.name), fields))
+}
+
+fn generate_type_name(member_name: &str, data_type: &DataType) -> Result<String, CompilationError> {
+    Ok(match data_type {
+        DataType::U8 => "UInt8".to_owned(),
+        DataType::I8 => "Int8".to_owned(),
+        DataType::U16 => "UInt16".to_owned(),
+        DataType::I16 => "Int16".to_owned(),
+        DataType::U32 => "UInt32".to_owned(),
+        DataType::I32 => "Int32".to_owned(),
+        DataType::U64 => "UInt64".to_owned(),
+        DataType::I64 => "Int64".to_owned(),
+        DataType::F32 => "Float32".to_owned(),
+        DataType::F64 => "Float64".to_owned(),
+        DataType::Char | DataType::String => "Text".to_owned(),
+        DataType::Bool => "Bool".to_owned(),
+        DataType::Option(_) => {
+            return Err(CompilationError::UnsupportedType(format!(
+                "'{}' nests an option in an option or array, which Cap'n Proto cannot represent",
+                member_name
+            )))
+        }
+        DataType::Array(inner_type) => format!("List({})", generate_type_name(member_name, inner_type)?),
+        DataType::UserDefined(name) => pascal_case(name),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+
+    const TEXT: &str = "struct person { id @1: u64, nickname @3: option(string), scores @2: array(array(f32)), mood @4: enum mood { happy, VerySad, } }";
+
+    const GENERATED_CODE: &str = "
+@0xd1f7a2a8e3c4b5a6;
+
+enum Mood {
+  happy @0;
+  verySad @1;
+}
+
+struct Person {
+  id @0 :UInt64;
+  nickname :union {
+    none @2 :Void;
+    value @3 :Text;
+  }
+  scores @1 :List(List(Float32));
+  mood @4 :Mood;
+}
+";
+
+    #[test]
+    fn test_generate_capnp() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+        let mut compilation_info = CompilationInfo::default();
+        compilation_info.options.set("file_id", "0xd1f7a2a8e3c4b5a6");
+
+        assert_eq!(
+            CapnpGenerator
+                .generate_code(&ast, &compilation_info)
+                .expect("should generate code"),
+            GENERATED_CODE
+        );
+    }
+
+    #[test]
+    fn test_generate_file_id() {
+        assert!(generate_file_id("schema/person.fruko") >= 1 << 63);
+        assert_eq!(generate_file_id("schema/person.fruko"), generate_file_id("schema/person.fruko"));
+        assert!(matches!(parse_file_id("0x1234"), Err(CompilationError::InvalidOption(_))));
+    }
+}
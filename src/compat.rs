@@ -0,0 +1,385 @@
+/// Compatibility checking between two versions of a data definition
+///
+/// The two versions are compared declaration by declaration, matching types, struct members and
+/// enum members by name, as values are exchanged as JSON (see [crate::validate]). Each change is
+/// classified by which readers can still read the values written by which writers:
+/// - backward compatible: readers using the new definition can read values written with the old
+/// - forward compatible: readers using the old definition can read values written with the new
+/// - fully compatible: both, e.g. adding an optional member
+/// - breaking: neither, e.g. changing a member from a string to a number
+///
+/// Readers ignore members they do not know of, and treat missing options as absent. The wire
+/// format is positional instead, so any change to the members of a struct breaks it.
+use crate::compilation_target::CompilationError;
+use crate::parser::{ASTNode, DataDefinition, DataType};
+use crate::schema::{Declaration, Schema};
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Compatibility {
+    Full,
+    Backward,
+    Forward,
+    Breaking,
+}
+
+impl Compatibility {
+    fn from_directions(backward: bool, forward: bool) -> Self {
+        match (backward, forward) {
+            (true, true) => Compatibility::Full,
+            (true, false) => Compatibility::Backward,
+            (false, true) => Compatibility::Forward,
+            (false, false) => Compatibility::Breaking,
+        }
+    }
+
+    /// Whether readers using the new definition can read values written with the old
+    pub fn is_backward(self) -> bool {
+        matches!(self, Compatibility::Full | Compatibility::Backward)
+    }
+
+    /// Whether readers using the old definition can read values written with the new
+    pub fn is_forward(self) -> bool {
+        matches!(self, Compatibility::Full | Compatibility::Forward)
+    }
+
+    /// The compatibility of two changes made together
+    pub fn and(self, other: Compatibility) -> Self {
+        Compatibility::from_directions(
+            self.is_backward() && other.is_backward(),
+            self.is_forward() && other.is_forward(),
+        )
+    }
+}
+
+impl Display for Compatibility {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Compatibility::Full => write!(f, "fully compatible"),
+            Compatibility::Backward => write!(f, "backward compatible"),
+            Compatibility::Forward => write!(f, "forward compatible"),
+            Compatibility::Breaking => write!(f, "breaking"),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    TypeAdded,
+    TypeRemoved,
+    /// A struct which became an enum, or the other way around
+    KindChanged,
+    MemberAdded,
+    MemberRemoved,
+    /// A member whose type changed in a way not covered by the other kinds
+    MemberRetyped,
+    OptionalToRequired,
+    RequiredToOptional,
+    /// An integer member changed to a type which holds fewer values, e.g. `u32` to `u16`
+    IntegerNarrowed,
+    /// An integer member changed to a type which holds more values, e.g. `u16` to `i32`
+    IntegerWidened,
+    EnumMemberAdded,
+    EnumMemberRemoved,
+}
+
+/// A change to a type, or to one of its members if `member` is given. `old` and `new` are the
+/// kinds of a declaration, or the types of a member, before and after a change of them
+#[derive(PartialEq, Eq, Debug, Clone, Serialize)]
+pub struct Change {
+    pub kind: ChangeKind,
+    #[serde(rename = "type")]
+    pub type_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub member: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<String>,
+    pub compatibility: Compatibility,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let subject = match &self.member {
+            Some(member) => format!("'{}.{}'", self.type_name, member),
+            None => format!("'{}'", self.type_name),
+        };
+        let old = self.old.as_deref().unwrap_or_default();
+        let new = self.new.as_deref().unwrap_or_default();
+        write!(f, "{}: ", self.compatibility)?;
+        match self.kind {
+            ChangeKind::TypeAdded => write!(f, "type {} was added", subject),
+            ChangeKind::TypeRemoved => write!(f, "type {} was removed", subject),
+            ChangeKind::KindChanged => write!(f, "type {} changed from {} to {}", subject, old, new),
+            ChangeKind::MemberAdded => write!(f, "member {} of type {} was added", subject, new),
+            ChangeKind::MemberRemoved => write!(f, "member {} of type {} was removed", subject, old),
+            ChangeKind::MemberRetyped => write!(f, "member {} changed type from {} to {}", subject, old, new),
+            ChangeKind::OptionalToRequired => write!(f, "member {} was made required, from {} to {}", subject, old, new),
+            ChangeKind::RequiredToOptional => write!(f, "member {} was made optional, from {} to {}", subject, old, new),
+            ChangeKind::IntegerNarrowed => write!(f, "member {} was narrowed from {} to {}", subject, old, new),
+            ChangeKind::IntegerWidened => write!(f, "member {} was widened from {} to {}", subject, old, new),
+            ChangeKind::EnumMemberAdded => write!(f, "enum member {} was added", subject),
+            ChangeKind::EnumMemberRemoved => write!(f, "enum member {} was removed", subject),
+        }
+    }
+}
+
+/// The changes from one version of a data definition to the next
+#[derive(PartialEq, Eq, Debug, Clone, Serialize)]
+pub struct Report {
+    /// The compatibility of all of the changes together
+    pub compatibility: Compatibility,
+    pub changes: Vec<Change>,
+}
+
+/// Compares two versions of a data definition
+/// # Returns
+/// The changes from `old` to `new`, in the order of the declarations of `old` followed by those
+/// added in `new`, or CompilationError::InvalidAST if either is not a valid data definition
+pub fn compare(old: &DataDefinition, new: &DataDefinition) -> Result<Report, CompilationError> {
+    let old_schema = Schema::from_ast(&ASTNode::DataDefinition(old.clone()))?;
+    let new_schema = Schema::from_ast(&ASTNode::DataDefinition(new.clone()))?;
+
+    let mut changes = Vec::new();
+    for old_declaration in &old_schema.declarations {
+        let type_name = old_declaration.name();
+        let change = |kind, member: Option<&str>, old: Option<String>, new: Option<String>, compatibility| Change {
+            kind,
+            type_name: type_name.to_owned(),
+            member: member.map(str::to_owned),
+            old,
+            new,
+            compatibility,
+        };
+        match (old_declaration, new_schema.declaration(type_name)) {
+            (_, None) => changes.push(change(ChangeKind::TypeRemoved, None, None, None, Compatibility::Breaking)),
+            (Declaration::Struct(old_struct), Some(Declaration::Struct(new_struct))) => {
+                for old_member in &old_struct.members {
+                    let old_type = Some(old_member.data_type.to_string());
+                    match new_struct.members.iter().find(|new_member| new_member.name == old_member.name) {
+                        // Old values with the member are still readable, but new values without it
+                        // are only readable by old readers if it was optional
+                        None => changes.push(change(
+                            ChangeKind::MemberRemoved,
+                            Some(&old_member.name),
+                            old_type,
+                            None,
+                            Compatibility::from_directions(true, is_optional(&old_member.data_type)),
+                        )),
+                        Some(new_member) if new_member.data_type != old_member.data_type => changes.push(change(
+                            member_change_kind(&old_member.data_type, &new_member.data_type),
+                            Some(&old_member.name),
+                            old_type,
+                            Some(new_member.data_type.to_string()),
+                            type_compatibility(&old_member.data_type, &new_member.data_type),
+                        )),
+                        Some(_) => {}
+                    }
+                }
+                for new_member in &new_struct.members {
+                    if !old_struct.members.iter().any(|old_member| old_member.name == new_member.name) {
+                        changes.push(change(
+                            ChangeKind::MemberAdded,
+                            Some(&new_member.name),
+                            None,
+                            Some(new_member.data_type.to_string()),
+                            Compatibility::from_directions(is_optional(&new_member.data_type), true),
+                        ));
+                    }
+                }
+            }
+            (Declaration::Enum(old_enum), Some(Declaration::Enum(new_enum))) => {
+                for old_member in &old_enum.members {
+                    if !new_enum.members.contains(old_member) {
+                        changes.push(change(ChangeKind::EnumMemberRemoved, Some(old_member), None, None, Compatibility::Forward));
+                    }
+                }
+                for new_member in &new_enum.members {
+                    if !old_enum.members.contains(new_member) {
+                        changes.push(change(ChangeKind::EnumMemberAdded, Some(new_member), None, None, Compatibility::Backward));
+                    }
+                }
+            }
+            (old_declaration, Some(new_declaration)) => changes.push(change(
+                ChangeKind::KindChanged,
+                None,
+                Some(kind_name(old_declaration).to_owned()),
+                Some(kind_name(new_declaration).to_owned()),
+                Compatibility::Breaking,
+            )),
+        }
+    }
+    for new_declaration in &new_schema.declarations {
+        if old_schema.declaration(new_declaration.name()).is_none() {
+            changes.push(Change {
+                kind: ChangeKind::TypeAdded,
+                type_name: new_declaration.name().to_owned(),
+                member: None,
+                old: None,
+                new: None,
+                compatibility: Compatibility::Full,
+            });
+        }
+    }
+
+    let compatibility = changes
+        .iter()
+        .fold(Compatibility::Full, |compatibility, change| compatibility.and(change.compatibility));
+    Ok(Report { compatibility, changes })
+}
+
+fn kind_name(declaration: &Declaration) -> &'static str {
+    match declaration {
+        Declaration::Struct(_) => "struct",
+        Declaration::Enum(_) => "enum",
+    }
+}
+
+fn is_optional(data_type: &DataType) -> bool {
+    matches!(data_type, DataType::Option(_))
+}
+
+/// The smallest and largest values of an integer type, or None for other types
+fn integer_range(data_type: &DataType) -> Option<(i128, i128)> {
+    match data_type {
+        DataType::U8 => Some((0, u8::MAX.into())),
+        DataType::U16 => Some((0, u16::MAX.into())),
+        DataType::U32 => Some((0, u32::MAX.into())),
+        DataType::U64 => Some((0, u64::MAX.into())),
+        DataType::I8 => Some((i8::MIN.into(), i8::MAX.into())),
+        DataType::I16 => Some((i16::MIN.into(), i16::MAX.into())),
+        DataType::I32 => Some((i32::MIN.into(), i32::MAX.into())),
+        DataType::I64 => Some((i64::MIN.into(), i64::MAX.into())),
+        _ => None,
+    }
+}
+
+/// Classifies a change of a member type. Integers are narrowed or widened within options and
+/// arrays too, e.g. from `array(u32)` to `array(u16)`
+fn member_change_kind(old: &DataType, new: &DataType) -> ChangeKind {
+    match (old, new) {
+        (DataType::Option(old_inner), new) if old_inner.as_ref() == new => ChangeKind::OptionalToRequired,
+        (old, DataType::Option(new_inner)) if new_inner.as_ref() == old => ChangeKind::RequiredToOptional,
+        (DataType::Option(old_inner), DataType::Option(new_inner))
+        | (DataType::Array(old_inner), DataType::Array(new_inner)) => match member_change_kind(old_inner, new_inner) {
+            kind @ (ChangeKind::IntegerNarrowed | ChangeKind::IntegerWidened) => kind,
+            _ => ChangeKind::MemberRetyped,
+        },
+        (old, new) => match type_compatibility(old, new) {
+            Compatibility::Forward if integer_range(old).is_some() => ChangeKind::IntegerNarrowed,
+            Compatibility::Backward if integer_range(old).is_some() => ChangeKind::IntegerWidened,
+            _ => ChangeKind::MemberRetyped,
+        },
+    }
+}
+
+/// The compatibility of a change of a member type. Changes are backward compatible if every old
+/// value is a valid new value, and forward compatible if every new value is a valid old value
+fn type_compatibility(old: &DataType, new: &DataType) -> Compatibility {
+    if old == new {
+        return Compatibility::Full;
+    }
+    if let (Some((old_min, old_max)), Some((new_min, new_max))) = (integer_range(old), integer_range(new)) {
+        return Compatibility::from_directions(
+            new_min <= old_min && new_max >= old_max,
+            old_min <= new_min && old_max >= new_max,
+        );
+    }
+    match (old, new) {
+        (DataType::F32, DataType::F64) => Compatibility::Backward,
+        (DataType::F64, DataType::F32) => Compatibility::Forward,
+        (DataType::Option(old_inner), DataType::Option(new_inner))
+        | (DataType::Array(old_inner), DataType::Array(new_inner)) => type_compatibility(old_inner, new_inner),
+        (DataType::Option(old_inner), new) => type_compatibility(old_inner, new).and(Compatibility::Forward),
+        (old, DataType::Option(new_inner)) => type_compatibility(old, new_inner).and(Compatibility::Backward),
+        _ => Compatibility::Breaking,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+
+    fn definition(text: &str) -> DataDefinition {
+        match parse_tokens(lex_tokens(text.to_owned()).expect("should lex")).expect("should parse") {
+            ASTNode::DataDefinition(definition) => definition,
+            _ => panic!("should parse a data definition"),
+        }
+    }
+
+    const OLD_TEXT: &str = "
+struct node {
+  id @1: u32, label @2: string, parent: option(node), depth: option(u8), weights: array(option(i64)),
+  ratio: f32, children: array(node), kind: enum kind { leaf, branch, }, tag: tag, hint: option(string),
+}
+struct tag { text: string }
+enum shape { round, square, }
+";
+
+    const NEW_TEXT: &str = "
+struct node {
+  id @2: u16, label @1: option(string), parent: node, depth: option(u16), weights: array(option(i32)),
+  ratio: f64, children: array(option(node)), kind: enum kind { leaf, root, }, tag: string, note: option(string),
+  size: u64,
+}
+struct shape { sides: u8 }
+enum color { red, blue, }
+";
+
+    #[test]
+    fn test_compare() {
+        let report = compare(&definition(OLD_TEXT), &definition(NEW_TEXT)).expect("should compare");
+        let changes = report.changes.iter().map(ToString::to_string).collect::<Vec<String>>();
+
+        assert_eq!(
+            changes,
+            [
+                "forward compatible: enum member 'kind.branch' was removed",
+                "backward compatible: enum member 'kind.root' was added",
+                "forward compatible: member 'node.id' was narrowed from u32 to u16",
+                "backward compatible: member 'node.label' was made optional, from string to option(string)",
+                "forward compatible: member 'node.parent' was made required, from option(node) to node",
+                "backward compatible: member 'node.depth' was widened from option(u8) to option(u16)",
+                "forward compatible: member 'node.weights' was narrowed from array(option(i64)) to array(option(i32))",
+                "backward compatible: member 'node.ratio' changed type from f32 to f64",
+                "backward compatible: member 'node.children' changed type from array(node) to array(option(node))",
+                "breaking: member 'node.tag' changed type from tag to string",
+                "fully compatible: member 'node.hint' of type option(string) was removed",
+                "fully compatible: member 'node.note' of type option(string) was added",
+                "forward compatible: member 'node.size' of type u64 was added",
+                "breaking: type 'tag' was removed",
+                "breaking: type 'shape' changed from enum to struct",
+                "fully compatible: type 'color' was added",
+            ]
+        );
+        assert_eq!(report.compatibility, Compatibility::Breaking);
+    }
+
+    #[test]
+    fn test_compatibility_directions() {
+        let old = definition("struct person { id: u8, role: enum role { admin, user, } }");
+        let new = definition("struct person { id: u16, role: enum role { admin, user, guest, }, email: option(string) }");
+
+        let report = compare(&old, &new).expect("should compare");
+        assert_eq!(report.compatibility, Compatibility::Backward);
+        assert_eq!(
+            serde_json::to_value(&report.changes[0]).expect("should serialize"),
+            serde_json::json!({
+                "kind": "enum_member_added",
+                "type": "role",
+                "member": "guest",
+                "compatibility": "backward",
+            })
+        );
+
+        let report = compare(&new, &old).expect("should compare");
+        assert_eq!(report.compatibility, Compatibility::Forward);
+        assert_eq!(compare(&old, &old).expect("should compare").changes, []);
+    }
+}
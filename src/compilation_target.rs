@@ -1,27 +1,157 @@
 use crate::parser::ASTNode;
+use crate::schema::DeclarationKind;
+use crate::target_registry::TargetRegistry;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
+use std::rc::Rc;
 use std::str::FromStr;
-use crate::cxx::CXXGenerator;
-use crate::ts_mobx::TSMobXGenerator;
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CompilationInfo {
     pub source_file_name: String,
     pub preamble_comments: Vec<String>,
+    pub options: TargetOptions,
+    /// User defined types referenced by this file, but declared in another file of the batch
+    #[serde(default)]
+    pub external_types: Vec<ExternalType>,
+}
+
+/// A user defined type declared in another file compiled in the same batch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalType {
+    pub name: String,
+    /// The file stem of the declaring file, which is also the `{name}` of its outputs. Targets
+    /// import the type from their output for this module
+    pub module: String,
+    #[serde(default)]
+    pub kind: DeclarationKind,
+}
+
+impl CompilationInfo {
+    /// The modules external types are declared in, each listed once, along with the names of the
+    /// types used from them
+    pub fn external_modules(&self) -> BTreeMap<&str, Vec<&str>> {
+        let mut modules: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for external_type in &self.external_types {
+            modules
+                .entry(external_type.module.as_str())
+                .or_default()
+                .push(external_type.name.as_str());
+        }
+        modules
+    }
+}
+
+/// Options for a single target, such as the C++ namespace. Targets ignore options they do not use
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "BTreeMap<String, OptionValue>")]
+pub struct TargetOptions(BTreeMap<String, String>);
+
+/// Option values as written in a configuration file. They are stored as strings, the same as
+/// options given on the command line
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OptionValue {
+    String(String),
+    Bool(bool),
+    Integer(i64),
+}
+
+impl From<BTreeMap<String, OptionValue>> for TargetOptions {
+    fn from(options: BTreeMap<String, OptionValue>) -> Self {
+        Self(
+            options
+                .into_iter()
+                .map(|(key, value)| {
+                    let value = match value {
+                        OptionValue::String(value) => value,
+                        OptionValue::Bool(value) => value.to_string(),
+                        OptionValue::Integer(value) => value.to_string(),
+                    };
+                    (key, value)
+                })
+                .collect(),
+        )
+    }
+}
+
+impl TargetOptions {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.0.insert(key.to_owned(), value.to_owned());
+    }
+
+    /// Gets an option which must be one of a fixed set of values
+    /// # Returns
+    /// The value of the option, `default` if it is not set, or CompilationError::InvalidOption if
+    /// it is set to a value not in `choices`
+    pub fn get_choice<'a>(&'a self, key: &str, choices: &[&'a str], default: &'a str) -> Result<&'a str, CompilationError> {
+        match self.get(key) {
+            None => Ok(default),
+            Some(value) if choices.contains(&value) => Ok(value),
+            Some(value) => Err(CompilationError::InvalidOption(format!(
+                "unknown {} '{}', expected one of {}",
+                key,
+                value,
+                choices.join(", ")
+            ))),
+        }
+    }
+
+    /// Sets every option of `other`, replacing the value of any option set in both
+    pub fn merge(&mut self, other: &TargetOptions) {
+        for (key, value) in &other.0 {
+            self.set(key, value);
+        }
+    }
 }
 
 pub trait CompilationTarget {
     fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError>;
+
+    /// Warnings about the generated code which do not prevent generating it, such as a type being
+    /// represented less precisely than declared. Targets without warnings need not implement this
+    fn warnings(&self, _ast: &ASTNode, _compilation_info: &CompilationInfo) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Files which the generated code imports, the same for every file of a batch, so written once
+    /// beside the outputs. Targets without them need not implement this
+    fn support_files(&self, _compilation_info: &CompilationInfo) -> Vec<SupportFile> {
+        Vec::new()
+    }
+}
+
+/// A file imported by generated code, named relative to the directory of the output importing it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SupportFile {
+    pub name: String,
+    pub contents: String,
 }
 
+/// A compilation target resolved from a name or an output file extension.
+/// Targets are shared with the `TargetRegistry` they were resolved from
+#[derive(Clone)]
 pub struct Target {
-    target: Box<dyn CompilationTarget>,
+    name: String,
+    extension: Option<String>,
+    target: Rc<dyn CompilationTarget>,
 }
 
 #[derive(Debug)]
 pub enum CompilationError {
     UnknownTarget(String),
     InvalidAST,
+    ExternalTarget(String),
+    Template(String),
+    InvalidOption(String),
+    InvalidFieldNumber(String),
+    UnsupportedType(String),
 }
 
 impl Display for CompilationError {
@@ -30,29 +160,57 @@ impl Display for CompilationError {
             CompilationError::UnknownTarget(name) => {
                 write!(f, "Unknown compilation target: {}", name)
             },
-            Self::InvalidAST => write!(f, "Invalid AST to generator")
+            Self::InvalidAST => write!(f, "Invalid AST to generator"),
+            Self::ExternalTarget(message) => write!(f, "External compilation target failed: {}", message),
+            Self::Template(message) => write!(f, "Template compilation target failed: {}", message),
+            Self::InvalidOption(message) => write!(f, "Invalid target option: {}", message),
+            Self::InvalidFieldNumber(message) => write!(f, "Invalid field number: {}", message),
+            Self::UnsupportedType(message) => write!(f, "Type not supported by the target: {}", message),
         }
     }
 }
 
 impl Error for CompilationError {}
 
+/// Resolves one of the built in targets, or a template target given as `template:<path>`. Use a
+/// `TargetRegistry` to also resolve targets registered by other crates or loaded at runtime
 impl FromStr for Target {
     type Err = CompilationError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "cxx" | "cpp" | "c++" | "h" => Ok(Self { target: Box::new(CXXGenerator {}) }),
-            "ts-mobx" | "typescript-mobx" | "ts" => Ok(Self { target: Box::new(TSMobXGenerator {}) }),
-            unknown_target => Err(CompilationError::UnknownTarget(
-                unknown_target.to_owned(),
-            )),
-        }
+        TargetRegistry::default().target(s)
     }
 }
 
 impl Target {
+    pub(crate) fn new(name: &str, extension: Option<&str>, target: Rc<dyn CompilationTarget>) -> Self {
+        Self {
+            name: name.to_owned(),
+            extension: extension.map(str::to_owned),
+            target,
+        }
+    }
+
+    /// The name the target was registered under first. Targets resolved through any of their
+    /// names or extensions share this name, so it is used to key per target options
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The extension the target was registered with first, used to name its output files
+    pub fn extension(&self) -> Option<&str> {
+        self.extension.as_deref()
+    }
+
     pub fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
         self.target.generate_code(ast, compilation_info)
     }
+
+    pub fn warnings(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Vec<String> {
+        self.target.warnings(ast, compilation_info)
+    }
+
+    pub fn support_files(&self, compilation_info: &CompilationInfo) -> Vec<SupportFile> {
+        self.target.support_files(compilation_info)
+    }
 }
@@ -0,0 +1,228 @@
+/// Generation for C#
+///
+/// Structs become sealed records (or classes) with `init` properties, named in `PascalCase` and
+/// mapped to their original names with `System.Text.Json` attributes. `Option` members are
+/// nullable, every other member is `required`, and arrays become `List<T>`. Enums are serialized
+/// as strings, keeping the names they are declared with. A property named like its record gets a
+/// trailing `_`, as C# does not allow members to be named like their enclosing type. A nullable
+/// type cannot be nullable again, so options of options are not supported.
+///
+/// Supported options:
+/// - `style`: `record` (the default) or `class`, generating classes with `set` properties
+/// - `namespace`: the file scoped namespace the types are declared in, none by default
+use crate::compilation_target::{CompilationError, CompilationInfo, CompilationTarget};
+use crate::naming::pascal_case;
+use crate::parser::{ASTNode, DataType};
+use crate::schema::{Declaration, EnumDefinition, Schema, StructDefinition};
+
+/// Keywords which cannot name an enum member unless escaped with `@`. Types and properties are in
+/// `PascalCase`, so never clash with a keyword
+const KEYWORDS: [&str; 77] = [
+    "abstract", "as", "base", "bool", "break", "byte", "case", "catch", "char", "checked", "class", "const",
+    "continue", "decimal", "default", "delegate", "do", "double", "else", "enum", "event", "explicit", "extern",
+    "false", "finally", "fixed", "float", "for", "foreach", "goto", "if", "implicit", "in", "int", "interface",
+    "internal", "is", "lock", "long", "namespace", "new", "null", "object", "operator", "out", "override", "params",
+    "private", "protected", "public", "readonly", "ref", "return", "sbyte", "sealed", "short", "sizeof",
+    "stackalloc", "static", "string", "struct", "switch", "this", "throw", "true", "try", "typeof", "uint", "ulong",
+    "unchecked", "unsafe", "ushort", "using", "virtual", "void", "volatile", "while",
+];
+
+pub struct CSharpGenerator;
+
+impl CompilationTarget for CSharpGenerator {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        let style = compilation_info.options.get_choice("style", &["record", "class"], "record")?;
+        let schema = Schema::from_ast(ast)?;
+
+        let namespace = compilation_info
+            .options
+            .get("namespace")
+            .map(|namespace| format!("\nnamespace {};\n", namespace))
+            .unwrap_or_default();
+        let declarations = schema
+            .declarations
+            .iter()
+            .map(|declaration| match declaration {
+                Declaration::Struct(struct_definition) => generate_type(struct_definition, style),
+                Declaration::Enum(enum_definition) => Ok(generate_enum(enum_definition)),
+            })
+            .map(|declaration| Ok(format!("\n{}", declaration?)))
+            .collect::<Result<String, CompilationError>>()?;
+
+        Ok(format!(
+            "{}\n#nullable enable\n\nusing System.Collections.Generic;\nusing System.Text.Json.Serialization;\n{}{}",
+            generate_preamble(compilation_info),
+            namespace,
+            declarations
+        ))
+    }
+}
+
+fn generate_preamble(compilation_info: &CompilationInfo) -> String {
+    compilation_info.preamble_comments.iter()
+        .map(|preamble| format!("// {}", preamble))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn generate_type(struct_definition: &StructDefinition, style: &str) -> Result<String, CompilationError> {
+    let accessors = match style {
+        "class" => "get; set;",
+        _ => "get; init;",
+    };
+    let type_name = pascal_case(&struct_definition.name);
+    let properties = struct_definition
+        .members
+        .iter()
+        .map(|member| {
+            let modifier = match member.data_type {
+                DataType::Option(_) => "",
+                _ => "required ",
+            };
+            let mut property_name = pascal_case(&member.name);
+            if property_name == type_name {
+                property_name.push('_');
+            }
+            Ok(format!(
+                "    [JsonPropertyName(\"{}\")]\n    public {}{} {} {{ {} }}\n",
+                member.name,
+                modifier,
+                generate_type_name(&member.name, &member.data_type)?,
+                property_name,
+                accessors
+            ))
+        })
+        .collect::<Result<Vec<String>, CompilationError>>()?
+        .join("\n");
+    Ok(format!("public sealed {} {}\n{{\n{}}}\n", style, type_name, properties))
+}
+
+fn generate_enum(enum_definition: &EnumDefinition) -> String {
+    let members = enum_definition
+        .members
+        .iter()
+        .map(|member| format!("    {},\n", escape_keyword(member)))
+        .collect::<String>();
+    format!(
+        "[JsonConverter(typeof(JsonStringEnumConverter))]\npublic enum {}\n{{\n{}}}\n",
+        pascal_case(&enum_definition.name),
+        members
+    )
+}
+
+fn escape_keyword(name: &str) -> String {
+    if KEYWORDS.contains(&name) {
+        format!("@{}", name)
+    } else {
+        name.to_owned()
+    }
+}
+
+fn generate_type_name(member_name: &str, data_type: &DataType) -> Result<String, CompilationError> {
+    Ok(match data_type {
+        DataType::U8 => "byte".to_owned(),
+        DataType::I8 => "sbyte".to_owned(),
+        DataType::U16 => "ushort".to_owned(),
+        DataType::I16 => "short".to_owned(),
+        DataType::U32 => "uint".to_owned(),
+        DataType::I32 => "int".to_owned(),
+        DataType::U64 => "ulong".to_owned(),
+        DataType::I64 => "long".to_owned(),
+        DataType::F32 => "float".to_owned(),
+        DataType::F64 => "double".to_owned(),
+        DataType::Char => "char".to_owned(),
+        DataType::String => "string".to_owned(),
+        DataType::Bool => "bool".to_owned(),
+        DataType::Option(inner_type) => {
+            if let DataType::Option(_) = inner_type.as_ref() {
+                return Err(CompilationError::UnsupportedType(format!(
+                    "'{}' is an option of an option, which C# nullable types cannot nest",
+                    member_name
+                )));
+            }
+            format!("{}?", generate_type_name(member_name, inner_type)?)
+        }
+        DataType::Array(inner_type) => format!("List<{}>", generate_type_name(member_name, inner_type)?),
+        DataType::UserDefined(name) => pascal_case(name),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+
+    // A property may be named like its type, but not like its record, and nullable types may nest
+    // in lists but not in each other
+    const TEXT: &str = "struct node { id: u64, node: string, labels: array(option(string)), kind: enum kind { leaf, branch, }, parent: option(node), children: array(node) }";
+
+    const GENERATED_CODE: &str = "
+#nullable enable
+
+using System.Collections.Generic;
+using System.Text.Json.Serialization;
+
+namespace Acme.Api;
+
+[JsonConverter(typeof(JsonStringEnumConverter))]
+public enum Kind
+{
+    leaf,
+    branch,
+}
+
+public sealed record Node
+{
+    [JsonPropertyName(\"id\")]
+    public required ulong Id { get; init; }
+
+    [JsonPropertyName(\"node\")]
+    public required string Node_ { get; init; }
+
+    [JsonPropertyName(\"labels\")]
+    public required List<string?> Labels { get; init; }
+
+    [JsonPropertyName(\"kind\")]
+    public required Kind Kind { get; init; }
+
+    [JsonPropertyName(\"parent\")]
+    public Node? Parent { get; init; }
+
+    [JsonPropertyName(\"children\")]
+    public required List<Node> Children { get; init; }
+}
+";
+
+    #[test]
+    fn test_generate_csharp() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+        let mut compilation_info = CompilationInfo::default();
+        compilation_info.options.set("namespace", "Acme.Api");
+
+        assert_eq!(
+            CSharpGenerator
+                .generate_code(&ast, &compilation_info)
+                .expect("should generate code"),
+            GENERATED_CODE
+        );
+
+        let ast = parse_tokens(lex_tokens("struct node { maybe: option(option(u8)) }".to_owned()).expect("should lex"))
+            .expect("should parse");
+        assert!(matches!(
+            CSharpGenerator.generate_code(&ast, &compilation_info),
+            Err(CompilationError::UnsupportedType(_))
+        ));
+    }
+
+    #[test]
+    fn test_escape_keywords() {
+        let text = "enum kind { class, event, record, }";
+        let ast = parse_tokens(lex_tokens(text.to_owned()).expect("should lex")).expect("should parse");
+
+        assert!(CSharpGenerator
+            .generate_code(&ast, &CompilationInfo::default())
+            .expect("should generate code")
+            .contains("public enum Kind\n{\n    @class,\n    @event,\n    record,\n}\n"));
+    }
+}
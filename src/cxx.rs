@@ -1,10 +1,22 @@
 /// Code generation for C++
-/// Includes AST transformation, which effectively just pulls inline struct and enum declarations
-/// out of line
-use crate::parser::{
-    ASTNode, DataDefinition, DataType, NamedStatementList, StructMemberDeclaration,
-};
+/// The AST is first transformed to pull inline struct and enum declarations out of line, as C++
+/// does not allow a member's type to be declared inline
+///
+/// With the `json` option set to `nlohmann`, `to_json` and `from_json` functions are generated
+/// for every struct and enum, which `nlohmann::json` finds by argument dependent lookup. Enums are
+/// written as the names of their members. An absent `std::optional` member is left out when
+/// writing, and may be either missing or `null` when reading. Chars are written as strings of one
+/// character, as in the other targets. JSON which does not match the schema throws a
+/// `fruko_json::error`, holding the JSON pointer of the mismatched value, e.g.
+/// `/manager/name: missing required member`
+///
+/// With the `wire` option set to `true`, `encode` and `decode` functions are generated for every
+/// struct and enum, converting to and from the compact binary wire format described in
+/// `crate::wire`. A payload which is not a valid encoding throws a `fruko_wire::error`
+use crate::parser::{ASTNode, DataType};
 use crate::compilation_target::{CompilationError, CompilationInfo, CompilationTarget};
+use crate::hoisting::hoist_inline_types;
+use crate::schema::{Declaration, EnumDefinition, Schema, StructDefinition};
 use std::borrow::Borrow;
 
 pub struct CXXGenerator;
@@ -18,11 +30,78 @@ impl CompilationTarget for CXXGenerator {
 /// Main C++ generation function. Takes an AST, and returns the generated code
 /// # Parameters
 /// ast - The abstract syntax tree of which to generate the code. It is assumed to be a valid data definition AST
+/// compilation_info - Supported options are `namespace`, wrapping the declarations in a namespace,
+/// and `header_guard`, being one of `none` (the default), `pragma` or `ifndef`. The macro used by
+/// an `ifndef` guard is derived from the source file name, unless set with `header_guard_name`.
+/// `json` is one of `none` (the default) or `nlohmann`, generating JSON serialization functions,
+/// and `wire` is `false` (the default) or `true`, generating binary encoding functions
 /// # Return
 /// returns the generated C++ code
 fn generate_code(ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
-    let new_ast = CXXASTTransformer::transform_ast(ast)?;
-    Ok(format!("{}\n{}\n{}", generate_preamble(compilation_info), generate_includes(), generate(&new_ast)))
+    let json = compilation_info.options.get_choice("json", &["none", "nlohmann"], "none")? == "nlohmann";
+    let wire = compilation_info.options.get_choice("wire", &["false", "true"], "false")? == "true";
+    let new_ast = hoist_inline_types(ast)?;
+    let mut body = generate(&new_ast);
+    let mut support = String::new();
+    if json {
+        body += &generate_json_functions(&Schema::from_ast(ast)?);
+        support += JSON_SUPPORT;
+    }
+    if wire {
+        body += &generate_wire_functions(&Schema::from_ast(ast)?);
+        support += WIRE_SUPPORT;
+    }
+    let declarations = match compilation_info.options.get("namespace") {
+        Some(namespace) => format!("namespace {} {{ {} }}", namespace, body),
+        None => body,
+    };
+    let (guard_start, guard_end) = generate_header_guard(compilation_info)?;
+    Ok(format!("{}\n{}{}{}{}\n{}{}", generate_preamble(compilation_info), guard_start, generate_includes(), generate_external_includes(compilation_info), support, declarations, guard_end))
+}
+
+/// Includes the headers generated for other files of the batch which declare types used here
+fn generate_external_includes(compilation_info: &CompilationInfo) -> String {
+    compilation_info
+        .external_modules()
+        .keys()
+        .map(|module| format!("\n#include \"{}.h\"", module))
+        .collect()
+}
+
+/// Generates the lines opening and closing the header guard selected by the `header_guard` option
+fn generate_header_guard(compilation_info: &CompilationInfo) -> Result<(String, String), CompilationError> {
+    match compilation_info.options.get("header_guard") {
+        None | Some("none") => Ok((String::new(), String::new())),
+        Some("pragma") => Ok(("#pragma once\n".to_owned(), String::new())),
+        Some("ifndef") => {
+            let guard_name = match compilation_info.options.get("header_guard_name") {
+                Some(name) => name.to_owned(),
+                None => header_guard_name(&compilation_info.source_file_name),
+            };
+            Ok((
+                format!("#ifndef {}\n#define {}\n", guard_name, guard_name),
+                format!("\n#endif // {}", guard_name),
+            ))
+        }
+        Some(style) => Err(CompilationError::InvalidOption(format!(
+            "unknown header_guard style '{}', expected none, pragma or ifndef",
+            style
+        ))),
+    }
+}
+
+/// Derives a header guard macro from the source file name, e.g. `schema/user.fruko` becomes
+/// `USER_FRUKO_H`
+pub(crate) fn header_guard_name(source_file_name: &str) -> String {
+    let file_name = std::path::Path::new(source_file_name)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let guard_name: String = file_name
+        .chars()
+        .map(|char| if char.is_ascii_alphanumeric() { char.to_ascii_uppercase() } else { '_' })
+        .collect();
+    format!("{}_H", guard_name)
 }
 
 fn generate_includes() -> String {
@@ -33,81 +112,6 @@ fn generate_includes() -> String {
         .join("\n")
 }
 
-/// Helper struct, made to just keep the transformed AST in memory whilst the function recursively
-/// transforms it
-struct CXXASTTransformer {
-    new_ast: DataDefinition,
-}
-
-impl CXXASTTransformer {
-    /// Main interface for the CXXASTTransformer
-    fn transform_ast(ast: &ASTNode) -> Result<ASTNode, CompilationError> {
-        let mut transformer = Self {
-            new_ast: DataDefinition::default(),
-        };
-        transformer.transform_ast_impl(ast)?;
-        Ok(ASTNode::DataDefinition(transformer.new_ast))
-    }
-
-    /// Does the actual transformation. The transformation is only taking inline struct and enum
-    /// declarations out of line, the AST is otherwise untouched
-    fn transform_ast_impl(&mut self, ast: &ASTNode) -> Result<(), CompilationError> {
-        match ast {
-            ASTNode::StructDeclaration(struct_declaration) => {
-                let mut pushed_struct = NamedStatementList::new(struct_declaration.name.clone());
-                for member in &struct_declaration.child_nodes {
-                    if let ASTNode::StructMemberDeclaration(member_declaration) = member {
-                        match member_declaration.data_type.borrow() {
-                            ASTNode::StructDeclaration(inline_struct_declaration) => {
-                                self.transform_ast_impl(member_declaration.data_type.borrow())?;
-                                let new_member = StructMemberDeclaration {
-                                    name: member_declaration.name.clone(),
-                                    data_type: Box::new(ASTNode::TypeLiteral(
-                                        DataType::UserDefined(
-                                            inline_struct_declaration.name.clone(),
-                                        ),
-                                    )),
-                                };
-                                pushed_struct
-                                    .child_nodes
-                                    .push(ASTNode::StructMemberDeclaration(new_member));
-                            }
-                            ASTNode::EnumDeclaration(inline_enum_declaration) => {
-                                self.transform_ast_impl(member_declaration.data_type.borrow())?;
-                                let new_member = StructMemberDeclaration {
-                                    name: member_declaration.name.clone(),
-                                    data_type: Box::new(ASTNode::TypeLiteral(
-                                        DataType::UserDefined(inline_enum_declaration.name.clone()),
-                                    )),
-                                };
-                                pushed_struct
-                                    .child_nodes
-                                    .push(ASTNode::StructMemberDeclaration(new_member));
-                            }
-                            _ => pushed_struct.child_nodes.push(member.clone()),
-                        }
-                    }
-                }
-                self.new_ast
-                    .child_nodes
-                    .push(ASTNode::StructDeclaration(pushed_struct));
-            }
-            ASTNode::EnumDeclaration(enum_declaration) => {
-                self.new_ast
-                    .child_nodes
-                    .push(ASTNode::EnumDeclaration(enum_declaration.clone()));
-            }
-            ASTNode::DataDefinition(data) => {
-                for child in &data.child_nodes {
-                    self.transform_ast_impl(child)?;
-                }
-            }
-            _ => return Err(CompilationError::InvalidAST)
-        }
-        Ok(())
-    }
-}
-
 fn generate_preamble(compilation_info: &CompilationInfo) -> String {
     compilation_info.preamble_comments.iter()
         .map(|preamble| format!("// {}", preamble))
@@ -129,7 +133,7 @@ fn generate(ast: &ASTNode) -> String {
             let body = enum_declaration
                 .child_nodes
                 .iter()
-                .map(|node| generate(node))
+                .map(generate)
                 .collect::<Vec<String>>() // Iterator::intersperse is unstable
                 .join(",");
             format!("enum class {} {{ {} }};", enum_declaration.name, body)
@@ -175,9 +179,546 @@ fn generate_type_name(data_type: &DataType) -> String {
     }
 }
 
+/// Support for the generated JSON functions, shared by every generated header, so guarded by a
+/// macro. `to_value` and `from_value` convert members, handling the options, arrays and chars
+/// which `nlohmann::json` does not convert as the other targets do, and checking the types and
+/// ranges of values
+const JSON_SUPPORT: &str = r#"
+#include <limits>
+#include <stdexcept>
+#include <type_traits>
+#include <nlohmann/json.hpp>
+
+#ifndef FRUKO_JSON_SUPPORT
+#define FRUKO_JSON_SUPPORT
+namespace fruko_json {
+
+/// Thrown when JSON does not match the schema. `path` is the JSON pointer of the mismatched value
+struct error : std::runtime_error {
+    std::string path;
+    std::string reason;
+
+    error(const std::string& path, const std::string& reason)
+        : std::runtime_error(path.empty() ? reason : path + ": " + reason), path(path), reason(reason) {}
+};
+
+template <typename T> nlohmann::json to_value(const T& value);
+inline nlohmann::json to_value(char value);
+template <typename T> nlohmann::json to_value(const std::optional<T>& value);
+template <typename T> nlohmann::json to_value(const std::vector<T>& value);
+template <typename T> void from_value(const nlohmann::json& json, T& value);
+inline void from_value(const nlohmann::json& json, char& value);
+template <typename T> void from_value(const nlohmann::json& json, std::optional<T>& value);
+template <typename T> void from_value(const nlohmann::json& json, std::vector<T>& value);
+
+template <typename T> nlohmann::json to_value(const T& value) {
+    return value;
+}
+
+inline nlohmann::json to_value(char value) {
+    return std::string(1, value);
+}
+
+template <typename T> nlohmann::json to_value(const std::optional<T>& value) {
+    return value ? to_value(*value) : nlohmann::json(nullptr);
+}
+
+template <typename T> nlohmann::json to_value(const std::vector<T>& value) {
+    nlohmann::json array = nlohmann::json::array();
+    for (const T& element : value) {
+        array.push_back(to_value(element));
+    }
+    return array;
+}
+
+/// The JSON pointer token of a key, with `~` and `/` escaped as RFC 6901 requires
+inline std::string pointer_token(const std::string& key) {
+    std::string token = "/";
+    for (char character : key) {
+        if (character == '~') {
+            token += "~0";
+        } else if (character == '/') {
+            token += "~1";
+        } else {
+            token += character;
+        }
+    }
+    return token;
+}
+
+/// Reads a value, prefixing the path of any error with `key`
+template <typename T> void from_value_at(const nlohmann::json& json, const std::string& key, T& value) {
+    try {
+        from_value(json, value);
+    } catch (const error& nested_error) {
+        throw error(pointer_token(key) + nested_error.path, nested_error.reason);
+    }
+}
+
+template <typename T> void from_value(const nlohmann::json& json, T& value) {
+    if constexpr (std::is_same_v<T, bool>) {
+        if (!json.is_boolean()) throw error("", "expected a boolean");
+        value = json.get<bool>();
+    } else if constexpr (std::is_integral_v<T>) {
+        bool in_range;
+        if (json.is_number_unsigned()) {
+            in_range = json.get<std::uint64_t>() <= static_cast<std::uint64_t>(std::numeric_limits<T>::max());
+        } else if (json.is_number_integer()) {
+            auto number = json.get<std::int64_t>();
+            in_range = number >= 0 ? static_cast<std::uint64_t>(number) <= static_cast<std::uint64_t>(std::numeric_limits<T>::max())
+                                   : std::is_signed_v<T> && number >= static_cast<std::int64_t>(std::numeric_limits<T>::min());
+        } else {
+            throw error("", "expected an integer");
+        }
+        if (!in_range) throw error("", "integer out of range");
+        value = json.get<T>();
+    } else if constexpr (std::is_floating_point_v<T>) {
+        if (!json.is_number()) throw error("", "expected a number");
+        value = json.get<T>();
+    } else if constexpr (std::is_same_v<T, std::string>) {
+        if (!json.is_string()) throw error("", "expected a string");
+        value = json.get<std::string>();
+    } else {
+        json.get_to(value);
+    }
+}
+
+inline void from_value(const nlohmann::json& json, char& value) {
+    if (!json.is_string() || json.get_ref<const std::string&>().size() != 1) throw error("", "expected a string of one character");
+    value = json.get_ref<const std::string&>()[0];
+}
+
+template <typename T> void from_value(const nlohmann::json& json, std::optional<T>& value) {
+    if (json.is_null()) {
+        value.reset();
+        return;
+    }
+    T inner;
+    from_value(json, inner);
+    value = std::move(inner);
+}
+
+template <typename T> void from_value(const nlohmann::json& json, std::vector<T>& value) {
+    if (!json.is_array()) throw error("", "expected an array");
+    value.clear();
+    for (std::size_t index = 0; index < json.size(); index++) {
+        T element;
+        from_value_at(json[index], std::to_string(index), element);
+        value.push_back(std::move(element));
+    }
+}
+
+template <typename T> void read_required(const nlohmann::json& json, const char* key, T& value) {
+    auto member = json.find(key);
+    if (member == json.end()) throw error(pointer_token(key), "missing required member");
+    from_value_at(*member, key, value);
+}
+
+template <typename T> void read_optional(const nlohmann::json& json, const char* key, std::optional<T>& value) {
+    auto member = json.find(key);
+    if (member == json.end()) {
+        value.reset();
+        return;
+    }
+    from_value_at(*member, key, value);
+}
+
+}
+#endif
+"#;
+
+/// Generates the `to_json` and `from_json` functions of every declaration. Every function is
+/// declared before any is defined, so that declarations may refer to each other in any order
+fn generate_json_functions(schema: &Schema) -> String {
+    let prototypes = schema
+        .declarations
+        .iter()
+        .map(|declaration| {
+            format!(
+                "inline void to_json(nlohmann::json& j, const {name}& value);\ninline void from_json(const nlohmann::json& j, {name}& value);\n",
+                name = declaration.name()
+            )
+        })
+        .collect::<String>();
+    let definitions = schema
+        .declarations
+        .iter()
+        .map(|declaration| match declaration {
+            Declaration::Struct(struct_definition) => generate_struct_json_functions(struct_definition),
+            Declaration::Enum(enum_definition) => generate_enum_json_functions(enum_definition),
+        })
+        .collect::<String>();
+    format!("\n{}\n{}", prototypes, definitions)
+}
+
+fn generate_struct_json_functions(struct_definition: &StructDefinition) -> String {
+    let writes = struct_definition
+        .members
+        .iter()
+        .map(|member| match member.data_type {
+            DataType::Option(_) => format!(
+                "    if (value.{name}) j[\"{name}\"] = fruko_json::to_value(*value.{name});\n",
+                name = member.name
+            ),
+            _ => format!("    j[\"{name}\"] = fruko_json::to_value(value.{name});\n", name = member.name),
+        })
+        .collect::<String>();
+    let reads = struct_definition
+        .members
+        .iter()
+        .map(|member| match member.data_type {
+            DataType::Option(_) => format!("    fruko_json::read_optional(j, \"{name}\", value.{name});\n", name = member.name),
+            _ => format!("    fruko_json::read_required(j, \"{name}\", value.{name});\n", name = member.name),
+        })
+        .collect::<String>();
+    // An empty struct does not otherwise use its value
+    let unused = if struct_definition.members.is_empty() { "    (void)value;\n" } else { "" };
+
+    format!(
+        "inline void to_json(nlohmann::json& j, const {name}& value) {{\n    j = nlohmann::json::object();\n{unused}{writes}}}\n\
+         inline void from_json(const nlohmann::json& j, {name}& value) {{\n    if (!j.is_object()) throw fruko_json::error(\"\", \"expected an object\");\n{unused}{reads}}}\n",
+        name = struct_definition.name,
+    )
+}
+
+fn generate_enum_json_functions(enum_definition: &EnumDefinition) -> String {
+    let name = &enum_definition.name;
+    let writes = enum_definition
+        .members
+        .iter()
+        .map(|member| format!("    case {name}::{member}: j = \"{member}\"; break;\n"))
+        .collect::<String>();
+    let reads = enum_definition
+        .members
+        .iter()
+        .map(|member| format!("if (j == \"{member}\") value = {name}::{member};\n    else "))
+        .collect::<String>();
+    let (writes, reads) = if enum_definition.members.is_empty() {
+        // An empty enum has no values to convert
+        (
+            "    (void)j;\n    (void)value;\n".to_owned(),
+            format!("(void)j;\n    (void)value;\n    throw fruko_json::error(\"\", \"'{name}' has no members\");\n"),
+        )
+    } else {
+        let expected = enum_definition
+            .members
+            .iter()
+            .map(|member| format!("'{}'", member))
+            .collect::<Vec<String>>()
+            .join(", ");
+        (
+            format!("    switch (value) {{\n{writes}    }}\n"),
+            format!("{reads}throw fruko_json::error(\"\", \"expected one of {expected}\");\n"),
+        )
+    };
+
+    format!(
+        "inline void to_json(nlohmann::json& j, const {name}& value) {{\n{writes}}}\n\
+         inline void from_json(const nlohmann::json& j, {name}& value) {{\n    {reads}}}\n"
+    )
+}
+
+/// Support for the generated wire format functions, shared by every generated header, so guarded
+/// by a macro. `write` and `read` convert values of every type, checking the ranges of the values
+/// read
+const WIRE_SUPPORT: &str = r#"
+#include <algorithm>
+#include <cstring>
+#include <limits>
+#include <stdexcept>
+#include <type_traits>
+
+#ifndef FRUKO_WIRE_SUPPORT
+#define FRUKO_WIRE_SUPPORT
+namespace fruko_wire {
+
+/// Thrown when a payload is not a valid encoding of its type, or a value cannot be encoded
+struct error : std::runtime_error {
+    using std::runtime_error::runtime_error;
+};
+
+struct writer {
+    std::vector<std::uint8_t> bytes;
+
+    void varint(std::uint64_t value) {
+        while (value >= 0x80) {
+            bytes.push_back(static_cast<std::uint8_t>(value | 0x80));
+            value >>= 7;
+        }
+        bytes.push_back(static_cast<std::uint8_t>(value));
+    }
+};
+
+/// The most options, arrays and structs a decoded value may have nested within each other
+constexpr std::size_t max_depth = 128;
+
+struct reader {
+    const std::vector<std::uint8_t>& bytes;
+    std::size_t offset = 0;
+    std::size_t depth = 0;
+
+    std::uint8_t byte() {
+        if (offset >= bytes.size()) throw error("the payload ends unexpectedly at offset " + std::to_string(offset));
+        return bytes[offset++];
+    }
+
+    std::uint64_t varint() {
+        std::size_t start = offset;
+        std::uint64_t value = 0;
+        for (unsigned shift = 0;; shift += 7) {
+            std::uint8_t next = byte();
+            // The tenth byte holds the highest bit only
+            if (shift == 63 && next > 1) throw error("the varint at offset " + std::to_string(start) + " is longer than 64 bits");
+            value |= static_cast<std::uint64_t>(next & 0x7f) << shift;
+            if (!(next & 0x80)) return value;
+        }
+    }
+
+    bool flag(const char* kind) {
+        std::size_t start = offset;
+        std::uint8_t next = byte();
+        if (next > 1) throw error(std::to_string(next) + " at offset " + std::to_string(start) + " is not a valid " + kind + " byte");
+        return next == 1;
+    }
+
+    std::size_t length() {
+        std::size_t start = offset;
+        std::uint64_t value = varint();
+        if (value > std::numeric_limits<std::uint32_t>::max()) throw error("the length at offset " + std::to_string(start) + " is out of range");
+        return static_cast<std::size_t>(value);
+    }
+};
+
+/// Counts an option, array or struct being read, which is nested one level deeper than its parent
+struct nested {
+    reader& r;
+
+    explicit nested(reader& r) : r(r) {
+        if (r.depth == max_depth) throw error("the value at offset " + std::to_string(r.offset) + " is nested more than " + std::to_string(max_depth) + " levels deep");
+        r.depth++;
+    }
+    nested(const nested&) = delete;
+    nested& operator=(const nested&) = delete;
+    ~nested() { r.depth--; }
+};
+
+/// Whether bytes are valid UTF-8, without overlong encodings or surrogates
+inline bool is_utf8(const std::uint8_t* data, std::size_t size) {
+    static const std::uint32_t minimum[] = {0, 0, 0x80, 0x800, 0x10000};
+    for (std::size_t index = 0; index < size;) {
+        std::uint8_t lead = data[index];
+        std::size_t length = lead < 0x80 ? 1 : (lead >> 5) == 0x6 ? 2 : (lead >> 4) == 0xe ? 3 : (lead >> 3) == 0x1e ? 4 : 0;
+        if (length == 0 || index + length > size) return false;
+        std::uint32_t scalar = length == 1 ? lead : lead & (0x7f >> length);
+        for (std::size_t continuation = 1; continuation < length; continuation++) {
+            if ((data[index + continuation] & 0xc0) != 0x80) return false;
+            scalar = scalar << 6 | (data[index + continuation] & 0x3f);
+        }
+        if (scalar < minimum[length] || (scalar >= 0xd800 && scalar <= 0xdfff) || scalar > 0x10ffff) return false;
+        index += length;
+    }
+    return true;
+}
+
+template <typename T> void write(writer& w, const T& value);
+inline void write(writer& w, char value);
+template <typename T> void write(writer& w, const std::optional<T>& value);
+template <typename T> void write(writer& w, const std::vector<T>& value);
+template <typename T> void read(reader& r, T& value);
+inline void read(reader& r, char& value);
+template <typename T> void read(reader& r, std::optional<T>& value);
+template <typename T> void read(reader& r, std::vector<T>& value);
+
+template <typename T> void write(writer& w, const T& value) {
+    if constexpr (std::is_same_v<T, bool>) {
+        w.bytes.push_back(value ? 1 : 0);
+    } else if constexpr (std::is_integral_v<T> && std::is_unsigned_v<T>) {
+        w.varint(value);
+    } else if constexpr (std::is_integral_v<T>) {
+        auto wide = static_cast<std::int64_t>(value);
+        w.varint((static_cast<std::uint64_t>(wide) << 1) ^ static_cast<std::uint64_t>(wide >> 63));
+    } else if constexpr (std::is_floating_point_v<T>) {
+        using bits_type = std::conditional_t<sizeof(T) == 4, std::uint32_t, std::uint64_t>;
+        bits_type bits;
+        std::memcpy(&bits, &value, sizeof(bits));
+        for (std::size_t index = 0; index < sizeof(bits); index++) {
+            w.bytes.push_back(static_cast<std::uint8_t>(bits >> (8 * index)));
+        }
+    } else if constexpr (std::is_same_v<T, std::string>) {
+        w.varint(value.size());
+        w.bytes.insert(w.bytes.end(), value.begin(), value.end());
+    } else {
+        wire_write(w, value);
+    }
+}
+
+inline void write(writer& w, char value) {
+    if (static_cast<unsigned char>(value) > 0x7f) throw error("a char must be an ASCII character");
+    w.varint(static_cast<unsigned char>(value));
+}
+
+template <typename T> void write(writer& w, const std::optional<T>& value) {
+    w.bytes.push_back(value ? 1 : 0);
+    if (value) write(w, *value);
+}
+
+template <typename T> void write(writer& w, const std::vector<T>& value) {
+    w.varint(value.size());
+    for (const T& element : value) {
+        write(w, element);
+    }
+}
+
+template <typename T> void read(reader& r, T& value) {
+    std::size_t start = r.offset;
+    auto out_of_range = [&](const std::string& number) {
+        return error(number + " at offset " + std::to_string(start) + " is out of range");
+    };
+    if constexpr (std::is_same_v<T, bool>) {
+        value = r.flag("bool");
+    } else if constexpr (std::is_integral_v<T> && std::is_unsigned_v<T>) {
+        std::uint64_t wide = r.varint();
+        if (wide > std::numeric_limits<T>::max()) throw out_of_range(std::to_string(wide));
+        value = static_cast<T>(wide);
+    } else if constexpr (std::is_integral_v<T>) {
+        std::uint64_t zigzag = r.varint();
+        auto wide = static_cast<std::int64_t>(zigzag >> 1) ^ -static_cast<std::int64_t>(zigzag & 1);
+        if (wide < std::numeric_limits<T>::min() || wide > std::numeric_limits<T>::max()) throw out_of_range(std::to_string(wide));
+        value = static_cast<T>(wide);
+    } else if constexpr (std::is_floating_point_v<T>) {
+        using bits_type = std::conditional_t<sizeof(T) == 4, std::uint32_t, std::uint64_t>;
+        bits_type bits = 0;
+        for (std::size_t index = 0; index < sizeof(bits); index++) {
+            bits |= static_cast<bits_type>(r.byte()) << (8 * index);
+        }
+        std::memcpy(&value, &bits, sizeof(bits));
+    } else if constexpr (std::is_same_v<T, std::string>) {
+        std::size_t length = r.length();
+        if (length > r.bytes.size() - r.offset) throw error("the payload ends unexpectedly at offset " + std::to_string(r.bytes.size()));
+        if (!is_utf8(r.bytes.data() + r.offset, length)) throw error("the string at offset " + std::to_string(r.offset) + " is not valid UTF-8");
+        value.assign(r.bytes.begin() + r.offset, r.bytes.begin() + r.offset + length);
+        r.offset += length;
+    } else {
+        wire_read(r, value);
+    }
+}
+
+inline void read(reader& r, char& value) {
+    std::size_t start = r.offset;
+    std::uint64_t scalar = r.varint();
+    if (scalar > 0x7f) throw error(std::to_string(scalar) + " at offset " + std::to_string(start) + " is not an ASCII character");
+    value = static_cast<char>(scalar);
+}
+
+template <typename T> void read(reader& r, std::optional<T>& value) {
+    nested guard{r};
+    if (!r.flag("presence")) {
+        value.reset();
+        return;
+    }
+    T inner;
+    read(r, inner);
+    value = std::move(inner);
+}
+
+template <typename T> void read(reader& r, std::vector<T>& value) {
+    nested guard{r};
+    std::size_t length = r.length();
+    value.clear();
+    value.reserve(std::min(length, r.bytes.size() - r.offset));
+    for (std::size_t index = 0; index < length; index++) {
+        T element;
+        read(r, element);
+        value.push_back(std::move(element));
+    }
+}
+
+}
+#endif
+"#;
+
+/// Generates the `encode` and `decode` functions of every declaration, and the `wire_write` and
+/// `wire_read` functions converting their values, which `fruko_wire` finds by argument dependent
+/// lookup
+fn generate_wire_functions(schema: &Schema) -> String {
+    let prototypes = schema
+        .declarations
+        .iter()
+        .map(|declaration| {
+            format!(
+                "inline void wire_write(fruko_wire::writer& w, const {name}& value);\ninline void wire_read(fruko_wire::reader& r, {name}& value);\n",
+                name = declaration.name()
+            )
+        })
+        .collect::<String>();
+    let definitions = schema
+        .declarations
+        .iter()
+        .map(|declaration| {
+            let functions = match declaration {
+                Declaration::Struct(struct_definition) => generate_struct_wire_functions(struct_definition),
+                Declaration::Enum(enum_definition) => generate_enum_wire_functions(enum_definition),
+            };
+            format!(
+                "{functions}inline std::vector<std::uint8_t> encode(const {name}& value) {{\n    fruko_wire::writer w;\n    wire_write(w, value);\n    return w.bytes;\n}}\n\
+                 inline void decode(const std::vector<std::uint8_t>& bytes, {name}& value) {{\n    fruko_wire::reader r{{bytes}};\n    wire_read(r, value);\n    \
+                 if (r.offset != bytes.size()) throw fruko_wire::error(\"the payload has unexpected bytes from offset \" + std::to_string(r.offset));\n}}\n",
+                name = declaration.name()
+            )
+        })
+        .collect::<String>();
+    format!("\n{}\n{}", prototypes, definitions)
+}
+
+fn generate_struct_wire_functions(struct_definition: &StructDefinition) -> String {
+    let writes = struct_definition
+        .members
+        .iter()
+        .map(|member| format!("    fruko_wire::write(w, value.{});\n", member.name))
+        .collect::<String>();
+    let reads = struct_definition
+        .members
+        .iter()
+        .map(|member| format!("    fruko_wire::read(r, value.{});\n", member.name))
+        .collect::<String>();
+    // An empty struct does not otherwise use its parameters
+    let (writes, reads) = if struct_definition.members.is_empty() {
+        ("    (void)w;\n    (void)value;\n".to_owned(), "    (void)value;\n".to_owned())
+    } else {
+        (writes, reads)
+    };
+
+    format!(
+        "inline void wire_write(fruko_wire::writer& w, const {name}& value) {{\n{writes}}}\n\
+         inline void wire_read(fruko_wire::reader& r, {name}& value) {{\n    fruko_wire::nested guard{{r}};\n{reads}}}\n",
+        name = struct_definition.name,
+    )
+}
+
+fn generate_enum_wire_functions(enum_definition: &EnumDefinition) -> String {
+    let name = &enum_definition.name;
+    let read = if enum_definition.members.is_empty() {
+        format!("    (void)r;\n    (void)value;\n    throw fruko_wire::error(\"'{name}' has no members\");\n")
+    } else {
+        format!(
+            "    std::size_t start = r.offset;\n    std::uint64_t index = r.varint();\n    \
+             if (index >= {count}) throw fruko_wire::error(std::to_string(index) + \" at offset \" + std::to_string(start) + \" is not a member index of '{name}'\");\n    \
+             value = static_cast<{name}>(index);\n",
+            count = enum_definition.members.len()
+        )
+    };
+
+    // Enum classes are numbered in declaration order, so the value is the member index
+    format!(
+        "inline void wire_write(fruko_wire::writer& w, const {name}& value) {{\n    w.varint(static_cast<std::uint64_t>(value));\n}}\n\
+         inline void wire_read(fruko_wire::reader& r, {name}& value) {{\n{read}}}\n"
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::{DataDefinition, NamedStatementList, StructMemberDeclaration};
 
     const GENERATED_OUTPUT: &str = "struct inner struct 2 {  };struct inner struct 1 { inner struct 2 inner struct 1 member 1; };struct outer struct { inner struct 1 outer struct member 1; };";
 
@@ -196,12 +737,19 @@ mod tests {
                                     NamedStatementList {
                                         name: "inner struct 2".to_owned(),
                                         child_nodes: Vec::new(),
+                                        description: None,
                                     },
                                 )),
+                                field_number: None,
+                                description: None,
                             },
                         )],
+                        description: None,
                     })),
+                    field_number: None,
+                    description: None,
                 })],
+                description: None,
             })],
         })
     }
@@ -212,6 +760,7 @@ mod tests {
                 ASTNode::StructDeclaration(NamedStatementList {
                     name: "inner struct 2".to_owned(),
                     child_nodes: Vec::new(),
+                    description: None,
                 }),
                 ASTNode::StructDeclaration(NamedStatementList {
                     name: "inner struct 1".to_owned(),
@@ -220,7 +769,10 @@ mod tests {
                         data_type: Box::new(ASTNode::TypeLiteral(DataType::UserDefined(
                             "inner struct 2".to_owned(),
                         ))),
+                        field_number: None,
+                        description: None,
                     })],
+                    description: None,
                 }),
                 ASTNode::StructDeclaration(NamedStatementList {
                     name: "outer struct".to_owned(),
@@ -229,27 +781,122 @@ mod tests {
                         data_type: Box::new(ASTNode::TypeLiteral(DataType::UserDefined(
                             "inner struct 1".to_owned(),
                         ))),
+                        field_number: None,
+                        description: None,
                     })],
+                    description: None,
                 }),
             ],
         })
     }
 
     #[test]
-    fn test_ast_transformation() {
+    fn test_cxx_code_generation() {
+        assert_eq!(generate(&transformed_ast()), GENERATED_OUTPUT);
+    }
+
+    #[test]
+    fn test_cxx_generation() {
+        assert_eq!(generate_code(&initial_ast(), &CompilationInfo::default()).expect("should be able to generate"), format!("\n{}\n{}", generate_includes(), GENERATED_OUTPUT));
+    }
+
+    #[test]
+    fn test_cxx_generation_options() {
+        let mut compilation_info = CompilationInfo {
+            source_file_name: "schema/user.fruko".to_owned(),
+            ..CompilationInfo::default()
+        };
+        compilation_info.options.set("namespace", "api");
+        compilation_info.options.set("header_guard", "ifndef");
+
         assert_eq!(
-            CXXASTTransformer::transform_ast(&initial_ast()).expect("should be able to transform"),
-            transformed_ast()
+            generate_code(&initial_ast(), &compilation_info).expect("should be able to generate"),
+            format!(
+                "\n#ifndef USER_FRUKO_H\n#define USER_FRUKO_H\n{}\nnamespace api {{ {} }}\n#endif // USER_FRUKO_H",
+                generate_includes(),
+                GENERATED_OUTPUT
+            )
         );
     }
 
+    /// Exercises the members which `nlohmann::json` does not convert by itself: 64 bit integers at
+    /// their limits, chars, options within arrays, and a struct recursive through an array
+    const JSON_TEXT: &str = "
+struct tag { name: string }
+struct node { id: u64, offset: i64, initial: char, labels: array(option(string)), children: array(node), label: option(tag), category: enum kind { leaf, branch, } }
+";
+
+    fn generate_json() -> String {
+        use crate::lexer::lex_tokens;
+        use crate::parser::parse_tokens;
+        let ast = parse_tokens(lex_tokens(JSON_TEXT.to_owned()).expect("should lex")).expect("should parse");
+        let mut compilation_info = CompilationInfo::default();
+        compilation_info.options.set("json", "nlohmann");
+        generate_code(&ast, &compilation_info).expect("should be able to generate")
+    }
+
     #[test]
-    fn test_cxx_code_generation() {
-        assert_eq!(generate(&transformed_ast()), GENERATED_OUTPUT);
+    fn test_cxx_json_generation() {
+        let generated_code = generate_json();
+        assert!(generated_code.contains("#include <nlohmann/json.hpp>"));
+        assert!(generated_code.contains("inline void to_json(nlohmann::json& j, const kind& value);\n"));
+        assert!(generated_code.contains("    case kind::branch: j = \"branch\"; break;\n"));
+        assert!(generated_code.contains("    if (value.label) j[\"label\"] = fruko_json::to_value(*value.label);\n"));
+        assert!(generated_code.contains("    fruko_json::read_required(j, \"children\", value.children);\n"));
+        assert!(generated_code.contains("    fruko_json::read_optional(j, \"label\", value.label);\n"));
     }
 
+    /// Compiles the generated header with `nlohmann::json`, and checks the round trip of a value
+    /// and the JSON pointers of errors
     #[test]
-    fn test_cxx_generation() {
-        assert_eq!(generate_code(&initial_ast(), &CompilationInfo { source_file_name: "".to_string(), preamble_comments: Vec::new() }).expect("should be able to generate"), GENERATED_OUTPUT);
+    fn test_cxx_json_compiles() {
+        use crate::toolchain::{self, run, temporary_directory};
+        if !toolchain::require_cxx_header("test_cxx_json_compiles", "nlohmann/json.hpp") {
+            return;
+        }
+        let directory = temporary_directory("cxx-json");
+        std::fs::write(directory.join("node.h"), generate_json()).expect("should write header");
+        std::fs::write(
+            directory.join("main.cpp"),
+            r#"#include "node.h"
+#include <cstdio>
+
+static void print_error(const char* text) {
+    try {
+        nlohmann::json::parse(text).get<node>();
+        std::printf("no error\n");
+    } catch (const fruko_json::error& error) {
+        std::printf("%s\n", error.what());
+    }
+}
+
+int main() {
+    const char* text = R"({"id":18446744073709551615,"offset":-9223372036854775808,"initial":"x","labels":["a",null],)"
+                       R"("children":[{"id":1,"offset":0,"initial":"y","labels":[],"children":[],"label":null,"category":"leaf"}],)"
+                       R"("label":{"name":"t"},"category":"branch"})";
+    node value = nlohmann::json::parse(text).get<node>();
+    std::printf("%s\n", nlohmann::json(value).dump().c_str());
+    print_error(R"({"id":1,"offset":0,"initial":"x","labels":[1],"children":[],"category":"leaf"})");
+    print_error(R"({"id":1,"offset":0,"initial":"x","labels":[],"children":[{"id":-1}],"category":"leaf"})");
+    print_error(R"({"id":1,"offset":0,"initial":"xy","labels":[],"children":[],"category":"leaf"})");
+    print_error(R"({"id":1,"offset":0,"initial":"x","labels":[],"children":[],"label":{},"category":"leaf"})");
+    std::printf("%s\n", fruko_json::pointer_token("a~b/c").c_str());
+}
+"#,
+        )
+        .expect("should write program");
+
+        run("g++", &["-std=c++17", "-Wall", "-Wextra", "-pedantic", "-Werror", "main.cpp", "-o", "main"], &directory);
+        assert_eq!(
+            run("./main", &[], &directory),
+            "{\"category\":\"branch\",\"children\":[{\"category\":\"leaf\",\"children\":[],\"id\":1,\"initial\":\"y\",\"labels\":[],\"offset\":0}],\"id\":18446744073709551615,\
+             \"initial\":\"x\",\"label\":{\"name\":\"t\"},\"labels\":[\"a\",null],\"offset\":-9223372036854775808}\n\
+             /labels/0: expected a string\n\
+             /children/0/id: integer out of range\n\
+             /initial: expected a string of one character\n\
+             /label/name: missing required member\n\
+             /a~0b~1c\n"
+        );
+        std::fs::remove_dir_all(directory).expect("should remove directory");
     }
 }
@@ -0,0 +1,380 @@
+/// Generation of type relationship diagrams, as Graphviz (`.dot`) or Mermaid class diagrams
+///
+/// Every struct and enum becomes a node listing its members, with inline declarations hoisted out
+/// of line. A struct has an edge to every user defined type its members refer to, labelled with
+/// the member name, and drawn by how the type is referred to:
+/// - containment, for a member of the type itself: a solid edge, with a cardinality of `1`
+/// - optional, for an `Option` of the type: a dashed edge (an aggregation in Mermaid), with a
+///   cardinality of `0..1`
+/// - array, for an array of the type: a solid edge with a crow's foot arrowhead, with a
+///   cardinality of `*`
+///
+/// Types declared in other files are drawn as nodes without members, labelled with their module.
+/// Mermaid class names which are keywords of its class diagrams are quoted with backticks.
+///
+/// Supported options:
+/// - `direction`: the direction the diagram is laid out in, `TB` (top to bottom, the default) or
+///   `LR` (left to right)
+use crate::batch::module_name;
+use crate::compilation_target::{CompilationError, CompilationInfo, CompilationTarget, ExternalType};
+use crate::parser::{ASTNode, DataType};
+use crate::schema::{Declaration, Schema, StructDefinition};
+use std::path::Path;
+
+/// The keywords of Mermaid class diagrams, which cannot name a class unless quoted
+const MERMAID_KEYWORDS: [&str; 16] = [
+    "accDescr", "accTitle", "call", "callback", "class", "classDef", "classDiagram", "click", "cssClass", "direction",
+    "end", "href", "link", "namespace", "note", "style",
+];
+
+pub struct DotGenerator;
+
+pub struct MermaidGenerator;
+
+/// How a struct refers to another type through one of its members
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+enum Relationship {
+    Containment,
+    Optional,
+    Array,
+}
+
+impl Relationship {
+    /// The relationship of a member type, where an array of options, or an option of an array,
+    /// is an array
+    fn of(data_type: &DataType) -> Self {
+        match data_type {
+            DataType::Array(_) => Relationship::Array,
+            DataType::Option(inner_type) => match Relationship::of(inner_type) {
+                Relationship::Array => Relationship::Array,
+                _ => Relationship::Optional,
+            },
+            _ => Relationship::Containment,
+        }
+    }
+
+    fn cardinality(self) -> &'static str {
+        match self {
+            Relationship::Containment => "1",
+            Relationship::Optional => "0..1",
+            Relationship::Array => "*",
+        }
+    }
+}
+
+/// An edge from a struct to a type one of its members refers to
+struct Edge<'a> {
+    from: &'a str,
+    to: &'a str,
+    member: &'a str,
+    relationship: Relationship,
+}
+
+fn edges(struct_definition: &StructDefinition) -> Vec<Edge<'_>> {
+    struct_definition
+        .members
+        .iter()
+        .flat_map(|member| {
+            member.data_type.user_defined_types().into_iter().map(|to| Edge {
+                from: &struct_definition.name,
+                to,
+                member: &member.name,
+                relationship: Relationship::of(&member.data_type),
+            })
+        })
+        .collect()
+}
+
+/// The external types referred to by the schema, in the order they are declared
+fn referenced_external_types<'a>(schema: &Schema, compilation_info: &'a CompilationInfo) -> Vec<&'a ExternalType> {
+    compilation_info
+        .external_types
+        .iter()
+        .filter(|external_type| {
+            schema
+                .structs()
+                .flat_map(|struct_definition| &struct_definition.members)
+                .any(|member| member.data_type.user_defined_types().contains(&external_type.name.as_str()))
+        })
+        .collect()
+}
+
+impl CompilationTarget for DotGenerator {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        let direction = compilation_info.options.get_choice("direction", &["TB", "LR"], "TB")?;
+        let schema = Schema::from_ast(ast)?;
+        let module = module_name(Path::new(&compilation_info.source_file_name));
+
+        let nodes = schema
+            .declarations
+            .iter()
+            .map(|declaration| {
+                let (title, members) = match declaration {
+                    Declaration::Struct(struct_definition) => (
+                        struct_definition.name.clone(),
+                        struct_definition
+                            .members
+                            .iter()
+                            .map(|member| format!("{}: {}\\l", member.name, member.data_type))
+                            .collect::<String>(),
+                    ),
+                    Declaration::Enum(enum_definition) => (
+                        format!("\\<\\<enum\\>\\>\\n{}", enum_definition.name),
+                        enum_definition
+                            .members
+                            .iter()
+                            .map(|member| format!("{}\\l", member))
+                            .collect::<String>(),
+                    ),
+                };
+                format!("  \"{}\" [label=\"{{{}|{}}}\"];\n", declaration.name(), title, members)
+            })
+            .collect::<String>();
+        let external_nodes = referenced_external_types(&schema, compilation_info)
+            .into_iter()
+            .map(|external_type| {
+                format!(
+                    "  \"{}\" [label=\"{{{}\\n({})}}\", style=dashed];\n",
+                    external_type.name, external_type.name, external_type.module
+                )
+            })
+            .collect::<String>();
+        let edges = schema
+            .structs()
+            .flat_map(edges)
+            .map(|edge| {
+                let attributes = match edge.relationship {
+                    Relationship::Containment => String::new(),
+                    Relationship::Optional => ", style=dashed".to_owned(),
+                    Relationship::Array => ", arrowhead=crow".to_owned(),
+                };
+                format!(
+                    "  \"{}\" -> \"{}\" [label=\"{}\", headlabel=\"{}\"{}];\n",
+                    edge.from,
+                    edge.to,
+                    edge.member,
+                    edge.relationship.cardinality(),
+                    attributes
+                )
+            })
+            .collect::<String>();
+
+        Ok(format!(
+            "{}\ndigraph \"{}\" {{\n  rankdir={};\n  node [shape=record, fontname=\"Helvetica\"];\n  edge [fontname=\"Helvetica\", fontsize=10];\n\n{}{}\n{}}}\n",
+            generate_preamble("//", compilation_info),
+            module,
+            direction,
+            nodes,
+            external_nodes,
+            edges
+        ))
+    }
+}
+
+impl CompilationTarget for MermaidGenerator {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        let direction = compilation_info.options.get_choice("direction", &["TB", "LR"], "TB")?;
+        let schema = Schema::from_ast(ast)?;
+
+        let classes = schema
+            .declarations
+            .iter()
+            .map(|declaration| {
+                let members = match declaration {
+                    Declaration::Struct(struct_definition) => struct_definition
+                        .members
+                        .iter()
+                        .map(|member| format!("    {} {}\n", generate_mermaid_type(&member.data_type), member.name))
+                        .collect::<String>(),
+                    Declaration::Enum(enum_definition) => {
+                        let values = enum_definition
+                            .members
+                            .iter()
+                            .map(|member| format!("    {}\n", member))
+                            .collect::<String>();
+                        format!("    <<enumeration>>\n{}", values)
+                    }
+                };
+                format!("  class {} {{\n{}  }}\n", quote_mermaid_name(declaration.name()), members)
+            })
+            .collect::<String>();
+        let external_classes = referenced_external_types(&schema, compilation_info)
+            .into_iter()
+            .map(|external_type| {
+                format!("  class {} {{\n    <<{}>>\n  }}\n", quote_mermaid_name(&external_type.name), external_type.module)
+            })
+            .collect::<String>();
+        let relationships = schema
+            .structs()
+            .flat_map(edges)
+            .map(|edge| {
+                let arrow = match edge.relationship {
+                    Relationship::Optional => "o--",
+                    Relationship::Containment | Relationship::Array => "*--",
+                };
+                format!(
+                    "  {} {} \"{}\" {} : {}\n",
+                    quote_mermaid_name(edge.from),
+                    arrow,
+                    edge.relationship.cardinality(),
+                    quote_mermaid_name(edge.to),
+                    edge.member
+                )
+            })
+            .collect::<String>();
+
+        Ok(format!(
+            "{}\nclassDiagram\n  direction {}\n{}{}{}",
+            generate_preamble("%%", compilation_info),
+            direction,
+            classes,
+            external_classes,
+            relationships
+        ))
+    }
+}
+
+fn quote_mermaid_name(name: &str) -> String {
+    if MERMAID_KEYWORDS.contains(&name) {
+        format!("`{}`", name)
+    } else {
+        name.to_owned()
+    }
+}
+
+/// Writes a data type with the `~` generics of Mermaid, e.g. `option~array~u8~~`
+fn generate_mermaid_type(data_type: &DataType) -> String {
+    match data_type {
+        DataType::Option(inner_type) => format!("option~{}~", generate_mermaid_type(inner_type)),
+        DataType::Array(inner_type) => format!("array~{}~", generate_mermaid_type(inner_type)),
+        data_type => data_type.to_string(),
+    }
+}
+
+/// Graphviz and Mermaid comments have different prefixes, so the prefix is given
+fn generate_preamble(prefix: &str, compilation_info: &CompilationInfo) -> String {
+    compilation_info.preamble_comments.iter()
+        .map(|preamble| format!("{} {}", prefix, preamble))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+    use crate::schema::DeclarationKind;
+
+    const TEXT: &str = "
+struct order {
+  lines: array(option(line)),
+  status: enum status { open, paid, },
+  history: option(array(status)),
+  previous: option(order),
+  customer: customer,
+}
+struct line { sku: string, quantity: u16 }
+";
+
+    const GENERATED_DOT: &str = r#"
+digraph "order" {
+  rankdir=TB;
+  node [shape=record, fontname="Helvetica"];
+  edge [fontname="Helvetica", fontsize=10];
+
+  "status" [label="{\<\<enum\>\>\nstatus|open\lpaid\l}"];
+  "order" [label="{order|lines: array(option(line))\lstatus: status\lhistory: option(array(status))\lprevious: option(order)\lcustomer: customer\l}"];
+  "line" [label="{line|sku: string\lquantity: u16\l}"];
+  "customer" [label="{customer\n(crm)}", style=dashed];
+
+  "order" -> "line" [label="lines", headlabel="*", arrowhead=crow];
+  "order" -> "status" [label="status", headlabel="1"];
+  "order" -> "status" [label="history", headlabel="*", arrowhead=crow];
+  "order" -> "order" [label="previous", headlabel="0..1", style=dashed];
+  "order" -> "customer" [label="customer", headlabel="1"];
+}
+"#;
+
+    const GENERATED_MERMAID: &str = "
+classDiagram
+  direction LR
+  class status {
+    <<enumeration>>
+    open
+    paid
+  }
+  class order {
+    array~option~line~~ lines
+    status status
+    option~array~status~~ history
+    option~order~ previous
+    customer customer
+  }
+  class line {
+    string sku
+    u16 quantity
+  }
+  class customer {
+    <<crm>>
+  }
+  order *-- \"*\" line : lines
+  order *-- \"1\" status : status
+  order *-- \"*\" status : history
+  order o-- \"0..1\" order : previous
+  order *-- \"1\" customer : customer
+";
+
+    /// Types from other files are drawn only where the schema refers to them
+    fn compilation_info() -> CompilationInfo {
+        let external_type = |name: &str, module: &str| ExternalType {
+            name: name.to_owned(),
+            module: module.to_owned(),
+            kind: DeclarationKind::Struct,
+        };
+        CompilationInfo {
+            source_file_name: "order.fruko".to_owned(),
+            external_types: vec![external_type("customer", "crm"), external_type("invoice", "billing")],
+            ..CompilationInfo::default()
+        }
+    }
+
+    #[test]
+    fn test_generate_dot() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+
+        assert_eq!(
+            DotGenerator
+                .generate_code(&ast, &compilation_info())
+                .expect("should generate code"),
+            GENERATED_DOT
+        );
+    }
+
+    #[test]
+    fn test_generate_mermaid() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+        let mut compilation_info = compilation_info();
+        compilation_info.options.set("direction", "LR");
+
+        assert_eq!(
+            MermaidGenerator
+                .generate_code(&ast, &compilation_info)
+                .expect("should generate code"),
+            GENERATED_MERMAID
+        );
+    }
+
+    #[test]
+    fn test_generate_mermaid_keywords() {
+        let ast = parse_tokens(lex_tokens("struct class { note: option(class), style: enum style { bold, } }".to_owned()).expect("should lex"))
+            .expect("should parse");
+
+        let generated_code = MermaidGenerator
+            .generate_code(&ast, &CompilationInfo::default())
+            .expect("should generate code");
+        assert!(generated_code.contains("  class `style` {\n    <<enumeration>>\n    bold\n  }\n"));
+        assert!(generated_code.contains("  class `class` {\n    option~class~ note\n    style style\n  }\n"));
+        assert!(generated_code.contains("  `class` o-- \"0..1\" `class` : note\n  `class` *-- \"1\" `style` : style\n"));
+    }
+}
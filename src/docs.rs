@@ -0,0 +1,405 @@
+/// Generation of browsable documentation of the data definition, as Markdown or HTML
+///
+/// The document starts with an index of every type, then has a section per struct and enum, in
+/// declaration order with inline declarations hoisted out of line. A struct section has a table of
+/// its members, with their type, whether they are optional and their description, and an enum
+/// section lists its values. Every section also lists the structs which use the type, and through
+/// which members. User defined types link to their section, or to the document generated for
+/// their module where declared in another file.
+///
+/// Descriptions are taken from the `///` documentation comments of declarations and members. They
+/// are written as they are, so may use Markdown in Markdown documents.
+///
+/// Supported options:
+/// - `title`: the title of the document, the module name by default
+use crate::batch::module_name;
+use crate::compilation_target::{CompilationError, CompilationInfo, CompilationTarget};
+use crate::parser::{ASTNode, DataType};
+use crate::schema::{Declaration, DeclarationKind, EnumDefinition, Schema, StructDefinition};
+use std::path::Path;
+
+pub struct DocsGenerator {
+    /// Whether the document is written as HTML, rather than Markdown
+    pub html: bool,
+}
+
+impl CompilationTarget for DocsGenerator {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        let schema = Schema::from_ast(ast)?;
+        let module = module_name(Path::new(&compilation_info.source_file_name));
+        let title = compilation_info.options.get("title").unwrap_or(&module);
+        let generator = DocsWriter {
+            schema: &schema,
+            compilation_info,
+            html: self.html,
+        };
+
+        let sections = schema
+            .declarations
+            .iter()
+            .map(|declaration| generator.generate_section(declaration))
+            .collect::<String>();
+        let body = format!(
+            "{}{}{}",
+            generator.heading(1, title, None),
+            generator.generate_index(),
+            sections
+        );
+
+        if self.html {
+            Ok(format!(
+                "{}\n<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+                generate_preamble(compilation_info),
+                escape_html(title),
+                body
+            ))
+        } else {
+            Ok(format!("{}{}", generate_preamble(compilation_info), body))
+        }
+    }
+}
+
+/// Both Markdown and HTML have HTML comments
+fn generate_preamble(compilation_info: &CompilationInfo) -> String {
+    compilation_info.preamble_comments.iter()
+        .map(|preamble| format!("<!-- {} -->", preamble))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// The anchor of the section of a type. Names are alphanumeric, so this matches the anchor
+/// Markdown renderers derive from the heading, and the kind keeps it apart from the anchor of the
+/// title, which is usually the name of a type
+fn anchor(kind: DeclarationKind, name: &str) -> String {
+    format!("{}-{}", kind_name(kind), name.to_lowercase())
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+struct DocsWriter<'a> {
+    schema: &'a Schema,
+    compilation_info: &'a CompilationInfo,
+    html: bool,
+}
+
+impl DocsWriter<'_> {
+    fn generate_index(&self) -> String {
+        let rows = self
+            .schema
+            .declarations
+            .iter()
+            .map(|declaration| {
+                let description = match declaration {
+                    Declaration::Struct(struct_definition) => &struct_definition.description,
+                    Declaration::Enum(enum_definition) => &enum_definition.description,
+                };
+                vec![
+                    self.link(declaration.name(), &format!("#{}", anchor(declaration.kind(), declaration.name()))),
+                    kind_name(declaration.kind()).to_owned(),
+                    // The summary, being the first line of the description
+                    self.text(description.as_deref().and_then(|description| description.lines().next()).unwrap_or_default()),
+                ]
+            })
+            .collect::<Vec<Vec<String>>>();
+        format!(
+            "{}{}",
+            self.heading(2, "Type index", Some("type-index")),
+            self.table(&["Type", "Kind", "Description"], &rows)
+        )
+    }
+
+    fn generate_section(&self, declaration: &Declaration) -> String {
+        let (description, contents) = match declaration {
+            Declaration::Struct(struct_definition) => (&struct_definition.description, self.generate_members(struct_definition)),
+            Declaration::Enum(enum_definition) => (&enum_definition.description, self.generate_values(enum_definition)),
+        };
+        let description = description
+            .as_deref()
+            .map(|description| self.paragraph(&self.text(description)))
+            .unwrap_or_default();
+
+        format!(
+            "{}{}{}{}",
+            self.heading(
+                2,
+                &format!("{} {}", kind_name(declaration.kind()), declaration.name()),
+                Some(&anchor(declaration.kind(), declaration.name()))
+            ),
+            description,
+            contents,
+            self.generate_used_by(declaration.name())
+        )
+    }
+
+    fn generate_members(&self, struct_definition: &StructDefinition) -> String {
+        if struct_definition.members.is_empty() {
+            return self.paragraph("No members.");
+        }
+
+        let rows = struct_definition
+            .members
+            .iter()
+            .map(|member| {
+                let optional = if let DataType::Option(_) = member.data_type { "yes" } else { "no" };
+                vec![
+                    self.code(&member.name),
+                    self.generate_type(&member.data_type),
+                    optional.to_owned(),
+                    // Table cells are a single line
+                    self.text(&member.description.as_deref().unwrap_or_default().replace('\n', " ")),
+                ]
+            })
+            .collect::<Vec<Vec<String>>>();
+        self.table(&["Member", "Type", "Optional", "Description"], &rows)
+    }
+
+    fn generate_values(&self, enum_definition: &EnumDefinition) -> String {
+        if enum_definition.members.is_empty() {
+            return self.paragraph("No values.");
+        }
+
+        let values = enum_definition
+            .members
+            .iter()
+            .map(|member| self.code(member))
+            .collect::<Vec<String>>();
+        format!("{}{}", self.paragraph("Values:"), self.list(&values))
+    }
+
+    /// Lists the structs with members of a type, and the names of those members
+    fn generate_used_by(&self, name: &str) -> String {
+        let users = self
+            .schema
+            .structs()
+            .filter_map(|struct_definition| {
+                let members = struct_definition
+                    .members
+                    .iter()
+                    .filter(|member| member.data_type.user_defined_types().contains(&name))
+                    .map(|member| self.code(&member.name))
+                    .collect::<Vec<String>>();
+                if members.is_empty() {
+                    return None;
+                }
+                Some(format!(
+                    "{}: {}",
+                    self.link(&struct_definition.name, &format!("#{}", anchor(DeclarationKind::Struct, &struct_definition.name))),
+                    members.join(", ")
+                ))
+            })
+            .collect::<Vec<String>>();
+
+        if users.is_empty() {
+            return String::new();
+        }
+        format!("{}{}", self.paragraph("Used by:"), self.list(&users))
+    }
+
+    /// Generates a type as it is written in the data definition, linking user defined types
+    fn generate_type(&self, data_type: &DataType) -> String {
+        match data_type {
+            DataType::Option(inner_type) => format!("option({})", self.generate_type(inner_type)),
+            DataType::Array(inner_type) => format!("array({})", self.generate_type(inner_type)),
+            DataType::UserDefined(name) => self.link(name, &self.generate_reference(name)),
+            data_type => data_type.to_string(),
+        }
+    }
+
+    /// Generates a link to the section of a type, in the document of another module if the type
+    /// is declared in another file
+    fn generate_reference(&self, name: &str) -> String {
+        let document = self
+            .compilation_info
+            .external_types
+            .iter()
+            .find(|external_type| external_type.name == name)
+            .map(|external_type| format!("{}.{}", external_type.module, if self.html { "html" } else { "md" }))
+            .unwrap_or_default();
+        let kind = self.schema.declaration_kind(name, &self.compilation_info.external_types);
+        format!("{}#{}", document, anchor(kind, name))
+    }
+
+    /// Free text, which is escaped in HTML. Markdown is written as it is, except for the pipes
+    /// which would end a table cell
+    fn text(&self, text: &str) -> String {
+        if self.html {
+            escape_html(text)
+        } else {
+            text.replace('|', "\\|")
+        }
+    }
+
+    fn heading(&self, level: usize, text: &str, id: Option<&str>) -> String {
+        if self.html {
+            let id = id.map(|id| format!(" id=\"{}\"", id)).unwrap_or_default();
+            format!("<h{level}{}>{}</h{level}>\n", id, escape_html(text))
+        } else {
+            format!("\n{} {}\n", "#".repeat(level), text)
+        }
+    }
+
+    fn paragraph(&self, text: &str) -> String {
+        if self.html {
+            format!("<p>{}</p>\n", text)
+        } else {
+            format!("\n{}\n", text)
+        }
+    }
+
+    fn code(&self, text: &str) -> String {
+        if self.html {
+            format!("<code>{}</code>", text)
+        } else {
+            format!("`{}`", text)
+        }
+    }
+
+    fn link(&self, text: &str, target: &str) -> String {
+        if self.html {
+            format!("<a href=\"{}\">{}</a>", target, text)
+        } else {
+            format!("[{}]({})", text, target)
+        }
+    }
+
+    fn list(&self, items: &[String]) -> String {
+        if self.html {
+            let items = items.iter().map(|item| format!("<li>{}</li>\n", item)).collect::<String>();
+            format!("<ul>\n{}</ul>\n", items)
+        } else {
+            let items = items.iter().map(|item| format!("- {}\n", item)).collect::<String>();
+            format!("\n{}", items)
+        }
+    }
+
+    fn table(&self, headers: &[&str], rows: &[Vec<String>]) -> String {
+        if self.html {
+            let header = headers.iter().map(|header| format!("<th>{}</th>", header)).collect::<String>();
+            let rows = rows
+                .iter()
+                .map(|row| format!("<tr>{}</tr>\n", row.iter().map(|cell| format!("<td>{}</td>", cell)).collect::<String>()))
+                .collect::<String>();
+            format!("<table>\n<tr>{}</tr>\n{}</table>\n", header, rows)
+        } else {
+            let rows = rows
+                .iter()
+                .map(|row| format!("| {} |\n", row.join(" | ")))
+                .collect::<String>();
+            format!("\n| {} |\n|{}\n{}", headers.join(" | "), "---|".repeat(headers.len()), rows)
+        }
+    }
+}
+
+fn kind_name(kind: DeclarationKind) -> &'static str {
+    match kind {
+        DeclarationKind::Struct => "struct",
+        DeclarationKind::Enum => "enum",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compilation_target::ExternalType;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+    use crate::schema::DeclarationKind;
+
+    const TEXT: &str = "
+/// Someone using the system
+struct person {
+  /// Unique, and never reused
+  id: u32,
+  /// What they would like to be called.
+  /// Defaults to their name
+  nickname: option(string),
+  mood: enum mood { happy, sad, },
+  manager: option(person),
+  role: role,
+}
+";
+
+    const GENERATED_CODE: &str = "
+# person
+
+## Type index
+
+| Type | Kind | Description |
+|---|---|---|
+| [mood](#enum-mood) | enum |  |
+| [person](#struct-person) | struct | Someone using the system |
+
+## enum mood
+
+Values:
+
+- `happy`
+- `sad`
+
+Used by:
+
+- [person](#struct-person): `mood`
+
+## struct person
+
+Someone using the system
+
+| Member | Type | Optional | Description |
+|---|---|---|---|
+| `id` | u32 | no | Unique, and never reused |
+| `nickname` | option(string) | yes | What they would like to be called. Defaults to their name |
+| `mood` | [mood](#enum-mood) | no |  |
+| `manager` | option([person](#struct-person)) | yes |  |
+| `role` | [role](user.md#enum-role) | no |  |
+
+Used by:
+
+- [person](#struct-person): `manager`
+";
+
+    #[test]
+    fn test_generate_markdown() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+        // The module is named like its main type, so the title and the type's section are apart
+        // only by their anchors
+        let compilation_info = CompilationInfo {
+            source_file_name: "person.fruko".to_owned(),
+            external_types: vec![ExternalType {
+                name: "role".to_owned(),
+                module: "user".to_owned(),
+                kind: DeclarationKind::Enum,
+            }],
+            ..CompilationInfo::default()
+        };
+
+        assert_eq!(
+            DocsGenerator { html: false }
+                .generate_code(&ast, &compilation_info)
+                .expect("should generate code"),
+            GENERATED_CODE
+        );
+    }
+
+    #[test]
+    fn test_generate_html() {
+        let ast = parse_tokens(lex_tokens("/// A <b> & c\nstruct b { a: a } struct a { x: u8 }".to_owned()).expect("should lex"))
+            .expect("should parse");
+        let compilation_info = CompilationInfo {
+            source_file_name: "b.fruko".to_owned(),
+            ..CompilationInfo::default()
+        };
+
+        let generated_code = DocsGenerator { html: true }
+            .generate_code(&ast, &compilation_info)
+            .expect("should generate code");
+        assert!(generated_code.contains("<h1>b</h1>\n"));
+        assert!(generated_code.contains("<h2 id=\"struct-b\">struct b</h2>\n<p>A &lt;b&gt; &amp; c</p>\n"));
+        assert!(generated_code.contains("<tr><td><code>a</code></td><td><a href=\"#struct-a\">a</a></td><td>no</td><td></td></tr>\n"));
+        assert!(generated_code.contains("<ul>\n<li><a href=\"#struct-b\">b</a>: <code>a</code></li>\n</ul>\n"));
+    }
+}
@@ -0,0 +1,182 @@
+/// Compilation target backed by an external executable
+///
+/// The executable is run once per output file. It receives a JSON object on stdin with the fields
+/// `compilation_info` and `ast`, being the serialized `CompilationInfo` and `ASTNode` respectively,
+/// and is expected to write the generated code to stdout and exit successfully. Anything written
+/// to stderr is reported back to the user if the executable fails.
+use crate::compilation_target::{CompilationError, CompilationInfo, CompilationTarget};
+use crate::parser::ASTNode;
+use crate::target_registry::TargetLoadError;
+use serde::Serialize;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+pub struct ExternalTarget {
+    program: String,
+    arguments: Vec<String>,
+}
+
+/// The document written to the stdin of the external executable
+#[derive(Serialize)]
+struct ExternalTargetInput<'a> {
+    compilation_info: &'a CompilationInfo,
+    ast: &'a ASTNode,
+}
+
+impl ExternalTarget {
+    /// Creates an external target from a command line, the first word being the program to run
+    /// and the rest its arguments. Words are split as a POSIX shell does, see `split_command`
+    pub fn new(command: &str) -> Result<Self, TargetLoadError> {
+        let mut words = split_command(command).map_err(TargetLoadError::Command)?.into_iter();
+        match words.next() {
+            Some(program) => Ok(Self {
+                program,
+                arguments: words.collect(),
+            }),
+            None => Err(TargetLoadError::Command("the command is empty".to_owned())),
+        }
+    }
+}
+
+/// Splits a command line into words on whitespace. As in a POSIX shell, single quotes keep
+/// everything up to the next single quote, double quotes keep everything but a backslash escaping
+/// `"` or `\`, and a backslash outside of quotes keeps the next character
+fn split_command(command: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    // None between words, so that quoted empty words are kept
+    let mut word: Option<String> = None;
+    let mut characters = command.chars();
+    while let Some(character) = characters.next() {
+        match character {
+            character if character.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match characters.next() {
+                        Some('\'') => break,
+                        Some(character) => word.push(character),
+                        None => return Err(format!("unterminated single quote in '{}'", command)),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match characters.next() {
+                        Some('"') => break,
+                        Some('\\') => match characters.next() {
+                            Some(escaped @ ('"' | '\\')) => word.push(escaped),
+                            Some(character) => {
+                                word.push('\\');
+                                word.push(character);
+                            }
+                            None => return Err(format!("unterminated double quote in '{}'", command)),
+                        },
+                        Some(character) => word.push(character),
+                        None => return Err(format!("unterminated double quote in '{}'", command)),
+                    }
+                }
+            }
+            '\\' => match characters.next() {
+                Some(escaped) => word.get_or_insert_with(String::new).push(escaped),
+                None => return Err(format!("trailing backslash in '{}'", command)),
+            },
+            character => word.get_or_insert_with(String::new).push(character),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+impl CompilationTarget for ExternalTarget {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        let input = serde_json::to_vec(&ExternalTargetInput {
+            compilation_info,
+            ast,
+        })
+        .map_err(|error| CompilationError::ExternalTarget(error.to_string()))?;
+
+        let mut child = Command::new(&self.program)
+            .args(&self.arguments)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|error| CompilationError::ExternalTarget(format!("{}: {}", self.program, error)))?;
+
+        // Written from another thread, so that an executable producing output before it has read
+        // all of its input cannot deadlock on a full pipe. Dropping stdin closes it, signalling EOF
+        let mut stdin = child.stdin.take().expect("stdin should be piped");
+        let writer = std::thread::spawn(move || stdin.write_all(&input));
+
+        let output = child
+            .wait_with_output()
+            .map_err(|error| CompilationError::ExternalTarget(format!("{}: {}", self.program, error)))?;
+        let write_result = writer.join().expect("stdin writer should not panic");
+
+        if !output.status.success() {
+            return Err(CompilationError::ExternalTarget(format!(
+                "{} exited with {}: {}",
+                self.program,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        write_result.map_err(|error| CompilationError::ExternalTarget(format!("{}: {}", self.program, error)))?;
+
+        String::from_utf8(output.stdout).map_err(|_| {
+            CompilationError::ExternalTarget(format!("{} produced output that is not valid UTF8", self.program))
+        })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::parser::DataDefinition;
+
+    fn compilation_info() -> CompilationInfo {
+        CompilationInfo {
+            source_file_name: "input.fruko".to_owned(),
+            ..CompilationInfo::default()
+        }
+    }
+
+    #[test]
+    fn test_external_target_receives_json() {
+        let output = ExternalTarget::new("cat")
+            .expect("should parse the command")
+            .generate_code(&ASTNode::DataDefinition(DataDefinition::default()), &compilation_info())
+            .expect("should be able to run cat");
+
+        assert_eq!(
+            output,
+            r#"{"compilation_info":{"source_file_name":"input.fruko","preamble_comments":[],"options":{},"external_types":[]},"ast":{"DataDefinition":{"child_nodes":[]}}}"#
+        );
+    }
+
+    #[test]
+    fn test_external_target_failure() {
+        let result = ExternalTarget::new("false")
+            .expect("should parse the command")
+            .generate_code(&ASTNode::DataDefinition(DataDefinition::default()), &compilation_info());
+
+        assert!(matches!(result, Err(CompilationError::ExternalTarget(_))));
+    }
+
+    #[test]
+    fn test_external_target_quoted_arguments() {
+        assert_eq!(
+            split_command(r#"'/opt/my tools/gen' --name "a \"b\" c" it\'s '' x\ y"#).expect("should split"),
+            ["/opt/my tools/gen", "--name", r#"a "b" c"#, "it's", "", "x y"]
+        );
+        assert!(matches!(ExternalTarget::new("gen 'name"), Err(TargetLoadError::Command(_))));
+        assert!(matches!(ExternalTarget::new("  "), Err(TargetLoadError::Command(_))));
+
+        let output = ExternalTarget::new(r#"sh -c 'cat > /dev/null; printf "%s|%s" "$0" "$1"' "first argument" second"#)
+            .expect("should parse the command")
+            .generate_code(&ASTNode::DataDefinition(DataDefinition::default()), &compilation_info())
+            .expect("should be able to run sh");
+        assert_eq!(output, "first argument|second");
+    }
+}
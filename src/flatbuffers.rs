@@ -0,0 +1,247 @@
+/// Generation for FlatBuffers schemas (`.fbs`)
+///
+/// Structs become tables and enums become enums, named in `PascalCase`. Members which are not an
+/// `Option` are marked `(required)`, or default to `null` where scalar, so absent values can be
+/// told apart from zero. Arrays become vectors, which cannot hold options or other vectors, and
+/// chars are strings. Types from other files are included.
+///
+/// Supported options:
+/// - `namespace`: the namespace of the types, none by default
+/// - `root`: the `root_type` of the file. By default, the one struct no other type uses, if any
+use crate::compilation_target::{CompilationError, CompilationInfo, CompilationTarget};
+use crate::naming::pascal_case;
+use crate::parser::{ASTNode, DataType};
+use crate::schema::{Declaration, DeclarationKind, EnumDefinition, Schema, StructDefinition, StructMember};
+
+pub struct FlatBuffersGenerator;
+
+impl CompilationTarget for FlatBuffersGenerator {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        let schema = Schema::from_ast(ast)?;
+
+        let includes = compilation_info
+            .external_modules()
+            .keys()
+            .map(|module| format!("include \"{}.fbs\";\n", module))
+            .collect::<String>();
+        let namespace = compilation_info
+            .options
+            .get("namespace")
+            .map(|namespace| format!("namespace {};\n", namespace))
+            .unwrap_or_default();
+        let separator = if includes.is_empty() || namespace.is_empty() { "" } else { "\n" };
+        let declarations = schema
+            .dependency_order()
+            .into_iter()
+            .map(|declaration| match declaration {
+                Declaration::Struct(struct_definition) => generate_table(struct_definition, &schema, compilation_info),
+                Declaration::Enum(enum_definition) => generate_enum(enum_definition),
+            })
+            .map(|declaration| declaration.map(|declaration| format!("\n{}", declaration)))
+            .collect::<Result<String, CompilationError>>()?;
+        let root_type = match root_type(&schema, compilation_info)? {
+            Some(root) => format!("\nroot_type {};\n", pascal_case(root)),
+            None => String::new(),
+        };
+
+        Ok(format!(
+            "{}\n{}{}{}{}{}",
+            generate_preamble(compilation_info),
+            includes,
+            separator,
+            namespace,
+            declarations,
+            root_type
+        ))
+    }
+}
+
+fn generate_preamble(compilation_info: &CompilationInfo) -> String {
+    compilation_info.preamble_comments.iter()
+        .map(|preamble| format!("// {}", preamble))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// The root type, being the `root` option or the one struct no other struct uses
+fn root_type<'a>(schema: &'a Schema, compilation_info: &'a CompilationInfo) -> Result<Option<&'a str>, CompilationError> {
+    if let Some(root) = compilation_info.options.get("root") {
+        return match schema.declaration(root) {
+            Some(Declaration::Struct(_)) => Ok(Some(root)),
+            _ => Err(CompilationError::InvalidOption(format!("root '{}' is not a declared struct", root))),
+        };
+    }
+
+    let mut roots = schema.structs().filter(|candidate| {
+        !schema.structs().any(|struct_definition| {
+            struct_definition.name != candidate.name
+                && struct_definition
+                    .members
+                    .iter()
+                    .any(|member| member.data_type.user_defined_types().contains(&candidate.name.as_str()))
+        })
+    });
+    Ok(match (roots.next(), roots.next()) {
+        (Some(root), None) => Some(root.name.as_str()),
+        _ => None,
+    })
+}
+
+fn generate_enum(enum_definition: &EnumDefinition) -> Result<String, CompilationError> {
+    let underlying_type = match enum_definition.members.len() {
+        0 => {
+            return Err(CompilationError::UnsupportedType(format!(
+                "'{}' has no members, which FlatBuffers enums require",
+                enum_definition.name
+            )))
+        }
+        1..=256 => "ubyte",
+        257..=65536 => "ushort",
+        _ => "uint",
+    };
+    let values = enum_definition
+        .members
+        .iter()
+        .map(|member| format!("  {},\n", member))
+        .collect::<String>();
+    Ok(format!(
+        "enum {} : {} {{\n{}}}\n",
+        pascal_case(&enum_definition.name),
+        underlying_type,
+        values
+    ))
+}
+
+fn generate_table(
+    struct_definition: &StructDefinition,
+    schema: &Schema,
+    compilation_info: &CompilationInfo,
+) -> Result<String, CompilationError> {
+    let fields = struct_definition
+        .members
+        .iter()
+        .map(|member| generate_field(member, schema, compilation_info))
+        .collect::<Result<String, CompilationError>>()?;
+    Ok(format!("table {} {{\n{}}}\n", pascal_case(&struct_definition.name), fields))
+}
+
+fn generate_field(member: &StructMember, schema: &Schema, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+    // Whether the field is a reference, rather than a scalar or enum stored inline
+    let is_reference = |data_type: &DataType| match data_type {
+        DataType::String | DataType::Char | DataType::Array(_) => true,
+        DataType::UserDefined(name) => {
+            schema.declaration_kind(name, &compilation_info.external_types) == DeclarationKind::Struct
+        }
+        _ => false,
+    };
+
+    let (type_name, attribute) = match &member.data_type {
+        DataType::Option(inner_type) => {
+            if let DataType::Option(_) = inner_type.as_ref() {
+                return Err(unsupported_type(&member.name, "an option of an option"));
+            }
+            let default = if is_reference(inner_type) { "" } else { " = null" };
+            (generate_type_name(&member.name, inner_type)?, default)
+        }
+        data_type if is_reference(data_type) => (generate_type_name(&member.name, data_type)?, " (required)"),
+        data_type => (generate_type_name(&member.name, data_type)?, ""),
+    };
+    Ok(format!("  {}:{}{};\n", member.name, type_name, attribute))
+}
+
+fn unsupported_type(name: &str, description: &str) -> CompilationError {
+    CompilationError::UnsupportedType(format!("'{}' is {}, which FlatBuffers cannot represent", name, description))
+}
+
+fn generate_type_name(member_name: &str, data_type: &DataType) -> Result<String, CompilationError> {
+    Ok(match data_type {
+        DataType::U8 => "ubyte".to_owned(),
+        DataType::I8 => "byte".to_owned(),
+        DataType::U16 => "ushort".to_owned(),
+        DataType::I16 => "short".to_owned(),
+        DataType::U32 => "uint".to_owned(),
+        DataType::I32 => "int".to_owned(),
+        DataType::U64 => "ulong".to_owned(),
+        DataType::I64 => "long".to_owned(),
+        DataType::F32 => "float".to_owned(),
+        DataType::F64 => "double".to_owned(),
+        DataType::Char | DataType::String => "string".to_owned(),
+        DataType::Bool => "bool".to_owned(),
+        DataType::Array(inner_type) => match inner_type.as_ref() {
+            DataType::Option(_) => return Err(unsupported_type(member_name, "an array of options")),
+            DataType::Array(_) => return Err(unsupported_type(member_name, "an array of arrays")),
+            inner_type => format!("[{}]", generate_type_name(member_name, inner_type)?),
+        },
+        DataType::Option(_) => return Err(unsupported_type(member_name, "a nested option")),
+        DataType::UserDefined(name) => pascal_case(name),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+
+    // Optional scalars and enums default to null, a table refers to itself, and the enum is
+    // declared before its first use
+    const TEXT: &str = "
+struct node { id: u64, initial: char, weight: option(f32), kind: option(kind), history: array(kind), children: array(node), parent: option(node) }
+enum kind { leaf, branch, }
+";
+
+    const GENERATED_CODE: &str = "
+namespace acme;
+
+enum Kind : ubyte {
+  leaf,
+  branch,
+}
+
+table Node {
+  id:ulong;
+  initial:string (required);
+  weight:float = null;
+  kind:Kind = null;
+  history:[Kind] (required);
+  children:[Node] (required);
+  parent:Node;
+}
+
+root_type Node;
+";
+
+    #[test]
+    fn test_generate_flatbuffers() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+        let mut compilation_info = CompilationInfo::default();
+        compilation_info.options.set("namespace", "acme");
+
+        assert_eq!(
+            FlatBuffersGenerator
+                .generate_code(&ast, &compilation_info)
+                .expect("should generate code"),
+            GENERATED_CODE
+        );
+    }
+
+    #[test]
+    fn test_unsupported_types() {
+        for text in [
+            "struct node { labels: array(option(string)) }",
+            "struct node { grid: array(array(u8)) }",
+            "struct node { maybe: option(option(u8)) }",
+            "enum empty { }",
+        ] {
+            let ast = parse_tokens(lex_tokens(text.to_owned()).expect("should lex")).expect("should parse");
+            assert!(
+                matches!(
+                    FlatBuffersGenerator.generate_code(&ast, &CompilationInfo::default()),
+                    Err(CompilationError::UnsupportedType(_))
+                ),
+                "{} should be unsupported",
+                text
+            );
+        }
+    }
+}
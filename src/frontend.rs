@@ -0,0 +1,165 @@
+/// Frontends, reading data definitions written in other schema languages
+///
+/// Besides `.fruko` data definitions, existing Protocol Buffers (`.proto`) and JSON Schema
+/// (`.json`) files can be compiled. Each frontend converts its input into the same
+/// `ASTNode::DataDefinition` the parser produces, so every target works on them. Constructs with
+/// no equivalent in a data definition are reported as diagnostics: approximations as warnings,
+/// and anything which cannot be represented at all as errors.
+pub mod json_schema;
+pub mod proto;
+
+use crate::batch::module_name;
+use crate::lexer::{lex_tokens, LexError};
+use crate::parser::{parse_tokens, ASTNode, ParseError, MAX_FIELD_NUMBER};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+/// The language a data definition file is written in, known from its extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceFormat {
+    Fruko,
+    Proto,
+    JsonSchema,
+}
+
+impl SourceFormat {
+    /// Files with an extension no frontend handles are read as `.fruko` data definitions
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("proto") => SourceFormat::Proto,
+            Some("json") => SourceFormat::JsonSchema,
+            _ => SourceFormat::Fruko,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem found while reading a data definition
+/// location is a `line:position` for text formats, or a JSON pointer for JSON Schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub location: String,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: {}: {}", severity, self.location, self.message)
+    }
+}
+
+/// A data definition read by a frontend, along with the warnings found reading it
+#[derive(Debug)]
+pub struct Import {
+    pub ast: ASTNode,
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// The diagnostics of a data definition which could not be read, containing at least one error
+#[derive(Debug)]
+pub struct ImportError(pub Vec<Diagnostic>);
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let diagnostics = self.0.iter().map(Diagnostic::to_string).collect::<Vec<String>>();
+        f.write_str(&diagnostics.join("\n"))
+    }
+}
+
+impl Error for ImportError {}
+
+/// Helper struct, collecting the diagnostics of a frontend
+#[derive(Default)]
+pub(crate) struct Diagnostics(Vec<Diagnostic>);
+
+impl Diagnostics {
+    pub(crate) fn warning(&mut self, location: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Warning, location.into(), message.into());
+    }
+
+    pub(crate) fn error(&mut self, location: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Error, location.into(), message.into());
+    }
+
+    fn push(&mut self, severity: Severity, location: String, message: String) {
+        self.0.push(Diagnostic {
+            severity,
+            location,
+            message,
+        });
+    }
+
+    /// Completes the import, failing if any error was found
+    pub(crate) fn finish(self, ast: ASTNode) -> Result<Import, ImportError> {
+        if self.0.iter().any(|diagnostic| diagnostic.severity == Severity::Error) {
+            Err(ImportError(self.0))
+        } else {
+            Ok(Import { ast, warnings: self.0 })
+        }
+    }
+}
+
+/// Reads a data definition file in any supported format
+/// # Parameters
+/// path - The path of the file, choosing the frontend by its extension. The file stem names the
+/// root type of a JSON Schema without a title
+/// contents - The contents of the file
+pub fn import_file(path: &Path, contents: &str) -> Result<Import, ImportError> {
+    match SourceFormat::from_path(path) {
+        SourceFormat::Fruko => import_fruko(contents),
+        SourceFormat::Proto => proto::import(contents),
+        SourceFormat::JsonSchema => json_schema::import(contents, &module_name(path)),
+    }
+}
+
+fn import_fruko(contents: &str) -> Result<Import, ImportError> {
+    let mut diagnostics = Diagnostics::default();
+    let tokens = match lex_tokens(contents.to_owned()) {
+        Ok(tokens) => tokens,
+        Err(LexError::UnknownCharacterError(location)) => {
+            diagnostics.error(location.to_string(), "unknown character");
+            return Err(ImportError(diagnostics.0));
+        }
+    };
+    match parse_tokens(tokens) {
+        Ok(ast) => diagnostics.finish(ast),
+        Err(ParseError::UnexpectedToken(location)) => {
+            diagnostics.error(location.to_string(), "unexpected token");
+            Err(ImportError(diagnostics.0))
+        }
+        Err(ParseError::UnexpectedEndOfTokens) => {
+            diagnostics.error("end of file", "the file ended unexpectedly");
+            Err(ImportError(diagnostics.0))
+        }
+        Err(ParseError::InvalidFieldNumber(location, number)) => {
+            diagnostics.error(
+                location.to_string(),
+                format!("field number {} is not from 1 to {}", number, MAX_FIELD_NUMBER),
+            );
+            Err(ImportError(diagnostics.0))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_fruko_diagnostics() {
+        let error = import_file(Path::new("person.fruko"), "struct person { name: string").expect_err("should not parse");
+
+        assert_eq!(error.to_string(), "error: end of file: the file ended unexpectedly");
+    }
+}
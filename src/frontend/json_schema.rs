@@ -0,0 +1,399 @@
+/// Frontend for JSON Schema
+///
+/// Definitions under `$defs` (or `definitions`) which are objects become structs, and string
+/// enums become enums. The root schema is declared as well when it is an object or an enum, named
+/// by its `title`, or after the file otherwise. Other definitions, such as a constrained string,
+/// are not declared, and references to them are replaced by their type.
+///
+/// Properties which are not `required` become options, as do nullable types (`"type": [T, "null"]`,
+/// or `anyOf`/`oneOf` with a `null` schema). Objects and enums declared inline are declared at the
+/// top level, named by their `title` or property name. Integers use their `format` where given,
+/// and otherwise the smallest type fitting their `minimum` and `maximum`.
+///
+/// Unions, maps, tuples and references outside the document have no equivalent, and are errors.
+use crate::frontend::{Diagnostics, Import, ImportError};
+use crate::parser::{
+    ASTNode, DataDefinition, DataType, EnumMemberDeclaration, NamedStatementList, StructMemberDeclaration,
+};
+use serde_json::{Map, Value};
+
+/// Keywords definitions are declared under, in the current and older drafts of JSON Schema
+const DEFINITIONS_KEYWORDS: [&str; 2] = ["$defs", "definitions"];
+
+/// Reads a JSON Schema document into a data definition
+/// # Parameters
+/// contents - The JSON Schema document
+/// root_name - The name of the root type, used when the root schema has no title
+pub fn import(contents: &str, root_name: &str) -> Result<Import, ImportError> {
+    let mut diagnostics = Diagnostics::default();
+    let document: Value = match serde_json::from_str(contents) {
+        Ok(document) => document,
+        Err(error) => {
+            diagnostics.error(format!("{}:{}", error.line(), error.column()), format!("invalid JSON: {}", error));
+            return Err(ImportError(diagnostics.0));
+        }
+    };
+
+    let mut converter = SchemaConverter {
+        document: &document,
+        diagnostics,
+        declarations: Vec::new(),
+        declared: Vec::new(),
+        inlining: Vec::new(),
+    };
+    converter.convert_document(root_name);
+    let ast = ASTNode::DataDefinition(DataDefinition {
+        child_nodes: converter.declarations,
+    });
+    converter.diagnostics.finish(ast)
+}
+
+/// Helper struct, keeping track of the declarations made so far
+struct SchemaConverter<'a> {
+    document: &'a Value,
+    diagnostics: Diagnostics,
+    declarations: Vec<ASTNode>,
+    declared: Vec<String>,
+    /// References currently being replaced by their type, to detect recursion
+    inlining: Vec<String>,
+}
+
+impl<'a> SchemaConverter<'a> {
+    fn convert_document(&mut self, root_name: &str) {
+        for keyword in DEFINITIONS_KEYWORDS {
+            if let Some(definitions) = self.document.get(keyword).and_then(Value::as_object) {
+                for (name, schema) in definitions {
+                    if is_declaration(schema) {
+                        self.declare(name, schema, &pointer("#", &[keyword, name]));
+                    }
+                }
+            }
+        }
+
+        if is_declaration(self.document) {
+            let name = self.document.get("title").and_then(Value::as_str).unwrap_or(root_name);
+            self.declare(name, self.document, "#");
+        }
+    }
+
+    /// Declares a struct or enum for an object or enum schema
+    fn declare(&mut self, name: &str, schema: &'a Value, location: &str) {
+        if self.declared.iter().any(|declared| declared == name) {
+            self.diagnostics.error(location, format!("'{}' is declared more than once", name));
+            return;
+        }
+        self.declared.push(name.to_owned());
+
+        let declaration = if schema.get("enum").is_some() {
+            ASTNode::EnumDeclaration(self.convert_enum(name, schema, location))
+        } else {
+            ASTNode::StructDeclaration(self.convert_object(name, schema, location))
+        };
+        self.declarations.push(declaration);
+    }
+
+    fn convert_enum(&mut self, name: &str, schema: &Value, location: &str) -> NamedStatementList {
+        let mut enum_declaration = NamedStatementList::new(name.to_owned());
+        enum_declaration.description = description(schema);
+        for value in schema["enum"].as_array().into_iter().flatten() {
+            match value.as_str() {
+                Some(value) => enum_declaration
+                    .child_nodes
+                    .push(ASTNode::EnumMemberDeclaration(EnumMemberDeclaration { name: value.to_owned() })),
+                None => self.diagnostics.error(
+                    pointer(location, &["enum"]),
+                    format!("enum '{}' has the value {}, but only string values have an equivalent", name, value),
+                ),
+            }
+        }
+        enum_declaration
+    }
+
+    fn convert_object(&mut self, name: &str, schema: &'a Value, location: &str) -> NamedStatementList {
+        let required: Vec<&str> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect();
+
+        let mut struct_declaration = NamedStatementList::new(name.to_owned());
+        struct_declaration.description = description(schema);
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (property_name, property_schema) in properties.into_iter().flatten() {
+            let property_location = pointer(location, &["properties", property_name]);
+            let data_type = self.convert_type(property_schema, &property_location, property_name);
+            let data_type = match data_type {
+                DataType::Option(_) if !required.contains(&property_name.as_str()) => {
+                    self.diagnostics.warning(
+                        property_location,
+                        format!("'{}' is nullable and not required, so null and absent are no longer told apart", property_name),
+                    );
+                    data_type
+                }
+                data_type if !required.contains(&property_name.as_str()) => DataType::Option(Box::new(data_type)),
+                data_type => data_type,
+            };
+            struct_declaration
+                .child_nodes
+                .push(ASTNode::StructMemberDeclaration(StructMemberDeclaration {
+                    name: property_name.clone(),
+                    data_type: Box::new(ASTNode::TypeLiteral(data_type)),
+                    field_number: None,
+                    description: description(property_schema),
+                }));
+        }
+        struct_declaration
+    }
+
+    /// Converts the schema of a property or array item
+    /// # Parameters
+    /// value - The schema to convert
+    /// location - The JSON pointer of the schema, for diagnostics
+    /// name - The name an inline object or enum is declared under, unless it has a title
+    fn convert_type(&mut self, value: &'a Value, location: &str, name: &str) -> DataType {
+        let schema = match value {
+            Value::Object(schema) => schema,
+            _ => {
+                self.diagnostics.error(location, "boolean schemas have no equivalent");
+                return DataType::String;
+            }
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            return self.convert_reference(reference, location);
+        }
+        if let Some(union) = schema.get("anyOf").or_else(|| schema.get("oneOf")) {
+            return self.convert_union(union, location, name);
+        }
+        if schema.contains_key("allOf") {
+            self.diagnostics.error(location, "allOf has no equivalent");
+            return DataType::String;
+        }
+        if schema.contains_key("const") {
+            self.diagnostics.warning(location, "const is not kept, only the type of the value");
+        }
+        if is_declaration(value) {
+            let name = schema.get("title").and_then(Value::as_str).unwrap_or(name);
+            self.declare(name, value, location);
+            return DataType::UserDefined(name.to_owned());
+        }
+
+        match schema.get("type") {
+            Some(Value::String(type_name)) => self.convert_typed(type_name, schema, location, name),
+            Some(Value::Array(type_names)) => {
+                let type_names: Vec<&str> = type_names.iter().filter_map(Value::as_str).collect();
+                match type_names.as_slice() {
+                    [type_name, "null"] | ["null", type_name] => {
+                        DataType::Option(Box::new(self.convert_typed(type_name, schema, location, name)))
+                    }
+                    [type_name] => self.convert_typed(type_name, schema, location, name),
+                    _ => {
+                        self.diagnostics.error(location, format!("the union of {} has no equivalent", type_names.join(", ")));
+                        DataType::String
+                    }
+                }
+            }
+            _ => {
+                self.diagnostics.error(location, "the schema has no type");
+                DataType::String
+            }
+        }
+    }
+
+    fn convert_typed(&mut self, type_name: &str, schema: &'a Map<String, Value>, location: &str, name: &str) -> DataType {
+        match type_name {
+            "string" => {
+                let length = |keyword: &str| schema.get(keyword).and_then(Value::as_u64);
+                if length("minLength") == Some(1) && length("maxLength") == Some(1) {
+                    DataType::Char
+                } else {
+                    DataType::String
+                }
+            }
+            "integer" => convert_integer(schema),
+            "number" => match schema.get("format").and_then(Value::as_str) {
+                Some("float") => DataType::F32,
+                _ => DataType::F64,
+            },
+            "boolean" => DataType::Bool,
+            "array" => {
+                if schema.contains_key("prefixItems") {
+                    self.diagnostics.error(location, "tuples have no equivalent");
+                }
+                match schema.get("items") {
+                    Some(items) => {
+                        let item_location = pointer(location, &["items"]);
+                        DataType::Array(Box::new(self.convert_type(items, &item_location, name)))
+                    }
+                    None => {
+                        self.diagnostics.error(location, "arrays need an items schema");
+                        DataType::Array(Box::new(DataType::String))
+                    }
+                }
+            }
+            "object" => {
+                self.diagnostics.error(location, "objects without properties, such as maps, have no equivalent");
+                DataType::String
+            }
+            _ => {
+                self.diagnostics.error(location, format!("type '{}' has no equivalent", type_name));
+                DataType::String
+            }
+        }
+    }
+
+    /// Converts a reference to a definition. A definition which is not declared is replaced by its
+    /// type
+    fn convert_reference(&mut self, reference: &str, location: &str) -> DataType {
+        let definition = DEFINITIONS_KEYWORDS.iter().find_map(|keyword| {
+            let name = reference.strip_prefix(&format!("#/{}/", keyword))?;
+            Some((name, self.document.get(keyword)?.get(name)?))
+        });
+        let Some((name, schema)) = definition else {
+            self.diagnostics.error(location, format!("reference '{}' is not to a definition of this document", reference));
+            return DataType::String;
+        };
+
+        if is_declaration(schema) {
+            return DataType::UserDefined(name.to_owned());
+        }
+        if self.inlining.iter().any(|inlining| inlining == reference) {
+            self.diagnostics.error(location, format!("reference '{}' is recursive, but is not an object", reference));
+            return DataType::String;
+        }
+        self.inlining.push(reference.to_owned());
+        let data_type = self.convert_type(schema, reference, name);
+        self.inlining.pop();
+        data_type
+    }
+
+    /// Converts an `anyOf` or `oneOf`, which only has an equivalent when it makes a type nullable
+    fn convert_union(&mut self, union: &'a Value, location: &str, name: &str) -> DataType {
+        let schemas = union.as_array().map(Vec::as_slice).unwrap_or_default();
+        let is_null = |schema: &Value| schema.get("type").and_then(Value::as_str) == Some("null");
+        match schemas {
+            [schema, null] | [null, schema] if is_null(null) && !is_null(schema) => {
+                DataType::Option(Box::new(self.convert_type(schema, location, name)))
+            }
+            _ => {
+                self.diagnostics.error(location, "unions have no equivalent, other than with null");
+                DataType::String
+            }
+        }
+    }
+}
+
+/// Whether a schema is declared as a struct or enum, being an object with properties or an enum
+fn is_declaration(schema: &Value) -> bool {
+    schema.get("properties").is_some() || schema.get("enum").is_some()
+}
+
+/// Converts an integer, by its format if it has one, otherwise by its bounds
+fn convert_integer(schema: &Map<String, Value>) -> DataType {
+    match schema.get("format").and_then(Value::as_str) {
+        Some("int8") => return DataType::I8,
+        Some("uint8") => return DataType::U8,
+        Some("int16") => return DataType::I16,
+        Some("uint16") => return DataType::U16,
+        Some("int32") => return DataType::I32,
+        Some("uint32") => return DataType::U32,
+        Some("int64") => return DataType::I64,
+        Some("uint64") => return DataType::U64,
+        _ => {}
+    }
+
+    let bound = |keyword: &str| schema.get(keyword).and_then(Value::as_f64);
+    match (bound("minimum"), bound("maximum")) {
+        (Some(minimum), Some(maximum)) if minimum >= 0.0 => match maximum {
+            maximum if maximum <= u8::MAX as f64 => DataType::U8,
+            maximum if maximum <= u16::MAX as f64 => DataType::U16,
+            maximum if maximum <= u32::MAX as f64 => DataType::U32,
+            _ => DataType::U64,
+        },
+        (Some(minimum), None) if minimum >= 0.0 => DataType::U64,
+        (Some(minimum), Some(maximum)) => match (minimum, maximum) {
+            (minimum, maximum) if minimum >= i8::MIN as f64 && maximum <= i8::MAX as f64 => DataType::I8,
+            (minimum, maximum) if minimum >= i16::MIN as f64 && maximum <= i16::MAX as f64 => DataType::I16,
+            (minimum, maximum) if minimum >= i32::MIN as f64 && maximum <= i32::MAX as f64 => DataType::I32,
+            _ => DataType::I64,
+        },
+        _ => DataType::I64,
+    }
+}
+
+/// The `description` of a schema, which becomes the documentation of its declaration or member
+fn description(schema: &Value) -> Option<String> {
+    schema.get("description").and_then(Value::as_str).map(str::to_owned)
+}
+
+/// Appends keys to a JSON pointer, escaping them
+fn pointer(base: &str, keys: &[&str]) -> String {
+    keys.iter().fold(base.to_owned(), |pointer, key| {
+        format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Schema;
+
+    const JSON_SCHEMA: &str = r##"{
+    "title": "person",
+    "type": "object",
+    "required": ["name", "age", "mood"],
+    "properties": {
+        "name": { "type": "string" },
+        "age": { "type": "integer", "minimum": 0, "maximum": 255 },
+        "nickname": { "type": "string" },
+        "scores": { "type": "array", "items": { "type": ["number", "null"] } },
+        "mood": { "$ref": "#/$defs/mood" },
+        "id": { "$ref": "#/$defs/id" }
+    },
+    "$defs": {
+        "mood": { "type": "string", "enum": ["happy", "sad"] },
+        "id": { "type": "integer", "format": "int64" }
+    }
+}"##;
+
+    #[test]
+    fn test_import_json_schema() {
+        let import = import(JSON_SCHEMA, "root").expect("should import");
+        assert!(import.warnings.is_empty());
+
+        let schema = Schema::from_ast(&import.ast).expect("should build schema");
+        let names: Vec<&str> = schema.declarations.iter().map(|declaration| declaration.name()).collect();
+        assert_eq!(names, vec!["mood", "person"]);
+
+        let person = schema.structs().next().expect("should have person");
+        let members: Vec<(&str, &DataType)> = person
+            .members
+            .iter()
+            .map(|member| (member.name.as_str(), &member.data_type))
+            .collect();
+        assert_eq!(
+            members,
+            vec![
+                ("name", &DataType::String),
+                ("age", &DataType::U8),
+                ("nickname", &DataType::Option(Box::new(DataType::String))),
+                ("scores", &DataType::Option(Box::new(DataType::Array(Box::new(DataType::Option(Box::new(DataType::F64))))))),
+                ("mood", &DataType::UserDefined("mood".to_owned())),
+                ("id", &DataType::Option(Box::new(DataType::I64))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_json_schema_diagnostics() {
+        let text = r#"{ "type": "object", "properties": { "tags": { "type": "object", "additionalProperties": { "type": "string" } } } }"#;
+        let error = import(text, "root").expect_err("should not import");
+
+        assert_eq!(
+            error.to_string(),
+            "error: #/properties/tags: objects without properties, such as maps, have no equivalent"
+        );
+    }
+}
@@ -0,0 +1,636 @@
+/// Frontend for Protocol Buffers, reading a subset of the proto3 syntax
+///
+/// Messages become structs and enums become enums. Nested declarations are moved to the top level,
+/// so their names must be unique within the file, and references to other types keep only the last
+/// part of their name. Fields keep their field numbers, `optional` fields become options and
+/// `repeated` fields arrays.
+///
+/// Approximated, with a warning: `bytes` as `array(u8)`, maps as arrays of key value structs,
+/// oneofs as separate optional members, and imports and services, which are ignored. Enum values
+/// lose their numbers; the values are stripped of the enum name prefix, and an `UNSPECIFIED` zero
+/// value is dropped. proto2, editions, extensions, groups and well known types are errors.
+use crate::frontend::{Diagnostics, Import, ImportError};
+use crate::naming::{pascal_case, screaming_snake_case};
+use crate::parser::{
+    ASTNode, DataDefinition, DataType, EnumMemberDeclaration, NamedStatementList, StructMemberDeclaration,
+};
+
+/// Reads a proto3 file into a data definition
+pub fn import(contents: &str) -> Result<Import, ImportError> {
+    let mut diagnostics = Diagnostics::default();
+    let tokens = match lex(contents) {
+        Ok(tokens) => tokens,
+        Err(location) => {
+            diagnostics.error(location, "unknown character");
+            return Err(ImportError(diagnostics.0));
+        }
+    };
+
+    let mut parser = ProtoParser {
+        tokens,
+        index: 0,
+        diagnostics,
+        declarations: Vec::new(),
+        declared: Vec::new(),
+    };
+    // A syntax error has already been reported as a diagnostic
+    let _ = parser.parse_file();
+    let ast = ASTNode::DataDefinition(DataDefinition {
+        child_nodes: parser.declarations,
+    });
+    parser.diagnostics.finish(ast)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TokenKind {
+    /// An identifier, possibly qualified with dots
+    Identifier(String),
+    /// A numeric literal, kept as written
+    Number(String),
+    StringLiteral(String),
+    Symbol(char),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    location: String,
+}
+
+/// Splits a proto file into tokens, discarding whitespace and comments
+/// # Returns
+/// The tokens, or the location of a character which cannot start a token
+fn lex(contents: &str) -> Result<Vec<Token>, String> {
+    let characters: Vec<char> = contents.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    let mut line = 1;
+    let mut line_start = 0;
+
+    while index < characters.len() {
+        let character = characters[index];
+        let location = format!("{}:{}", line, index - line_start + 1);
+        let start = index;
+
+        if character == '\n' {
+            line += 1;
+            line_start = index + 1;
+            index += 1;
+            continue;
+        } else if character.is_whitespace() {
+            index += 1;
+            continue;
+        } else if characters[index..].starts_with(&['/', '/']) {
+            while index < characters.len() && characters[index] != '\n' {
+                index += 1;
+            }
+            continue;
+        } else if characters[index..].starts_with(&['/', '*']) {
+            index += 2;
+            while index < characters.len() && !characters[index..].starts_with(&['*', '/']) {
+                if characters[index] == '\n' {
+                    line += 1;
+                    line_start = index + 1;
+                }
+                index += 1;
+            }
+            index += 2;
+            continue;
+        }
+
+        let kind = if character.is_alphabetic() || character == '_' || character == '.' && is_identifier_start(characters.get(index + 1)) {
+            index += 1;
+            while index < characters.len() && (characters[index].is_alphanumeric() || "_.".contains(characters[index])) {
+                index += 1;
+            }
+            TokenKind::Identifier(characters[start..index].iter().collect())
+        } else if character.is_ascii_digit() || character == '-' {
+            index += 1;
+            while index < characters.len() && (characters[index].is_alphanumeric() || characters[index] == '.') {
+                index += 1;
+            }
+            TokenKind::Number(characters[start..index].iter().collect())
+        } else if character == '"' || character == '\'' {
+            index += 1;
+            while index < characters.len() && characters[index] != character {
+                if characters[index] == '\\' {
+                    index += 1;
+                }
+                index += 1;
+            }
+            index += 1;
+            TokenKind::StringLiteral(characters[start + 1..(index - 1).min(characters.len())].iter().collect())
+        } else if "{}[]()<>;=,".contains(character) {
+            index += 1;
+            TokenKind::Symbol(character)
+        } else {
+            return Err(location);
+        };
+        tokens.push(Token { kind, location });
+    }
+    Ok(tokens)
+}
+
+fn is_identifier_start(character: Option<&char>) -> bool {
+    character.is_some_and(|character| character.is_alphabetic() || *character == '_')
+}
+
+/// A syntax error, which has been reported as a diagnostic and ends parsing
+struct SyntaxError;
+
+type ParseResult<T> = Result<T, SyntaxError>;
+
+/// A field of a message, before its type has been labelled
+struct Field {
+    type_name: String,
+    name: String,
+    field_number: u32,
+    location: String,
+}
+
+/// Helper struct, keeping track of the position in the tokens and of the declarations found
+struct ProtoParser {
+    tokens: Vec<Token>,
+    index: usize,
+    diagnostics: Diagnostics,
+    declarations: Vec<ASTNode>,
+    declared: Vec<String>,
+}
+
+impl ProtoParser {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.index).map(|token| &token.kind)
+    }
+
+    /// The location of the next token, for diagnostics
+    fn location(&self) -> String {
+        match self.tokens.get(self.index) {
+            Some(token) => token.location.clone(),
+            None => "end of file".to_owned(),
+        }
+    }
+
+    fn next(&mut self) -> ParseResult<Token> {
+        match self.tokens.get(self.index) {
+            Some(token) => {
+                self.index += 1;
+                Ok(token.clone())
+            }
+            None => self.syntax_error("the file ended unexpectedly"),
+        }
+    }
+
+    fn syntax_error<T>(&mut self, message: &str) -> ParseResult<T> {
+        let location = self.location();
+        self.diagnostics.error(location, message);
+        Err(SyntaxError)
+    }
+
+    fn is_symbol(&self, symbol: char) -> bool {
+        self.peek() == Some(&TokenKind::Symbol(symbol))
+    }
+
+    fn expect_symbol(&mut self, symbol: char) -> ParseResult<()> {
+        if self.is_symbol(symbol) {
+            self.index += 1;
+            Ok(())
+        } else {
+            self.syntax_error(&format!("expected '{}'", symbol))
+        }
+    }
+
+    fn expect_identifier(&mut self) -> ParseResult<String> {
+        match self.peek() {
+            Some(TokenKind::Identifier(identifier)) => {
+                let identifier = identifier.clone();
+                self.index += 1;
+                Ok(identifier)
+            }
+            _ => self.syntax_error("expected a name"),
+        }
+    }
+
+    fn expect_number(&mut self) -> ParseResult<String> {
+        match self.peek() {
+            Some(TokenKind::Number(number)) => {
+                let number = number.clone();
+                self.index += 1;
+                Ok(number)
+            }
+            _ => self.syntax_error("expected a number"),
+        }
+    }
+
+    fn expect_string(&mut self) -> ParseResult<String> {
+        match self.peek() {
+            Some(TokenKind::StringLiteral(string)) => {
+                let string = string.clone();
+                self.index += 1;
+                Ok(string)
+            }
+            _ => self.syntax_error("expected a string"),
+        }
+    }
+
+    /// Skips to the end of the current statement, or past the block it opens
+    fn skip_statement(&mut self) -> ParseResult<()> {
+        let mut depth = 0;
+        loop {
+            match self.next()?.kind {
+                TokenKind::Symbol(';') if depth == 0 => return Ok(()),
+                TokenKind::Symbol('{' | '[' | '(') => depth += 1,
+                TokenKind::Symbol('}' | ']' | ')') => {
+                    depth -= 1;
+                    if depth == 0 && !self.is_symbol(';') && self.tokens[self.index - 1].kind == TokenKind::Symbol('}') {
+                        return Ok(());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Skips the options of a field or enum value, given in square brackets
+    fn skip_options(&mut self) -> ParseResult<()> {
+        if self.is_symbol('[') {
+            while !self.is_symbol(']') {
+                self.next()?;
+            }
+            self.index += 1;
+        }
+        Ok(())
+    }
+
+    fn parse_file(&mut self) -> ParseResult<()> {
+        while let Some(token) = self.tokens.get(self.index).cloned() {
+            match &token.kind {
+                TokenKind::Identifier(keyword) => match keyword.as_str() {
+                    "syntax" => {
+                        self.index += 1;
+                        self.expect_symbol('=')?;
+                        let syntax = self.expect_string()?;
+                        if syntax != "proto3" {
+                            self.diagnostics.error(
+                                token.location.clone(),
+                                format!("only proto3 is supported, the file uses {}", syntax),
+                            );
+                        }
+                        self.expect_symbol(';')?;
+                    }
+                    "edition" => {
+                        self.diagnostics.error(token.location.clone(), "editions are not supported, only proto3");
+                        self.skip_statement()?;
+                    }
+                    "import" => {
+                        self.index += 1;
+                        if matches!(self.peek(), Some(TokenKind::Identifier(modifier)) if modifier == "public" || modifier == "weak") {
+                            self.index += 1;
+                        }
+                        let path = self.expect_string()?;
+                        self.diagnostics.warning(
+                            token.location.clone(),
+                            format!("import of '{}' is not followed, its types must be compiled in the same batch", path),
+                        );
+                        self.expect_symbol(';')?;
+                    }
+                    "package" | "option" => self.skip_statement()?,
+                    "message" => {
+                        self.index += 1;
+                        self.parse_message()?;
+                    }
+                    "enum" => {
+                        self.index += 1;
+                        self.parse_enum()?;
+                    }
+                    "service" => {
+                        self.diagnostics.warning(token.location.clone(), "services have no equivalent, and are ignored");
+                        self.skip_statement()?;
+                    }
+                    "extend" => {
+                        self.diagnostics.error(token.location.clone(), "extensions have no equivalent");
+                        self.skip_statement()?;
+                    }
+                    _ => return self.syntax_error(&format!("unexpected '{}'", keyword)),
+                },
+                TokenKind::Symbol(';') => self.index += 1,
+                _ => return self.syntax_error("expected a declaration"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Records a declaration. Nested declarations are moved to the top level, so every declaration
+    /// in the file must have a different name
+    fn declare(&mut self, name: &str, location: String, declaration: ASTNode) {
+        if self.declared.iter().any(|declared| declared == name) {
+            self.diagnostics.error(
+                location,
+                format!("'{}' is declared more than once; nested declarations are moved to the top level, so need unique names", name),
+            );
+        }
+        self.declared.push(name.to_owned());
+        self.declarations.push(declaration);
+    }
+
+    fn parse_message(&mut self) -> ParseResult<()> {
+        let location = self.location();
+        let name = self.expect_identifier()?;
+        self.expect_symbol('{')?;
+
+        let mut members = Vec::new();
+        while !self.is_symbol('}') {
+            let token = self.next()?;
+            let keyword = match &token.kind {
+                TokenKind::Identifier(keyword) => keyword.clone(),
+                TokenKind::Symbol(';') => continue,
+                _ => {
+                    self.index -= 1;
+                    return self.syntax_error("expected a field");
+                }
+            };
+
+            match keyword.as_str() {
+                "message" => self.parse_message()?,
+                "enum" => self.parse_enum()?,
+                "option" | "reserved" => self.skip_statement()?,
+                "extensions" | "extend" => {
+                    self.diagnostics.error(token.location.clone(), "extensions have no equivalent");
+                    self.skip_statement()?;
+                }
+                "required" | "group" => {
+                    self.diagnostics.error(token.location.clone(), format!("'{}' is proto2 syntax, which is not supported", keyword));
+                    self.skip_statement()?;
+                }
+                "oneof" => {
+                    let oneof_name = self.expect_identifier()?;
+                    self.diagnostics.warning(
+                        token.location.clone(),
+                        format!("oneof '{}' becomes separate optional members, which does not ensure that only one is set", oneof_name),
+                    );
+                    self.expect_symbol('{')?;
+                    while !self.is_symbol('}') {
+                        if matches!(self.peek(), Some(TokenKind::Identifier(keyword)) if keyword == "option") {
+                            self.skip_statement()?;
+                            continue;
+                        }
+                        let type_name = self.expect_identifier()?;
+                        let field = self.parse_field(type_name)?;
+                        let data_type = DataType::Option(Box::new(self.field_type(&field)));
+                        members.push(member(field, data_type));
+                    }
+                    self.expect_symbol('}')?;
+                }
+                "map" => {
+                    self.expect_symbol('<')?;
+                    let key_type = self.expect_identifier()?;
+                    self.expect_symbol(',')?;
+                    let value_type = self.expect_identifier()?;
+                    self.expect_symbol('>')?;
+                    let field = self.parse_field(String::new())?;
+                    let entry_name = format!("{}{}Entry", name, pascal_case(&field.name));
+                    self.diagnostics.warning(
+                        token.location.clone(),
+                        format!("map '{}' becomes an array of '{}', which does not ensure that keys are unique", field.name, entry_name),
+                    );
+
+                    let entry_members = [("key", key_type, 1), ("value", value_type, 2)]
+                        .into_iter()
+                        .map(|(entry_member_name, type_name, field_number)| {
+                            let entry_field = Field {
+                                type_name,
+                                name: entry_member_name.to_owned(),
+                                field_number,
+                                location: field.location.clone(),
+                            };
+                            let data_type = self.field_type(&entry_field);
+                            member(entry_field, data_type)
+                        })
+                        .collect();
+                    let entry = ASTNode::StructDeclaration(NamedStatementList {
+                        name: entry_name.clone(),
+                        child_nodes: entry_members,
+                        description: None,
+                    });
+                    self.declare(&entry_name, field.location.clone(), entry);
+                    members.push(member(field, DataType::Array(Box::new(DataType::UserDefined(entry_name)))));
+                }
+                "optional" | "repeated" => {
+                    let type_name = self.expect_identifier()?;
+                    let field = self.parse_field(type_name)?;
+                    let data_type = Box::new(self.field_type(&field));
+                    let data_type = match keyword.as_str() {
+                        "optional" => DataType::Option(data_type),
+                        _ => DataType::Array(data_type),
+                    };
+                    members.push(member(field, data_type));
+                }
+                _ => {
+                    let field = self.parse_field(keyword)?;
+                    let data_type = self.field_type(&field);
+                    members.push(member(field, data_type));
+                }
+            }
+        }
+        self.expect_symbol('}')?;
+
+        let declaration = ASTNode::StructDeclaration(NamedStatementList {
+            name: name.clone(),
+            child_nodes: members,
+            description: None,
+        });
+        self.declare(&name, location, declaration);
+        Ok(())
+    }
+
+    /// Parses the rest of a field following its type, being its name, number and options
+    fn parse_field(&mut self, type_name: String) -> ParseResult<Field> {
+        let location = self.location();
+        let name = self.expect_identifier()?;
+        self.expect_symbol('=')?;
+        let number = self.expect_number()?;
+        let field_number = match number.parse() {
+            Ok(field_number) => field_number,
+            Err(_) => {
+                self.diagnostics.error(location.clone(), format!("'{}' is not a valid field number", number));
+                0
+            }
+        };
+        self.skip_options()?;
+        self.expect_symbol(';')?;
+
+        Ok(Field {
+            type_name,
+            name,
+            field_number,
+            location,
+        })
+    }
+
+    /// Converts the type of a field. A reference to another declaration keeps only the last part
+    /// of its name, as declarations are not nested in a data definition
+    fn field_type(&mut self, field: &Field) -> DataType {
+        match field.type_name.as_str() {
+            "double" => DataType::F64,
+            "float" => DataType::F32,
+            "int32" | "sint32" | "sfixed32" => DataType::I32,
+            "int64" | "sint64" | "sfixed64" => DataType::I64,
+            "uint32" | "fixed32" => DataType::U32,
+            "uint64" | "fixed64" => DataType::U64,
+            "bool" => DataType::Bool,
+            "string" => DataType::String,
+            "bytes" => {
+                self.diagnostics.warning(field.location.clone(), format!("bytes field '{}' becomes array(u8)", field.name));
+                DataType::Array(Box::new(DataType::U8))
+            }
+            type_name => {
+                let type_name = type_name.trim_start_matches('.');
+                if type_name.starts_with("google.protobuf.") {
+                    self.diagnostics.error(
+                        field.location.clone(),
+                        format!("well known type '{}' of field '{}' has no equivalent", type_name, field.name),
+                    );
+                }
+                DataType::UserDefined(type_name.rsplit('.').next().unwrap_or(type_name).to_owned())
+            }
+        }
+    }
+
+    fn parse_enum(&mut self) -> ParseResult<()> {
+        let location = self.location();
+        let name = self.expect_identifier()?;
+        self.expect_symbol('{')?;
+
+        let mut values = Vec::new();
+        while !self.is_symbol('}') {
+            if self.is_symbol(';') {
+                self.index += 1;
+                continue;
+            }
+            let value_name = self.expect_identifier()?;
+            if value_name == "option" || value_name == "reserved" {
+                self.skip_statement()?;
+                continue;
+            }
+            self.expect_symbol('=')?;
+            let number = self.expect_number()?;
+            self.skip_options()?;
+            self.expect_symbol(';')?;
+            values.push((value_name, number.parse::<i64>().ok()));
+        }
+        self.expect_symbol('}')?;
+
+        let prefix = format!("{}_", screaming_snake_case(&name));
+        if values.iter().all(|(value_name, _)| value_name.len() > prefix.len() && value_name.starts_with(&prefix)) {
+            for (value_name, _) in &mut values {
+                value_name.replace_range(..prefix.len(), "");
+            }
+        }
+        if values.first().is_some_and(|(value_name, number)| value_name == "UNSPECIFIED" && *number == Some(0)) {
+            values.remove(0);
+        }
+
+        let numbers: Vec<Option<i64>> = values.iter().map(|(_, number)| *number).collect();
+        let is_sequential = |start: i64| numbers.iter().zip(start..).all(|(number, expected)| *number == Some(expected));
+        if !is_sequential(0) && !is_sequential(1) {
+            self.diagnostics.warning(
+                location.clone(),
+                format!("the values of enum '{}' are not numbered in declaration order, and their numbers are not kept", name),
+            );
+        }
+
+        let members = values
+            .into_iter()
+            .map(|(value_name, _)| ASTNode::EnumMemberDeclaration(EnumMemberDeclaration { name: value_name }))
+            .collect();
+        let declaration = ASTNode::EnumDeclaration(NamedStatementList {
+            name: name.clone(),
+            child_nodes: members,
+            description: None,
+        });
+        self.declare(&name, location, declaration);
+        Ok(())
+    }
+}
+
+fn member(field: Field, data_type: DataType) -> ASTNode {
+    ASTNode::StructMemberDeclaration(StructMemberDeclaration {
+        name: field.name,
+        data_type: Box::new(ASTNode::TypeLiteral(data_type)),
+        field_number: Some(field.field_number),
+        description: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::Severity;
+    use crate::schema::Schema;
+
+    const PROTO: &str = r#"
+syntax = "proto3";
+package acme.v1;
+
+// A person
+message Person {
+    string name = 1;
+    optional uint32 age = 4 [deprecated = true];
+    repeated Address addresses = 2;
+    Mood mood = 3;
+
+    message Address {
+        string street = 1;
+    }
+}
+
+enum Mood {
+    MOOD_UNSPECIFIED = 0;
+    MOOD_HAPPY = 1;
+    MOOD_SAD = 2;
+}
+"#;
+
+    #[test]
+    fn test_import_proto() {
+        let import = import(PROTO).expect("should import");
+        assert!(import.warnings.is_empty());
+
+        let schema = Schema::from_ast(&import.ast).expect("should build schema");
+        let names: Vec<&str> = schema.declarations.iter().map(|declaration| declaration.name()).collect();
+        assert_eq!(names, vec!["Address", "Person", "Mood"]);
+
+        let person = schema.structs().find(|struct_definition| struct_definition.name == "Person").expect("should have Person");
+        let members: Vec<(&str, &DataType, Option<u32>)> = person
+            .members
+            .iter()
+            .map(|member| (member.name.as_str(), &member.data_type, member.field_number))
+            .collect();
+        assert_eq!(
+            members,
+            vec![
+                ("name", &DataType::String, Some(1)),
+                ("age", &DataType::Option(Box::new(DataType::U32)), Some(4)),
+                ("addresses", &DataType::Array(Box::new(DataType::UserDefined("Address".to_owned()))), Some(2)),
+                ("mood", &DataType::UserDefined("Mood".to_owned()), Some(3)),
+            ]
+        );
+        assert_eq!(schema.enums().next().expect("should have Mood").members, vec!["HAPPY", "SAD"]);
+    }
+
+    #[test]
+    fn test_import_proto_diagnostics() {
+        let text = r#"syntax = "proto3";
+message Event {
+    map<string, int64> counts = 1;
+    google.protobuf.Timestamp time = 2;
+}"#;
+        let error = import(text).expect_err("should not import");
+
+        let diagnostics: Vec<(Severity, &str)> = error
+            .0
+            .iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.location.as_str()))
+            .collect();
+        assert_eq!(diagnostics, vec![(Severity::Warning, "3:5"), (Severity::Error, "4:31")]);
+    }
+}
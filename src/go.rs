@@ -0,0 +1,247 @@
+/// Generation for Go
+///
+/// Structs become exported struct types, whose fields are exported by converting their names to
+/// `PascalCase`, and tagged with their original name for `encoding/json`. `Option` members are
+/// pointers tagged `omitempty`, and `Array` members slices. Inline declarations are hoisted out of
+/// line. Types from other files of a batch need no imports, as the files share a package.
+///
+/// Enums become a named `int` type with a typed `const` block using `iota`. By default they also
+/// implement `encoding.TextMarshaler` and `encoding.TextUnmarshaler`, so they are encoded as their
+/// member names, the same as in other targets.
+///
+/// Supported options:
+/// - `package`: the package clause, `models` by default
+/// - `enum_encoding`: `string` (the default) encodes enums as their member names, `int` as their
+///   numeric value
+use crate::compilation_target::{CompilationError, CompilationInfo, CompilationTarget};
+use crate::naming::{pascal_case, words};
+use crate::parser::{ASTNode, DataType};
+use crate::schema::{Declaration, EnumDefinition, Schema, StructDefinition};
+
+const DEFAULT_PACKAGE: &str = "models";
+
+/// Words written in all capitals in Go names, following the Go conventions
+const INITIALISMS: [&str; 8] = ["api", "http", "id", "json", "uri", "url", "uuid", "xml"];
+
+pub struct GoGenerator;
+
+impl CompilationTarget for GoGenerator {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        let package = compilation_info.options.get("package").unwrap_or(DEFAULT_PACKAGE);
+        let string_enums = compilation_info.options.get_choice("enum_encoding", &["string", "int"], "string")? == "string";
+        let schema = Schema::from_ast(ast)?;
+
+        let imports = if string_enums && schema.enums().next().is_some() {
+            "\nimport \"fmt\"\n"
+        } else {
+            ""
+        };
+        let declarations = schema
+            .declarations
+            .iter()
+            .map(|declaration| match declaration {
+                Declaration::Struct(struct_definition) => generate_struct(struct_definition),
+                Declaration::Enum(enum_definition) => generate_enum(enum_definition, string_enums),
+            })
+            .map(|declaration| format!("\n{}", declaration))
+            .collect::<String>();
+
+        Ok(format!(
+            "{}\npackage {}\n{}{}",
+            generate_preamble(compilation_info),
+            package,
+            imports,
+            declarations
+        ))
+    }
+}
+
+fn generate_preamble(compilation_info: &CompilationInfo) -> String {
+    compilation_info.preamble_comments.iter()
+        .map(|preamble| format!("// {}", preamble))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Converts a name to an exported Go name
+fn exported_name(name: &str) -> String {
+    words(name)
+        .iter()
+        .map(|word| {
+            if INITIALISMS.contains(&word.as_str()) {
+                word.to_uppercase()
+            } else {
+                pascal_case(word)
+            }
+        })
+        .collect()
+}
+
+/// Generates a struct, aligning its fields the way `gofmt` does
+fn generate_struct(struct_definition: &StructDefinition) -> String {
+    let fields: Vec<[String; 3]> = struct_definition
+        .members
+        .iter()
+        .map(|member| {
+            let tag = match member.data_type {
+                DataType::Option(_) => format!("`json:\"{},omitempty\"`", member.name),
+                _ => format!("`json:\"{}\"`", member.name),
+            };
+            [exported_name(&member.name), generate_type_name(&member.data_type), tag]
+        })
+        .collect();
+    let name_width = fields.iter().map(|[name, _, _]| name.len()).max().unwrap_or_default();
+    let type_width = fields.iter().map(|[_, type_name, _]| type_name.len()).max().unwrap_or_default();
+
+    let fields = fields
+        .iter()
+        .map(|[name, type_name, tag]| format!("\t{:name_width$} {:type_width$} {}\n", name, type_name, tag))
+        .collect::<String>();
+    format!("type {} struct {{\n{}}}\n", exported_name(&struct_definition.name), fields)
+}
+
+fn generate_enum(enum_definition: &EnumDefinition, string_enums: bool) -> String {
+    let name = exported_name(&enum_definition.name);
+    let constants = enum_definition
+        .members
+        .iter()
+        .enumerate()
+        .map(|(index, member)| match index {
+            0 => format!("\t{}{} {} = iota\n", name, exported_name(member), name),
+            _ => format!("\t{}{}\n", name, exported_name(member)),
+        })
+        .collect::<String>();
+
+    let mut code = format!("type {} int\n", name);
+    if !constants.is_empty() {
+        code += &format!("\nconst (\n{})\n", constants);
+    }
+    if string_enums {
+        code += &generate_enum_text_encoding(enum_definition, &name);
+    }
+    code
+}
+
+/// Generates the methods encoding an enum as its member names
+fn generate_enum_text_encoding(enum_definition: &EnumDefinition, name: &str) -> String {
+    let mut characters = name.chars();
+    let names_variable = match characters.next() {
+        Some(first) => format!("{}{}Names", first.to_lowercase(), characters.as_str()),
+        None => "names".to_owned(),
+    };
+    let member_names = enum_definition
+        .members
+        .iter()
+        .map(|member| format!("\"{}\"", member))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    format!(
+        "
+var {names} = [...]string{{{member_names}}}
+
+func (value {name}) String() string {{
+\tif value < 0 || int(value) >= len({names}) {{
+\t\treturn fmt.Sprintf(\"{name}(%d)\", int(value))
+\t}}
+\treturn {names}[value]
+}}
+
+func (value {name}) MarshalText() ([]byte, error) {{
+\tif value < 0 || int(value) >= len({names}) {{
+\t\treturn nil, fmt.Errorf(\"invalid {name} %d\", int(value))
+\t}}
+\treturn []byte({names}[value]), nil
+}}
+
+func (value *{name}) UnmarshalText(text []byte) error {{
+\tfor index, name := range {names} {{
+\t\tif name == string(text) {{
+\t\t\t*value = {name}(index)
+\t\t\treturn nil
+\t\t}}
+\t}}
+\treturn fmt.Errorf(\"unknown {name} %q\", text)
+}}
+",
+        names = names_variable,
+        name = name,
+        member_names = member_names
+    )
+}
+
+/// Generates the type names. Chars are strings, so they are encoded the same as in other targets,
+/// rather than as the number a `rune` is encoded as
+fn generate_type_name(data_type: &DataType) -> String {
+    match data_type {
+        DataType::U8 => "uint8".to_owned(),
+        DataType::I8 => "int8".to_owned(),
+        DataType::U16 => "uint16".to_owned(),
+        DataType::I16 => "int16".to_owned(),
+        DataType::U32 => "uint32".to_owned(),
+        DataType::I32 => "int32".to_owned(),
+        DataType::U64 => "uint64".to_owned(),
+        DataType::I64 => "int64".to_owned(),
+        DataType::F32 => "float32".to_owned(),
+        DataType::F64 => "float64".to_owned(),
+        DataType::Char | DataType::String => "string".to_owned(),
+        DataType::Bool => "bool".to_owned(),
+        DataType::Option(inner_type) => format!("*{}", generate_type_name(inner_type)),
+        DataType::Array(inner_type) => format!("[]{}", generate_type_name(inner_type)),
+        DataType::UserDefined(name) => exported_name(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+
+    const TEXT: &str = "struct person { userId: u64, nickname: option(string), scores: array(f32), mood: enum mood { happy, verySad, } }";
+
+    const GENERATED_CODE: &str = "
+package api
+
+type Mood int
+
+const (
+\tMoodHappy Mood = iota
+\tMoodVerySad
+)
+
+type Person struct {
+\tUserID   uint64    `json:\"userId\"`
+\tNickname *string   `json:\"nickname,omitempty\"`
+\tScores   []float32 `json:\"scores\"`
+\tMood     Mood      `json:\"mood\"`
+}
+";
+
+    #[test]
+    fn test_generate_go() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+        let mut compilation_info = CompilationInfo::default();
+        compilation_info.options.set("package", "api");
+        compilation_info.options.set("enum_encoding", "int");
+
+        assert_eq!(
+            GoGenerator
+                .generate_code(&ast, &compilation_info)
+                .expect("should generate code"),
+            GENERATED_CODE
+        );
+    }
+
+    #[test]
+    fn test_generate_go_string_enums() {
+        let ast = parse_tokens(lex_tokens("enum mood { happy, }".to_owned()).expect("should lex")).expect("should parse");
+
+        let generated_code = GoGenerator
+            .generate_code(&ast, &CompilationInfo::default())
+            .expect("should generate code");
+        assert!(generated_code.starts_with("\npackage models\n\nimport \"fmt\"\n"));
+        assert!(generated_code.contains("var moodNames = [...]string{\"happy\"}\n"));
+        assert!(generated_code.contains("func (value *Mood) UnmarshalText(text []byte) error {\n"));
+    }
+}
@@ -0,0 +1,215 @@
+/// Generation for the GraphQL schema definition language
+///
+/// Structs become object types and enums become enums, named in `PascalCase`, while fields and
+/// enum values keep their names so that responses match the JSON of the other targets. Members
+/// are non-null unless they are an `Option`. GraphQL has no 64-bit or unsigned integers, nor
+/// chars, so these are the custom scalars `Int64`, `UInt32`, `UInt64` and `Char`.
+///
+/// Supported options:
+/// - `variant`: `type` (the default) generates object types, `input` input types named
+///   `<Type>Input`, and `both` generates both
+/// - `declare_scalars`: `true` (the default) or `false`, leaving the custom scalars to be declared
+///   once elsewhere, as when merging the schemas of several files
+use crate::compilation_target::{CompilationError, CompilationInfo, CompilationTarget};
+use crate::naming::pascal_case;
+use crate::parser::{ASTNode, DataType};
+use crate::schema::{Declaration, DeclarationKind, EnumDefinition, Schema, StructDefinition};
+
+/// The custom scalars, with the types declaring them
+const CUSTOM_SCALARS: [(&str, DataType); 4] = [
+    ("Int64", DataType::I64),
+    ("UInt32", DataType::U32),
+    ("UInt64", DataType::U64),
+    ("Char", DataType::Char),
+];
+
+pub struct GraphQLGenerator;
+
+impl CompilationTarget for GraphQLGenerator {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        let variant = compilation_info.options.get_choice("variant", &["type", "input", "both"], "type")?;
+        let declare_scalars = compilation_info.options.get_choice("declare_scalars", &["true", "false"], "true")? == "true";
+        let schema = Schema::from_ast(ast)?;
+
+        let scalars = if declare_scalars {
+            generate_scalars(&schema)
+        } else {
+            String::new()
+        };
+        let declarations = schema
+            .declarations
+            .iter()
+            .map(|declaration| match declaration {
+                Declaration::Struct(struct_definition) => {
+                    generate_struct(struct_definition, variant, &schema, compilation_info)
+                }
+                Declaration::Enum(enum_definition) => generate_enum(enum_definition),
+            })
+            .collect::<Result<Vec<String>, CompilationError>>()?
+            .into_iter()
+            .map(|declaration| format!("\n{}", declaration))
+            .collect::<String>();
+
+        Ok(format!("{}\n{}{}", generate_preamble(compilation_info), scalars, declarations))
+    }
+}
+
+fn generate_preamble(compilation_info: &CompilationInfo) -> String {
+    compilation_info.preamble_comments.iter()
+        .map(|preamble| format!("# {}", preamble))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Declares the custom scalars the members of the schema use
+fn generate_scalars(schema: &Schema) -> String {
+    fn uses(data_type: &DataType, scalar_type: &DataType) -> bool {
+        match data_type {
+            DataType::Option(inner_type) | DataType::Array(inner_type) => uses(inner_type, scalar_type),
+            data_type => data_type == scalar_type,
+        }
+    }
+
+    CUSTOM_SCALARS
+        .iter()
+        .filter(|(_, scalar_type)| {
+            schema
+                .structs()
+                .flat_map(|struct_definition| &struct_definition.members)
+                .any(|member| uses(&member.data_type, scalar_type))
+        })
+        .map(|(name, _)| format!("scalar {}\n", name))
+        .collect()
+}
+
+/// Generates the object type and/or input type of a struct. GraphQL requires at least one field
+fn generate_struct(
+    struct_definition: &StructDefinition,
+    variant: &str,
+    schema: &Schema,
+    compilation_info: &CompilationInfo,
+) -> Result<String, CompilationError> {
+    if struct_definition.members.is_empty() {
+        return Err(CompilationError::UnsupportedType(format!(
+            "'{}' has no members, which GraphQL types require",
+            struct_definition.name
+        )));
+    }
+
+    let generate = |keyword: &str, suffix: &str| {
+        // Input types can only refer to other input types, and enums, which both variants share
+        let type_name = |name: &str| match schema.declaration_kind(name, &compilation_info.external_types) {
+            DeclarationKind::Struct => format!("{}{}", pascal_case(name), suffix),
+            DeclarationKind::Enum => pascal_case(name),
+        };
+        let fields = struct_definition
+            .members
+            .iter()
+            .map(|member| format!("  {}: {}\n", member.name, generate_type_name(&member.data_type, &type_name)))
+            .collect::<String>();
+        format!("{} {}{} {{\n{}}}\n", keyword, pascal_case(&struct_definition.name), suffix, fields)
+    };
+    Ok(match variant {
+        "input" => generate("input", "Input"),
+        "both" => format!("{}\n{}", generate("type", ""), generate("input", "Input")),
+        _ => generate("type", ""),
+    })
+}
+
+fn generate_enum(enum_definition: &EnumDefinition) -> Result<String, CompilationError> {
+    if enum_definition.members.is_empty() {
+        return Err(CompilationError::UnsupportedType(format!(
+            "'{}' has no members, which GraphQL enums require",
+            enum_definition.name
+        )));
+    }
+
+    let values = enum_definition
+        .members
+        .iter()
+        .map(|member| format!("  {}\n", member))
+        .collect::<String>();
+    Ok(format!("enum {} {{\n{}}}\n", pascal_case(&enum_definition.name), values))
+}
+
+/// Generates a non-null type name, unless it is an `Option`. User defined types are named by
+/// `type_name`
+fn generate_type_name(data_type: &DataType, type_name: &dyn Fn(&str) -> String) -> String {
+    match data_type {
+        DataType::Option(inner_type) => generate_nullable_type_name(inner_type, type_name),
+        data_type => format!("{}!", generate_nullable_type_name(data_type, type_name)),
+    }
+}
+
+fn generate_nullable_type_name(data_type: &DataType, type_name: &dyn Fn(&str) -> String) -> String {
+    match data_type {
+        DataType::U8 | DataType::I8 | DataType::U16 | DataType::I16 | DataType::I32 => "Int".to_owned(),
+        DataType::U32 => "UInt32".to_owned(),
+        DataType::I64 => "Int64".to_owned(),
+        DataType::U64 => "UInt64".to_owned(),
+        DataType::F32 | DataType::F64 => "Float".to_owned(),
+        DataType::Char => "Char".to_owned(),
+        DataType::String => "String".to_owned(),
+        DataType::Bool => "Boolean".to_owned(),
+        DataType::Option(inner_type) => generate_nullable_type_name(inner_type, type_name),
+        DataType::Array(inner_type) => format!("[{}]", generate_type_name(inner_type, type_name)),
+        DataType::UserDefined(name) => type_name(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+
+    const TEXT: &str = "struct person { id: u64, name: string, nickname: option(string), initial: char, scores: array(option(f32)), friends: option(array(person)), mood: enum mood { happy, verySad, } }";
+
+    const GENERATED_CODE: &str = "
+scalar UInt64
+scalar Char
+
+enum Mood {
+  happy
+  verySad
+}
+
+type Person {
+  id: UInt64!
+  name: String!
+  nickname: String
+  initial: Char!
+  scores: [Float]!
+  friends: [Person!]
+  mood: Mood!
+}
+";
+
+    #[test]
+    fn test_generate_graphql() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+
+        assert_eq!(
+            GraphQLGenerator
+                .generate_code(&ast, &CompilationInfo::default())
+                .expect("should generate code"),
+            GENERATED_CODE
+        );
+    }
+
+    #[test]
+    fn test_generate_graphql_input() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+        let mut compilation_info = CompilationInfo::default();
+        compilation_info.options.set("variant", "both");
+        compilation_info.options.set("declare_scalars", "false");
+
+        let generated_code = GraphQLGenerator
+            .generate_code(&ast, &compilation_info)
+            .expect("should generate code");
+        assert!(generated_code.starts_with("\n\nenum Mood {\n"));
+        assert!(generated_code.contains("\ntype Person {\n"));
+        assert!(generated_code.contains("\ninput PersonInput {\n"));
+        assert!(generated_code.contains("  friends: [PersonInput!]\n  mood: Mood!\n}\n"));
+    }
+}
@@ -0,0 +1,181 @@
+/// Hoisting of inline type declarations
+///
+/// A struct member may declare its type inline as a struct or enum. Most targets cannot express
+/// this, so the inline declarations are pulled out of line, being declared before the struct that
+/// uses them, and the member refers to them by name instead.
+use crate::compilation_target::CompilationError;
+use crate::parser::{
+    ASTNode, DataDefinition, DataType, NamedStatementList, StructMemberDeclaration,
+};
+use std::borrow::Borrow;
+
+/// Transforms the AST such that every inline struct and enum declaration is moved out of line
+/// # Parameters
+/// ast - The data definition to transform
+/// # Returns
+/// A new ASTNode::DataDefinition containing only top level struct and enum declarations, or
+/// CompilationError::InvalidAST if the AST is not a valid data definition
+pub fn hoist_inline_types(ast: &ASTNode) -> Result<ASTNode, CompilationError> {
+    InlineTypeHoister::transform_ast(ast)
+}
+
+/// Helper struct, made to just keep the transformed AST in memory whilst the function recursively
+/// transforms it
+struct InlineTypeHoister {
+    new_ast: DataDefinition,
+}
+
+impl InlineTypeHoister {
+    /// Main interface for the InlineTypeHoister
+    fn transform_ast(ast: &ASTNode) -> Result<ASTNode, CompilationError> {
+        let mut transformer = Self {
+            new_ast: DataDefinition::default(),
+        };
+        transformer.transform_ast_impl(ast)?;
+        Ok(ASTNode::DataDefinition(transformer.new_ast))
+    }
+
+    /// Does the actual transformation. The transformation is only taking inline struct and enum
+    /// declarations out of line, the AST is otherwise untouched
+    fn transform_ast_impl(&mut self, ast: &ASTNode) -> Result<(), CompilationError> {
+        match ast {
+            ASTNode::StructDeclaration(struct_declaration) => {
+                let mut pushed_struct = NamedStatementList::new(struct_declaration.name.clone());
+                pushed_struct.description = struct_declaration.description.clone();
+                for member in &struct_declaration.child_nodes {
+                    if let ASTNode::StructMemberDeclaration(member_declaration) = member {
+                        match member_declaration.data_type.borrow() {
+                            ASTNode::StructDeclaration(inline_struct_declaration) => {
+                                self.transform_ast_impl(member_declaration.data_type.borrow())?;
+                                let new_member = StructMemberDeclaration {
+                                    name: member_declaration.name.clone(),
+                                    data_type: Box::new(ASTNode::TypeLiteral(
+                                        DataType::UserDefined(
+                                            inline_struct_declaration.name.clone(),
+                                        ),
+                                    )),
+                                    field_number: member_declaration.field_number,
+                                    description: member_declaration.description.clone(),
+                                };
+                                pushed_struct
+                                    .child_nodes
+                                    .push(ASTNode::StructMemberDeclaration(new_member));
+                            }
+                            ASTNode::EnumDeclaration(inline_enum_declaration) => {
+                                self.transform_ast_impl(member_declaration.data_type.borrow())?;
+                                let new_member = StructMemberDeclaration {
+                                    name: member_declaration.name.clone(),
+                                    data_type: Box::new(ASTNode::TypeLiteral(
+                                        DataType::UserDefined(inline_enum_declaration.name.clone()),
+                                    )),
+                                    field_number: member_declaration.field_number,
+                                    description: member_declaration.description.clone(),
+                                };
+                                pushed_struct
+                                    .child_nodes
+                                    .push(ASTNode::StructMemberDeclaration(new_member));
+                            }
+                            _ => pushed_struct.child_nodes.push(member.clone()),
+                        }
+                    }
+                }
+                self.new_ast
+                    .child_nodes
+                    .push(ASTNode::StructDeclaration(pushed_struct));
+            }
+            ASTNode::EnumDeclaration(enum_declaration) => {
+                self.new_ast
+                    .child_nodes
+                    .push(ASTNode::EnumDeclaration(enum_declaration.clone()));
+            }
+            ASTNode::DataDefinition(data) => {
+                for child in &data.child_nodes {
+                    self.transform_ast_impl(child)?;
+                }
+            }
+            _ => return Err(CompilationError::InvalidAST)
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn initial_ast() -> ASTNode {
+        ASTNode::DataDefinition(DataDefinition {
+            child_nodes: vec![ASTNode::StructDeclaration(NamedStatementList {
+                name: "outer struct".to_owned(),
+                child_nodes: vec![ASTNode::StructMemberDeclaration(StructMemberDeclaration {
+                    name: "outer struct member 1".to_owned(),
+                    data_type: Box::new(ASTNode::StructDeclaration(NamedStatementList {
+                        name: "inner struct 1".to_owned(),
+                        child_nodes: vec![ASTNode::StructMemberDeclaration(
+                            StructMemberDeclaration {
+                                name: "inner struct 1 member 1".to_owned(),
+                                data_type: Box::new(ASTNode::StructDeclaration(
+                                    NamedStatementList {
+                                        name: "inner struct 2".to_owned(),
+                                        child_nodes: Vec::new(),
+                                        description: None,
+                                    },
+                                )),
+                                field_number: None,
+                                description: None,
+                            },
+                        )],
+                        description: None,
+                    })),
+                    field_number: None,
+                    description: None,
+                })],
+                description: None,
+            })],
+        })
+    }
+
+    fn transformed_ast() -> ASTNode {
+        ASTNode::DataDefinition(DataDefinition {
+            child_nodes: vec![
+                ASTNode::StructDeclaration(NamedStatementList {
+                    name: "inner struct 2".to_owned(),
+                    child_nodes: Vec::new(),
+                    description: None,
+                }),
+                ASTNode::StructDeclaration(NamedStatementList {
+                    name: "inner struct 1".to_owned(),
+                    child_nodes: vec![ASTNode::StructMemberDeclaration(StructMemberDeclaration {
+                        name: "inner struct 1 member 1".to_owned(),
+                        data_type: Box::new(ASTNode::TypeLiteral(DataType::UserDefined(
+                            "inner struct 2".to_owned(),
+                        ))),
+                        field_number: None,
+                        description: None,
+                    })],
+                    description: None,
+                }),
+                ASTNode::StructDeclaration(NamedStatementList {
+                    name: "outer struct".to_owned(),
+                    child_nodes: vec![ASTNode::StructMemberDeclaration(StructMemberDeclaration {
+                        name: "outer struct member 1".to_owned(),
+                        data_type: Box::new(ASTNode::TypeLiteral(DataType::UserDefined(
+                            "inner struct 1".to_owned(),
+                        ))),
+                        field_number: None,
+                        description: None,
+                    })],
+                    description: None,
+                }),
+            ],
+        })
+    }
+
+    #[test]
+    fn test_ast_transformation() {
+        assert_eq!(
+            hoist_inline_types(&initial_ast()).expect("should be able to transform"),
+            transformed_ast()
+        );
+    }
+}
@@ -0,0 +1,173 @@
+/// Generation for Kotlin
+///
+/// Structs become `@Serializable` data classes for `kotlinx.serialization`, with `val` properties
+/// named in `camelCase` and mapped to their original names with `@SerialName`. `Option` members
+/// are nullable and default to `null`, arrays become `List<T>`, and unsigned integers use the
+/// Kotlin unsigned types. Enums become enum classes with `SCREAMING_SNAKE_CASE` entries.
+///
+/// Supported options:
+/// - `package`: the package the types are declared in, none by default
+use crate::compilation_target::{CompilationError, CompilationInfo, CompilationTarget};
+use crate::naming::{camel_case, pascal_case, screaming_snake_case};
+use crate::parser::{ASTNode, DataType};
+use crate::schema::{Declaration, EnumDefinition, Schema, StructDefinition};
+
+/// Keywords which cannot name a property unless escaped with backticks
+const KEYWORDS: [&str; 28] = [
+    "as", "break", "class", "continue", "do", "else", "false", "for", "fun", "if", "in", "interface", "is", "null",
+    "object", "package", "return", "super", "this", "throw", "true", "try", "typealias", "typeof", "val", "var",
+    "when", "while",
+];
+
+pub struct KotlinGenerator;
+
+impl CompilationTarget for KotlinGenerator {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        let schema = Schema::from_ast(ast)?;
+
+        let package = compilation_info
+            .options
+            .get("package")
+            .map(|package| format!("package {}\n\n", package))
+            .unwrap_or_default();
+        let declarations = schema
+            .declarations
+            .iter()
+            .map(|declaration| match declaration {
+                Declaration::Struct(struct_definition) => generate_data_class(struct_definition),
+                Declaration::Enum(enum_definition) => generate_enum(enum_definition),
+            })
+            .map(|declaration| format!("\n{}", declaration))
+            .collect::<String>();
+
+        Ok(format!(
+            "{}\n{}import kotlinx.serialization.SerialName\nimport kotlinx.serialization.Serializable\n{}",
+            generate_preamble(compilation_info),
+            package,
+            declarations
+        ))
+    }
+}
+
+fn generate_preamble(compilation_info: &CompilationInfo) -> String {
+    compilation_info.preamble_comments.iter()
+        .map(|preamble| format!("// {}", preamble))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Generates a data class. A data class needs at least one property, so a struct without
+/// members becomes a plain class
+fn generate_data_class(struct_definition: &StructDefinition) -> String {
+    let name = pascal_case(&struct_definition.name);
+    if struct_definition.members.is_empty() {
+        return format!("@Serializable\nclass {}\n", name);
+    }
+
+    let properties = struct_definition
+        .members
+        .iter()
+        .map(|member| {
+            let property_name = camel_case(&member.name);
+            let serial_name = if property_name == member.name {
+                String::new()
+            } else {
+                format!("@SerialName(\"{}\") ", member.name)
+            };
+            let default = match member.data_type {
+                DataType::Option(_) => " = null",
+                _ => "",
+            };
+            format!(
+                "    {}val {}: {}{},\n",
+                serial_name,
+                escape_keyword(property_name),
+                generate_type_name(&member.data_type),
+                default
+            )
+        })
+        .collect::<String>();
+    format!("@Serializable\ndata class {}(\n{})\n", name, properties)
+}
+
+fn generate_enum(enum_definition: &EnumDefinition) -> String {
+    let entries = enum_definition
+        .members
+        .iter()
+        .map(|member| format!("    @SerialName(\"{}\") {},\n", member, screaming_snake_case(member)))
+        .collect::<String>();
+    format!("@Serializable\nenum class {} {{\n{}}}\n", pascal_case(&enum_definition.name), entries)
+}
+
+fn escape_keyword(name: String) -> String {
+    if KEYWORDS.contains(&name.as_str()) {
+        format!("`{}`", name)
+    } else {
+        name
+    }
+}
+
+fn generate_type_name(data_type: &DataType) -> String {
+    match data_type {
+        DataType::U8 => "UByte".to_owned(),
+        DataType::I8 => "Byte".to_owned(),
+        DataType::U16 => "UShort".to_owned(),
+        DataType::I16 => "Short".to_owned(),
+        DataType::U32 => "UInt".to_owned(),
+        DataType::I32 => "Int".to_owned(),
+        DataType::U64 => "ULong".to_owned(),
+        DataType::I64 => "Long".to_owned(),
+        DataType::F32 => "Float".to_owned(),
+        DataType::F64 => "Double".to_owned(),
+        DataType::Char => "Char".to_owned(),
+        DataType::String => "String".to_owned(),
+        DataType::Bool => "Boolean".to_owned(),
+        DataType::Option(inner_type) => format!("{}?", generate_type_name(inner_type)),
+        DataType::Array(inner_type) => format!("List<{}>", generate_type_name(inner_type)),
+        DataType::UserDefined(name) => pascal_case(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+
+    const TEXT: &str = "struct person { UserId: u64, nickname: option(string), in: array(u8), mood: enum mood { happy, verySad, } }";
+
+    const GENERATED_CODE: &str = "
+package com.acme.api
+
+import kotlinx.serialization.SerialName
+import kotlinx.serialization.Serializable
+
+@Serializable
+enum class Mood {
+    @SerialName(\"happy\") HAPPY,
+    @SerialName(\"verySad\") VERY_SAD,
+}
+
+@Serializable
+data class Person(
+    @SerialName(\"UserId\") val userId: ULong,
+    val nickname: String? = null,
+    val `in`: List<UByte>,
+    val mood: Mood,
+)
+";
+
+    #[test]
+    fn test_generate_kotlin() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+        let mut compilation_info = CompilationInfo::default();
+        compilation_info.options.set("package", "com.acme.api");
+
+        assert_eq!(
+            KotlinGenerator
+                .generate_code(&ast, &compilation_info)
+                .expect("should generate code"),
+            GENERATED_CODE
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::iter::Peekable;
@@ -5,7 +6,7 @@ use std::str::Chars;
 
 /// Represents a location within a string
 /// Which line, and at what position that line this thing is on
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct SourceLocation {
     pub line: i32,
     pub position: i32,
@@ -27,7 +28,7 @@ impl Display for SourceLocation {
 }
 
 /// Our token type
-#[derive(PartialEq, Eq, Debug, Clone)]
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
 pub enum TokenType {
     // Grammar
     LParen,
@@ -38,6 +39,7 @@ pub enum TokenType {
     RSquare,
     Comma,
     Colon,
+    At,
     // keywords
     Struct,
     Enum,
@@ -59,9 +61,13 @@ pub enum TokenType {
     Array,
     // Generic string identifier
     Identifier(String),
+    // Unsigned integer literal, such as a field number
+    Number(u32),
+    // Text of a `///` documentation comment, describing the declaration or member following it
+    DocComment(String),
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
 pub struct Token {
     pub token_type: TokenType,
     pub source_location: SourceLocation,
@@ -85,12 +91,13 @@ impl Display for LexError {
 
 impl Error for LexError {}
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TokenList(pub Vec<Token>);
 
 /// Transforms a string into a list of Tokens
 /// This is intended to make parsing much easier
-/// Whitespace is disregarded and discarded, and so has no effect on the tokens
+/// Whitespace and `//` comments are disregarded and discarded, and so have no effect on the
+/// tokens. `///` documentation comments are kept as tokens
 /// # Arguments
 /// `contents` - The string to extract tokens from
 /// # Returns
@@ -99,7 +106,7 @@ pub struct TokenList(pub Vec<Token>);
 pub fn lex_tokens(contents: String) -> Result<TokenList, LexError> {
     Lexer {
         source_location: SourceLocation::default(),
-        iterator: contents.chars().into_iter().peekable(),
+        iterator: contents.chars().peekable(),
     }
     .lex_impl()
 }
@@ -132,7 +139,7 @@ impl<'a> Lexer<'a> {
     pub fn lex_impl(&mut self) -> Result<TokenList, LexError> {
         let mut tokens = Vec::new();
 
-        while self.peek() != None {
+        while self.peek().is_some() {
             let char = self.next().unwrap();
 
             if char.is_whitespace() {
@@ -174,7 +181,18 @@ impl<'a> Lexer<'a> {
                     token_type: TokenType::Colon,
                     source_location,
                 },
-                x if x.is_alphanumeric() => self.lex_name(x),
+                '@' => Token {
+                    token_type: TokenType::At,
+                    source_location,
+                },
+                '/' if self.peek() == Some(&'/') => match self.lex_comment(source_location) {
+                    Some(token) => token,
+                    None => continue,
+                },
+                x if x.is_alphanumeric() => {
+                    let is_field_number = tokens.last().is_some_and(|token: &Token| token.token_type == TokenType::At);
+                    self.lex_name(x, is_field_number)
+                }
                 _ => return Err(LexError::UnknownCharacterError(source_location)),
             });
         }
@@ -182,15 +200,45 @@ impl<'a> Lexer<'a> {
         Ok(TokenList(tokens))
     }
 
-    /// Lexes a name, being any of a struct, enum, or named identifier
-    fn lex_name(&mut self, start_char: char) -> Token {
+    /// Lexes a comment running to the end of the line, following its first `/`. A documentation
+    /// comment, starting with `///`, becomes a token of its trimmed text; any other comment is
+    /// discarded
+    fn lex_comment(&mut self, source_location: SourceLocation) -> Option<Token> {
+        self.next();
+        let mut text = String::new();
+        while self.peek().is_some_and(|character| *character != '\n') {
+            text.push(self.next().unwrap());
+        }
+
+        // A comment of four or more slashes is not a documentation comment
+        match text.strip_prefix('/') {
+            Some(doc) if !doc.starts_with('/') => Some(Token {
+                token_type: TokenType::DocComment(doc.trim().to_owned()),
+                source_location,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Lexes a name, being any of a struct, enum, or named identifier. A field number, following
+    /// an `@`, is a number instead; elsewhere, names made up only of digits are identifiers
+    fn lex_name(&mut self, start_char: char, is_field_number: bool) -> Token {
         let mut name = String::from(start_char);
         let source_location = self.source_location.clone();
 
-        while self.peek() != None && self.peek().unwrap().is_alphanumeric() {
+        while self.peek().is_some() && self.peek().unwrap().is_alphanumeric() {
             name.push(self.next().unwrap());
         }
 
+        if is_field_number && name.chars().all(|character| character.is_ascii_digit()) {
+            if let Ok(number) = name.parse() {
+                return Token {
+                    token_type: TokenType::Number(number),
+                    source_location,
+                };
+            }
+        }
+
         match name.as_str() {
             "struct" => Token {
                 token_type: TokenType::Struct,
@@ -373,4 +421,45 @@ mod tests {
 
         assert_eq!(tokens.0, expected_tokens);
     }
+
+    #[test]
+    fn test_token_serialization() {
+        let tokens = lex_tokens(String::from("age: u32")).expect("should be able to tokenize");
+        let json = serde_json::to_string(&tokens).expect("should be able to serialize");
+
+        assert_eq!(
+            json,
+            r#"[{"token_type":{"Identifier":"age"},"source_location":{"line":1,"position":1}},{"token_type":"Colon","source_location":{"line":1,"position":4}},{"token_type":"U32","source_location":{"line":1,"position":6}}]"#
+        );
+    }
+
+    #[test]
+    fn test_field_number_tokens() {
+        let tokens = lex_tokens(String::from("id @12")).expect("should be able to tokenize");
+        let token_types: Vec<TokenType> = tokens.0.into_iter().map(|token| token.token_type).collect();
+
+        assert_eq!(
+            token_types,
+            vec![TokenType::Identifier(String::from("id")), TokenType::At, TokenType::Number(12)]
+        );
+
+        // Only field numbers are numbers, so that existing names made up of digits keep working
+        let tokens = lex_tokens(String::from("404 @404")).expect("should be able to tokenize");
+        let token_types: Vec<TokenType> = tokens.0.into_iter().map(|token| token.token_type).collect();
+        assert_eq!(
+            token_types,
+            vec![TokenType::Identifier(String::from("404")), TokenType::At, TokenType::Number(404)]
+        );
+    }
+
+    #[test]
+    fn test_comment_tokens() {
+        let tokens = lex_tokens(String::from("// note\n/// The age\nage //// ignored\n")).expect("should be able to tokenize");
+        let token_types: Vec<TokenType> = tokens.0.into_iter().map(|token| token.token_type).collect();
+
+        assert_eq!(
+            token_types,
+            vec![TokenType::DocComment(String::from("The age")), TokenType::Identifier(String::from("age"))]
+        );
+    }
 }
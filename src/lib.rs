@@ -1,6 +1,37 @@
+pub mod batch;
+pub mod compat;
 pub mod compilation_target;
+pub mod external_target;
+pub mod frontend;
+pub mod hoisting;
 pub mod lexer;
+pub mod naming;
 pub mod parser;
+pub mod project;
+pub mod schema;
+pub mod target_registry;
+pub mod template;
+pub mod validate;
+pub mod wire;
 
+pub(crate) mod avro;
+pub(crate) mod c;
+pub(crate) mod capnp;
+pub(crate) mod csharp;
 pub(crate) mod cxx;
+pub(crate) mod diagram;
+pub(crate) mod docs;
+pub(crate) mod flatbuffers;
+pub(crate) mod go;
+pub(crate) mod graphql;
+pub(crate) mod kotlin;
+pub(crate) mod openapi;
+pub(crate) mod proto;
+pub(crate) mod python;
+pub(crate) mod sql;
+pub(crate) mod swift;
+#[cfg(test)]
+pub(crate) mod toolchain;
 pub(crate) mod ts_mobx;
+pub(crate) mod typescript;
+pub(crate) mod zod;
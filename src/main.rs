@@ -1,44 +1,390 @@
-use clap::{arg, Parser};
-use fruko_bindgen::compilation_target::{CompilationInfo, Target};
+use clap::{Args, Parser, Subcommand};
+use fruko_bindgen::compilation_target::{CompilationInfo, Target, TargetOptions};
+use fruko_bindgen::batch::{expand_inputs, resolve_external_types, SourceFile};
+use fruko_bindgen::project::{output_path, ProjectConfig};
+use fruko_bindgen::target_registry::TargetRegistry;
 use fruko_bindgen::*;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
-use std::path::PathBuf;
-use std::str::FromStr;
+use std::path::{Path, PathBuf};
 
+/// Generates code from a data definition file, unless a command is given.
+///
+/// Without any arguments, the inputs and outputs are read from the `fruko.toml` project file found
+/// in the working directory or one of its ancestors. Arguments override the project file.
 #[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Cli {
-    /// Input data definition file
-    input_file: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    /// The files that the generated output will be placed into
+    #[command(flatten)]
+    generate: GenerateArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Prints the tokens lexed from a data definition file as JSON
+    DumpTokens {
+        /// Input data definition file
+        input_file: PathBuf,
+    },
+    /// Prints the AST parsed from a data definition file as JSON
+    DumpAst {
+        /// Input data definition file, which may also be a `.proto` or JSON Schema file
+        input_file: PathBuf,
+    },
+    /// Checks a JSON value against a type of a data definition, printing every error found
+    Validate {
+        /// Input data definition file, which may also be a `.proto` or JSON Schema file
+        input_file: PathBuf,
+
+        /// The struct or enum the value is of
+        root_type: String,
+
+        /// JSON file holding the value, read from stdin if not given
+        json_file: Option<PathBuf>,
+    },
+    /// Compares two versions of a data definition, reporting the compatibility of every change.
+    /// Fails if a change is breaking, or not of the compatibility given by `--require`
+    Compat {
+        /// The previous version of the data definition
+        old_file: PathBuf,
+
+        /// The next version of the data definition
+        new_file: PathBuf,
+
+        /// Prints the report as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// The compatibility every change must have
+        #[arg(long, value_parser = ["backward", "forward", "full"])]
+        require: Option<String>,
+    },
+}
+
+#[derive(Args)]
+struct GenerateArgs {
+    /// Input data definition file. Replaces the inputs of the project file. Protocol Buffers
+    /// (`.proto`) and JSON Schema (`.json`) files are imported
+    input_file: Option<PathBuf>,
+
+    /// The files that the generated output will be placed into. The target is chosen from the
+    /// file extension. Outputs given as arguments replace the outputs of the project file.
+    /// `{name}` is replaced with the file stem of the input
     output_files: Vec<PathBuf>,
 
+    /// Additional input file, directory or glob pattern. Directories are searched for `.fruko`
+    /// files recursively
+    #[arg(short, long = "input")]
+    inputs: Vec<PathBuf>,
+
+    /// Generates the named target for every input, into a file named by `--output-pattern`
+    #[arg(short, long)]
+    emit: Vec<String>,
+
+    /// Name of the files generated by `--emit`, where `{name}` is the file stem of the input and
+    /// `{ext}` the extension of the target
+    #[arg(long, default_value = "{name}.{ext}")]
+    output_pattern: String,
+
+    /// Directory the files generated by `--emit` are placed into
+    #[arg(long, default_value = ".")]
+    out_dir: PathBuf,
+
+    /// Replaces the preamble comments of the project file
     #[arg(short, long)]
     preamble_comments: Vec<String>,
+
+    /// Generates an output with an explicitly named target, as `<target>=<output file>`
+    #[arg(short, long = "target", value_parser = parse_key_value::<PathBuf>)]
+    targets: Vec<(String, PathBuf)>,
+
+    /// Dynamic library exporting additional targets through `fruko_register_targets`
+    #[arg(long)]
+    target_library: Vec<PathBuf>,
+
+    /// Registers an executable as a target, as `<target>=<command>`. The executable receives the
+    /// AST as JSON on stdin, and writes the generated code to stdout. The command is split into
+    /// words as a shell does, so paths and arguments with spaces must be quoted
+    #[arg(long, value_parser = parse_key_value::<String>)]
+    external_target: Vec<(String, String)>,
+
+    /// Sets a target option, as `<target>.<option>=<value>`, e.g. `cxx.namespace=api`
+    #[arg(short, long = "option", value_parser = parse_target_option)]
+    options: Vec<(String, String, String)>,
+
+    /// Project file to use instead of discovering `fruko.toml`
+    #[arg(long)]
+    config: Option<PathBuf>,
+}
+
+/// Parses command line values of the form `key=value`
+fn parse_key_value<T: From<String>>(argument: &str) -> Result<(String, T), String> {
+    match argument.split_once('=') {
+        Some((key, value)) => Ok((key.to_owned(), T::from(value.to_owned()))),
+        None => Err(format!("expected `<key>=<value>`, found '{}'", argument)),
+    }
+}
+
+/// Parses target options of the form `target.option=value`. The target name is everything before
+/// the last `.`, as template target names may themselves contain a `.`
+fn parse_target_option(argument: &str) -> Result<(String, String, String), String> {
+    let (key, value) = parse_key_value::<String>(argument)?;
+    match key.rsplit_once('.') {
+        Some((target, option)) => Ok((target.to_owned(), option.to_owned(), value)),
+        None => Err(format!("expected `<target>.<option>=<value>`, found '{}'", argument)),
+    }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Cli::parse();
 
-    let input_contents = std::fs::read_to_string(&args.input_file)?;
+    match args.command {
+        Some(Command::DumpTokens { input_file }) => {
+            let tokens = lexer::lex_tokens(std::fs::read_to_string(input_file)?)?;
+            println!("{}", serde_json::to_string_pretty(&tokens)?);
+            Ok(())
+        }
+        Some(Command::DumpAst { input_file }) => {
+            let ast = read_data_definition(&input_file)?;
+            println!("{}", serde_json::to_string_pretty(&ast)?);
+            Ok(())
+        }
+        Some(Command::Validate { input_file, root_type, json_file }) => {
+            validate(&input_file, &root_type, json_file.as_deref())
+        }
+        Some(Command::Compat { old_file, new_file, json, require }) => {
+            compat(&old_file, &new_file, json, require.as_deref())
+        }
+        None => generate(args.generate),
+    }
+}
+
+fn generate(args: GenerateArgs) -> Result<(), Box<dyn Error>> {
+    let project = match &args.config {
+        Some(path) => ProjectConfig::load(path)?,
+        None => ProjectConfig::discover(&std::env::current_dir()?)?.unwrap_or_default(),
+    };
 
-    let tokens = lexer::lex_tokens(input_contents)?;
-    let ast = parser::parse_tokens(tokens)?;
+    let mut registry = TargetRegistry::default();
+    for library in &args.target_library {
+        registry.load_library(library)?;
+    }
+    for (name, command) in &args.external_target {
+        registry.register_external(name, command)?;
+    }
 
-    let first_preamble_comment = format!("This file has been generated from '{}'", args.input_file.to_str().unwrap());
-    let mut preamble_comments = vec![first_preamble_comment];
-    preamble_comments.append(&mut args.preamble_comments.clone());
+    let mut inputs: Vec<PathBuf> = args.input_file.into_iter().chain(args.inputs).collect();
+    if inputs.is_empty() {
+        inputs = project.inputs;
+    }
+    if inputs.is_empty() {
+        return Err("No input file given, and no inputs found in a project file".into());
+    }
+    let input_files = expand_inputs(&inputs)?;
 
-    let compilation_info = CompilationInfo {
-        source_file_name: args.input_file.to_str().unwrap().to_string(),
-        preamble_comments,
+    let preamble_comments = if args.preamble_comments.is_empty() {
+        project.preamble_comments
+    } else {
+        args.preamble_comments
     };
 
+    let mut outputs: Vec<(Target, PathBuf)> = Vec::new();
     for file in args.output_files {
-        let compilation_target = Target::from_str(file.extension().expect("should have extension").to_str().expect("should be valid UTF8"))?;
+        outputs.push((registry.target_for_file(&file)?, file));
+    }
+    for (name, file) in args.targets {
+        outputs.push((registry.target(&name)?, file));
+    }
+    for name in &args.emit {
+        let target = registry.target(name)?;
+        let extension = target
+            .extension()
+            .ok_or_else(|| format!("Target '{}' has no file extension, use --target to name its output", name))?;
+        let file_name = args.output_pattern.replace("{ext}", extension);
+        outputs.push((target, args.out_dir.join(file_name)));
+    }
+    if outputs.is_empty() {
+        for (name, target_config) in &project.targets {
+            outputs.push((registry.target(name)?, target_config.output.clone()));
+        }
+    }
+
+    if input_files.len() > 1 {
+        if let Some((_, output)) = outputs.iter().find(|(_, output)| !output.to_string_lossy().contains("{name}")) {
+            return Err(format!("Output '{}' must contain {{name}} when compiling more than one input", output.display()).into());
+        }
+    }
+
+    // Checked before generating anything, as each output would silently overwrite the last
+    let mut output_targets: HashMap<PathBuf, &str> = HashMap::new();
+    for input_file in &input_files {
+        for (compilation_target, output_pattern) in &outputs {
+            let file = output_path(output_pattern, input_file);
+            if let Some(other_target) = output_targets.insert(file.clone(), compilation_target.name()) {
+                return Err(format!(
+                    "Targets '{}' and '{}' would both write {}, use --target to name their outputs apart",
+                    other_target,
+                    compilation_target.name(),
+                    file.display()
+                )
+                .into());
+            }
+        }
+    }
+
+    // Keyed by the canonical target name, so options apply however the target was selected
+    let mut options: BTreeMap<String, TargetOptions> = BTreeMap::new();
+    for (name, target_config) in &project.targets {
+        let target = registry.target(name)?;
+        options.entry(target.name().to_owned()).or_default().merge(&target_config.options);
+    }
+    for (name, option, value) in &args.options {
+        let target = registry.target(name)?;
+        options.entry(target.name().to_owned()).or_default().set(option, value);
+    }
+
+    // Files imported by the generated code, keyed by path, as outputs in the same directory share
+    // them. These depend only on the options, so are also checked before generating anything
+    let mut support_files: BTreeMap<PathBuf, String> = BTreeMap::new();
+    for input_file in &input_files {
+        for (compilation_target, output_pattern) in &outputs {
+            let compilation_info = CompilationInfo {
+                options: options.get(compilation_target.name()).cloned().unwrap_or_default(),
+                ..CompilationInfo::default()
+            };
+            let file = output_path(output_pattern, input_file);
+            for support_file in compilation_target.support_files(&compilation_info) {
+                let path = file.with_file_name(&support_file.name);
+                if let Some(other_target) = output_targets.get(&path) {
+                    return Err(format!(
+                        "The '{}' output {} would be overwritten by a file the '{}' output imports",
+                        other_target,
+                        path.display(),
+                        compilation_target.name()
+                    )
+                    .into());
+                }
+                support_files.insert(path, support_file.contents);
+            }
+        }
+    }
+
+    let mut source_files = Vec::new();
+    for input_file in input_files {
+        let ast = read_data_definition(&input_file)?;
+        source_files.push(SourceFile { path: input_file, ast });
+    }
+    let resolutions = resolve_external_types(&source_files)?;
+
+    for (source_file, resolution) in source_files.iter().zip(resolutions) {
+        let input_file = &source_file.path;
+        if source_files.len() > 1 {
+            for name in &resolution.unresolved_types {
+                eprintln!("warning: type '{}' used in '{}' is not declared in any input", name, input_file.display());
+            }
+        }
+
+        let first_preamble_comment = format!("This file has been generated from '{}'", input_file.to_str().unwrap());
+        let mut file_preamble_comments = vec![first_preamble_comment];
+        file_preamble_comments.append(&mut preamble_comments.clone());
+
+        for (compilation_target, output_pattern) in &outputs {
+            let compilation_info = CompilationInfo {
+                source_file_name: input_file.to_str().unwrap().to_string(),
+                preamble_comments: file_preamble_comments.clone(),
+                options: options.get(compilation_target.name()).cloned().unwrap_or_default(),
+                external_types: resolution.external_types.clone(),
+            };
+
+            let file = output_path(output_pattern, input_file);
+            if file.canonicalize().ok() == input_file.canonicalize().ok() {
+                return Err(format!("Output {} would overwrite its input", file.display()).into());
+            }
+            if let Some(directory) = file.parent().filter(|directory| !directory.as_os_str().is_empty()) {
+                std::fs::create_dir_all(directory)?;
+            }
+            std::fs::write(&file, compilation_target.generate_code(&source_file.ast, &compilation_info)?)?;
+            for warning in compilation_target.warnings(&source_file.ast, &compilation_info) {
+                eprintln!("warning: {}: {}", file.display(), warning);
+            }
+        }
+    }
 
-        std::fs::write(file, compilation_target.generate_code(&ast, &compilation_info)?)?;
+    for (file, contents) in support_files {
+        std::fs::write(&file, contents)?;
     }
 
     Ok(())
 }
+
+fn validate(input_file: &Path, root_type: &str, json_file: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let parser::ASTNode::DataDefinition(definition) = read_data_definition(input_file)? else {
+        return Err(format!("{} is not a data definition", input_file.display()).into());
+    };
+    let json = match json_file {
+        Some(json_file) => std::fs::read_to_string(json_file)?,
+        None => std::io::read_to_string(std::io::stdin())?,
+    };
+    let errors = validate::validate(&serde_json::from_str(&json)?, &definition, root_type);
+    for error in &errors {
+        eprintln!("{}", error);
+    }
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err("The value has 1 error".into()),
+        count => Err(format!("The value has {} errors", count).into()),
+    }
+}
+
+fn compat(old_file: &Path, new_file: &Path, json: bool, require: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let mut definitions = Vec::new();
+    for file in [old_file, new_file] {
+        match read_data_definition(file)? {
+            parser::ASTNode::DataDefinition(definition) => definitions.push(definition),
+            _ => return Err(format!("{} is not a data definition", file.display()).into()),
+        }
+    }
+    let report = compat::compare(&definitions[0], &definitions[1])?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        for change in &report.changes {
+            println!("{}", change);
+        }
+        println!("Overall: {}", report.compatibility);
+    }
+
+    let compatible = match require {
+        Some("backward") => report.compatibility.is_backward(),
+        Some("forward") => report.compatibility.is_forward(),
+        Some(_) => report.compatibility == compat::Compatibility::Full,
+        None => report.compatibility != compat::Compatibility::Breaking,
+    };
+    match compatible {
+        true => Ok(()),
+        false => Err(format!("The changes are {}", report.compatibility).into()),
+    }
+}
+
+/// Reads a data definition file in any supported format, printing the warnings of its frontend
+fn read_data_definition(input_file: &Path) -> Result<parser::ASTNode, Box<dyn Error>> {
+    let contents = std::fs::read_to_string(input_file)?;
+    match frontend::import_file(input_file, &contents) {
+        Ok(import) => {
+            for warning in &import.warnings {
+                eprintln!("{}: {}", input_file.display(), warning);
+            }
+            Ok(import.ast)
+        }
+        Err(error) => {
+            for diagnostic in &error.0 {
+                eprintln!("{}: {}", input_file.display(), diagnostic);
+            }
+            Err(format!("Could not read {}", input_file.display()).into())
+        }
+    }
+}
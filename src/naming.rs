@@ -0,0 +1,93 @@
+/// Conversions between the naming conventions of target languages
+///
+/// Names in a data definition are alphanumeric, and may be written in any case, while imported
+/// names may also contain underscores. Names are split into words at underscores and changes of
+/// case, such that `userId`, `UserId`, `USERId` and `user_id` all consist of the words `user` and
+/// `id`.
+use std::mem;
+
+/// Splits a name into its lowercase words
+pub fn words(name: &str) -> Vec<String> {
+    let characters: Vec<char> = name.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (index, character) in characters.iter().enumerate() {
+        if !character.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(mem::take(&mut word));
+            }
+            continue;
+        }
+        let previous = index.checked_sub(1).map(|index| characters[index]);
+        let next = characters.get(index + 1);
+        let starts_word = character.is_uppercase()
+            && previous.is_some_and(|previous| {
+                previous.is_lowercase()
+                    || previous.is_ascii_digit()
+                    || (previous.is_uppercase() && next.is_some_and(|next| next.is_lowercase()))
+            });
+        if starts_word && !word.is_empty() {
+            words.push(mem::take(&mut word));
+        }
+        word.extend(character.to_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Converts a name to `snake_case`
+pub fn snake_case(name: &str) -> String {
+    words(name).join("_")
+}
+
+/// Converts a name to `SCREAMING_SNAKE_CASE`
+pub fn screaming_snake_case(name: &str) -> String {
+    words(name)
+        .iter()
+        .map(|word| word.to_uppercase())
+        .collect::<Vec<String>>()
+        .join("_")
+}
+
+/// Converts a name to `PascalCase`
+pub fn pascal_case(name: &str) -> String {
+    words(name)
+        .iter()
+        .map(|word| {
+            let mut characters = word.chars();
+            match characters.next() {
+                Some(first) => first.to_uppercase().chain(characters).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Converts a name to `camelCase`
+pub fn camel_case(name: &str) -> String {
+    let pascal_case = pascal_case(name);
+    let mut characters = pascal_case.chars();
+    match characters.next() {
+        Some(first) => first.to_lowercase().chain(characters).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_words() {
+        assert_eq!(words("userId"), vec!["user", "id"]);
+        assert_eq!(words("HTTPServer2Config"), vec!["http", "server2", "config"]);
+        assert_eq!(words("mood"), vec!["mood"]);
+        assert_eq!(words("USER_ID"), vec!["user", "id"]);
+        assert_eq!(snake_case("HTTPServer"), "http_server");
+        assert_eq!(screaming_snake_case("userRole"), "USER_ROLE");
+        assert_eq!(pascal_case("userRole"), "UserRole");
+        assert_eq!(camel_case("UserRole"), "userRole");
+    }
+}
@@ -0,0 +1,302 @@
+/// Generation for OpenAPI, as the `components.schemas` of a document in YAML or JSON
+///
+/// Structs become object schemas and enums become string schemas, named in `PascalCase`. Members
+/// which are not an `Option` are `required`. Numbers carry the format they fit, with bounds for
+/// the smaller and unsigned integers; `u64` exceeds `int64`, so has only a minimum. Types from
+/// other files are referred to with `$ref`, to the document generated for their module.
+///
+/// Supported options:
+/// - `version`: the OpenAPI version, `3.1` (the default) or `3.0`. In 3.1, `null` is allowed by
+///   the type array of JSON Schema, and in 3.0 with `nullable`
+/// - `title`: the title of the document, the module name by default
+/// - `api_version`: the version of the document, `1.0.0` by default
+use crate::batch::module_name;
+use crate::compilation_target::{CompilationError, CompilationInfo, CompilationTarget};
+use crate::naming::pascal_case;
+use crate::parser::{ASTNode, DataType};
+use crate::schema::{Declaration, EnumDefinition, Schema, StructDefinition};
+use serde_json::{json, Map, Value};
+use std::path::Path;
+
+pub struct OpenApiGenerator {
+    /// Whether the document is written as JSON, rather than YAML
+    pub json: bool,
+}
+
+impl CompilationTarget for OpenApiGenerator {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        let version = compilation_info.options.get_choice("version", &["3.1", "3.0"], "3.1")?;
+        let schema = Schema::from_ast(ast)?;
+        let module = module_name(Path::new(&compilation_info.source_file_name));
+        let generator = SchemaGenerator {
+            compilation_info,
+            extension: if self.json { "json" } else { "yaml" },
+            openapi_30: version == "3.0",
+        };
+
+        let schemas = schema
+            .declarations
+            .iter()
+            .map(|declaration| {
+                let schema = match declaration {
+                    Declaration::Struct(struct_definition) => generator.generate_struct(struct_definition),
+                    Declaration::Enum(enum_definition) => generate_enum(enum_definition),
+                };
+                (pascal_case(declaration.name()), schema)
+            })
+            .collect::<Map<String, Value>>();
+        let mut document = json!({
+            "openapi": if generator.openapi_30 { "3.0.3" } else { "3.1.0" },
+            "info": {
+                "title": compilation_info.options.get("title").unwrap_or(&module),
+                "version": compilation_info.options.get("api_version").unwrap_or("1.0.0"),
+            },
+            // 3.0 requires paths, which 3.1 documents of components alone may leave out
+            "paths": if generator.openapi_30 { json!({}) } else { Value::Null },
+            "components": {
+                "schemas": schemas,
+            },
+        });
+        if let Some(object) = document.as_object_mut() {
+            object.retain(|_, value| !value.is_null());
+        }
+
+        if self.json {
+            let code = serde_json::to_string_pretty(&document).expect("JSON values should serialize");
+            Ok(format!("{}\n", code))
+        } else {
+            let code = serde_yaml_ng::to_string(&document).expect("JSON values should serialize to YAML");
+            Ok(format!("{}\n{}", generate_preamble(compilation_info), code))
+        }
+    }
+}
+
+/// JSON has no comments, so only YAML documents have a preamble
+fn generate_preamble(compilation_info: &CompilationInfo) -> String {
+    compilation_info.preamble_comments.iter()
+        .map(|preamble| format!("# {}", preamble))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn generate_enum(enum_definition: &EnumDefinition) -> Value {
+    json!({
+        "type": "string",
+        "enum": enum_definition.members,
+    })
+}
+
+struct SchemaGenerator<'a> {
+    compilation_info: &'a CompilationInfo,
+    /// The extension of the documents of other modules, which external types are referred to in
+    extension: &'a str,
+    openapi_30: bool,
+}
+
+impl SchemaGenerator<'_> {
+    fn generate_struct(&self, struct_definition: &StructDefinition) -> Value {
+        let properties = struct_definition
+            .members
+            .iter()
+            .map(|member| (member.name.clone(), self.generate_schema(&member.data_type)))
+            .collect::<Map<String, Value>>();
+        let required = struct_definition
+            .members
+            .iter()
+            .filter(|member| !matches!(member.data_type, DataType::Option(_)))
+            .map(|member| Value::from(member.name.as_str()))
+            .collect::<Vec<Value>>();
+
+        let mut schema = json!({
+            "type": "object",
+            "properties": properties,
+        });
+        if !required.is_empty() {
+            schema["required"] = Value::Array(required);
+        }
+        schema
+    }
+
+    fn generate_schema(&self, data_type: &DataType) -> Value {
+        match data_type {
+            DataType::U8 => json!({ "type": "integer", "format": "int32", "minimum": 0, "maximum": u8::MAX }),
+            DataType::I8 => json!({ "type": "integer", "format": "int32", "minimum": i8::MIN, "maximum": i8::MAX }),
+            DataType::U16 => json!({ "type": "integer", "format": "int32", "minimum": 0, "maximum": u16::MAX }),
+            DataType::I16 => json!({ "type": "integer", "format": "int32", "minimum": i16::MIN, "maximum": i16::MAX }),
+            DataType::U32 => json!({ "type": "integer", "format": "int64", "minimum": 0, "maximum": u32::MAX }),
+            DataType::I32 => json!({ "type": "integer", "format": "int32" }),
+            DataType::U64 => json!({ "type": "integer", "minimum": 0 }),
+            DataType::I64 => json!({ "type": "integer", "format": "int64" }),
+            DataType::F32 => json!({ "type": "number", "format": "float" }),
+            DataType::F64 => json!({ "type": "number", "format": "double" }),
+            DataType::Char => json!({ "type": "string", "minLength": 1, "maxLength": 1 }),
+            DataType::String => json!({ "type": "string" }),
+            DataType::Bool => json!({ "type": "boolean" }),
+            DataType::Option(inner_type) => self.generate_nullable_schema(inner_type),
+            DataType::Array(inner_type) => json!({ "type": "array", "items": self.generate_schema(inner_type) }),
+            DataType::UserDefined(name) => json!({ "$ref": self.generate_reference(name) }),
+        }
+    }
+
+    /// Generates the schema of an `Option`, which also accepts `null`
+    fn generate_nullable_schema(&self, data_type: &DataType) -> Value {
+        let mut schema = self.generate_schema(data_type);
+        if self.openapi_30 {
+            // Siblings of a `$ref` are ignored in 3.0, so the reference is wrapped
+            if schema.get("$ref").is_some() {
+                schema = json!({ "allOf": [schema] });
+            }
+            schema["nullable"] = Value::Bool(true);
+            return schema;
+        }
+
+        match schema.get("type").cloned() {
+            Some(Value::String(type_name)) => schema["type"] = json!([type_name, "null"]),
+            // An option of an option, or of a reference
+            _ => return json!({ "oneOf": [schema, { "type": "null" }] }),
+        }
+        schema
+    }
+
+    /// Generates a reference to a schema, in the document of another module if the type is
+    /// declared in another file
+    fn generate_reference(&self, name: &str) -> String {
+        let document = self
+            .compilation_info
+            .external_types
+            .iter()
+            .find(|external_type| external_type.name == name)
+            .map(|external_type| format!("{}.{}", external_type.module, self.extension))
+            .unwrap_or_default();
+        format!("{}#/components/schemas/{}", document, pascal_case(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compilation_target::ExternalType;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+    use crate::schema::DeclarationKind;
+
+    const TEXT: &str = "
+struct orderLine {
+  quantity: u16,
+  discount: option(i8),
+  total: u64,
+  weight: f64,
+  grade: char,
+  status: enum lineStatus { open, shipped, },
+  tags: array(option(string)),
+  product: product,
+  replaces: option(orderLine),
+}
+";
+
+    const GENERATED_CODE: &str = "
+openapi: 3.1.0
+info:
+  title: sales
+  version: 1.0.0
+components:
+  schemas:
+    LineStatus:
+      type: string
+      enum:
+      - open
+      - shipped
+    OrderLine:
+      type: object
+      properties:
+        quantity:
+          type: integer
+          format: int32
+          minimum: 0
+          maximum: 65535
+        discount:
+          type:
+          - integer
+          - 'null'
+          format: int32
+          minimum: -128
+          maximum: 127
+        total:
+          type: integer
+          minimum: 0
+        weight:
+          type: number
+          format: double
+        grade:
+          type: string
+          minLength: 1
+          maxLength: 1
+        status:
+          $ref: '#/components/schemas/LineStatus'
+        tags:
+          type: array
+          items:
+            type:
+            - string
+            - 'null'
+        product:
+          $ref: catalog.yaml#/components/schemas/Product
+        replaces:
+          oneOf:
+          - $ref: '#/components/schemas/OrderLine'
+          - type: 'null'
+      required:
+      - quantity
+      - total
+      - weight
+      - grade
+      - status
+      - tags
+      - product
+";
+
+    fn compilation_info() -> CompilationInfo {
+        CompilationInfo {
+            source_file_name: "sales.fruko".to_owned(),
+            external_types: vec![ExternalType {
+                name: "product".to_owned(),
+                module: "catalog".to_owned(),
+                kind: DeclarationKind::Struct,
+            }],
+            ..CompilationInfo::default()
+        }
+    }
+
+    #[test]
+    fn test_generate_openapi() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+
+        assert_eq!(
+            OpenApiGenerator { json: false }
+                .generate_code(&ast, &compilation_info())
+                .expect("should generate code"),
+            GENERATED_CODE
+        );
+    }
+
+    #[test]
+    fn test_generate_openapi_30_json() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+        let mut compilation_info = compilation_info();
+        compilation_info.options.set("version", "3.0");
+
+        let generated_code = OpenApiGenerator { json: true }
+            .generate_code(&ast, &compilation_info)
+            .expect("should generate code");
+        let document: Value = serde_json::from_str(&generated_code).expect("should be JSON");
+        let order_line = &document["components"]["schemas"]["OrderLine"]["properties"];
+        assert_eq!(document["openapi"], "3.0.3");
+        assert_eq!(order_line["discount"]["nullable"], true);
+        assert_eq!(order_line["tags"]["items"], json!({ "type": "string", "nullable": true }));
+        assert_eq!(
+            order_line["replaces"],
+            json!({ "allOf": [{ "$ref": "#/components/schemas/OrderLine" }], "nullable": true })
+        );
+        assert_eq!(order_line["product"]["$ref"], "catalog.json#/components/schemas/Product");
+    }
+}
@@ -1,11 +1,16 @@
 use crate::lexer::{SourceLocation, Token, TokenList, TokenType};
+use serde::{Deserialize, Serialize};
 use std::borrow::Borrow;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::iter::Peekable;
 
+/// The largest field number, being the largest Protocol Buffers allows, so that explicit field
+/// numbers carry over to every wire format
+pub const MAX_FIELD_NUMBER: u32 = 536_870_911;
+
 /// Node type for our Abstract Syntax Tree (AST)
-#[derive(PartialEq, Eq, Debug, Clone)]
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
 pub enum ASTNode {
     StructDeclaration(NamedStatementList),
     EnumDeclaration(NamedStatementList),
@@ -17,7 +22,7 @@ pub enum ASTNode {
 
 /// All of our supported data types, including types defined by the user (e.g a struct or enum).
 /// During parsing, we do not check whether a user defined type has actually been defined
-#[derive(PartialEq, Eq, Debug, Clone)]
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
 pub enum DataType {
     U8,
     I8,
@@ -37,25 +42,60 @@ pub enum DataType {
     UserDefined(String),
 }
 
+/// Writes a data type as it is written in a data definition, e.g. `option(array(u8))`
+impl Display for DataType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataType::U8 => f.write_str("u8"),
+            DataType::I8 => f.write_str("i8"),
+            DataType::U16 => f.write_str("u16"),
+            DataType::I16 => f.write_str("i16"),
+            DataType::U32 => f.write_str("u32"),
+            DataType::I32 => f.write_str("i32"),
+            DataType::U64 => f.write_str("u64"),
+            DataType::I64 => f.write_str("i64"),
+            DataType::F32 => f.write_str("f32"),
+            DataType::F64 => f.write_str("f64"),
+            DataType::Char => f.write_str("char"),
+            DataType::String => f.write_str("string"),
+            DataType::Bool => f.write_str("bool"),
+            DataType::Option(inner_type) => write!(f, "option({})", inner_type),
+            DataType::Array(inner_type) => write!(f, "array({})", inner_type),
+            DataType::UserDefined(name) => f.write_str(name),
+        }
+    }
+}
+
 /// Data required to define a struct member
 /// data_type takes an ASTNode to allow inline definition of a struct or enum
-#[derive(PartialEq, Eq, Debug, Clone)]
+/// field_number is an explicitly assigned, stable number identifying the member, written as
+/// `name @1: type`. Targets for wire formats which identify members by number use it
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
 pub struct StructMemberDeclaration {
     pub name: String,
     pub data_type: Box<ASTNode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field_number: Option<u32>,
+    /// The `///` documentation comment of the member
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
 }
 
-/// Simple enum member declaration; Only has a name (we do not emulate an underlying type)
-#[derive(PartialEq, Eq, Debug, Clone)]
+/// Simple enum member declaration; Only has a name (we do not emulate an underlying type).
+/// Documentation comments of enum members are accepted, but not kept
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
 pub struct EnumMemberDeclaration {
     pub name: String,
 }
 
 /// A named statement list - Either a struct or an enum.
-#[derive(PartialEq, Eq, Debug, Clone)]
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
 pub struct NamedStatementList {
     pub name: String,
     pub child_nodes: Vec<ASTNode>,
+    /// The `///` documentation comment of the declaration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
 }
 
 impl NamedStatementList {
@@ -63,12 +103,13 @@ impl NamedStatementList {
         Self {
             name,
             child_nodes: Vec::new(),
+            description: None,
         }
     }
 }
 
 /// Root node for our AST
-#[derive(Default, PartialEq, Eq, Debug, Clone)]
+#[derive(Default, PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
 pub struct DataDefinition {
     pub child_nodes: Vec<ASTNode>,
 }
@@ -81,6 +122,7 @@ pub struct DataDefinition {
 pub enum ParseError {
     UnexpectedToken(SourceLocation),
     UnexpectedEndOfTokens,
+    InvalidFieldNumber(SourceLocation, u32),
 }
 
 impl Display for ParseError {
@@ -92,6 +134,9 @@ impl Display for ParseError {
             ParseError::UnexpectedEndOfTokens => {
                 f.write_str("The stream of tokens ended unexpectedly")
             }
+            ParseError::InvalidFieldNumber(location, number) => {
+                write!(f, "Field number {} at {} is not from 1 to {}", number, location, MAX_FIELD_NUMBER)
+            }
         }
     }
 }
@@ -108,8 +153,12 @@ pub fn parse_tokens(tokens: TokenList) -> Result<ASTNode, ParseError> {
     let mut base_ast_node = DataDefinition::default();
     let mut iterator = tokens.0.iter().peekable();
 
-    while iterator.peek() != None {
-        base_ast_node.child_nodes.push(parse(&mut iterator)?);
+    loop {
+        let description = parse_doc_comments(&mut iterator);
+        if iterator.peek().is_none() {
+            break;
+        }
+        base_ast_node.child_nodes.push(parse(&mut iterator, description)?);
     }
 
     Ok(ASTNode::DataDefinition(base_ast_node))
@@ -154,19 +203,41 @@ fn assert_token(token: Option<&Token>, expected_token: TokenType) -> Result<(),
     }
 }
 
+/// Parses any documentation comments, joining their lines
+/// # Returns
+/// The documentation, or None if there are no documentation comments
+fn parse_doc_comments<'a>(
+    token_iter: &mut Peekable<impl Iterator<Item = &'a Token>>,
+) -> Option<String> {
+    let mut lines = Vec::new();
+    while let Some(Token { token_type: TokenType::DocComment(line), .. }) = token_iter.peek() {
+        lines.push(line.clone());
+        token_iter.next();
+    }
+
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
 /// Main work function for parsing our AST. Deals with the top level of our data definition,
 /// covering our raw enum and struct definitions
 fn parse<'a>(
     token_iter: &mut Peekable<impl Iterator<Item = &'a Token>>,
+    description: Option<String>,
 ) -> Result<ASTNode, ParseError> {
     let token = unwrap_or_error(token_iter.next())?;
 
     match &token.token_type {
         TokenType::Struct => Ok(ASTNode::StructDeclaration(parse_named_statement_list(
             token_iter,
+            description,
         )?)),
         TokenType::Enum => Ok(ASTNode::EnumDeclaration(parse_named_statement_list(
             token_iter,
+            description,
         )?)),
         _ => Err(ParseError::UnexpectedToken(token.source_location.clone())),
     }
@@ -175,6 +246,7 @@ fn parse<'a>(
 /// Parses the body of a named statement list. This could either be a struct or an enum.
 fn parse_named_statement_list<'a>(
     token_iter: &mut Peekable<impl Iterator<Item = &'a Token>>,
+    description: Option<String>,
 ) -> Result<NamedStatementList, ParseError> {
     let name_token = unwrap_or_error(token_iter.next())?;
     let name = match &name_token.token_type {
@@ -191,6 +263,7 @@ fn parse_named_statement_list<'a>(
     let named_statement_list = NamedStatementList {
         name: name.clone(),
         child_nodes: parse_named_statement_list_children(token_iter)?,
+        description,
     };
 
     assert_token(token_iter.next(), TokenType::RCurly)?;
@@ -207,6 +280,7 @@ fn parse_named_statement_list_children<'a>(
 ) -> Result<Vec<ASTNode>, ParseError> {
     let mut ret_val = Vec::new();
     loop {
+        let description = parse_doc_comments(token_iter);
         let name_token = unwrap_peek_or_error(token_iter.peek())?;
         let name = match &name_token.token_type {
             TokenType::Identifier(name) => name,
@@ -214,6 +288,7 @@ fn parse_named_statement_list_children<'a>(
         };
         token_iter.next();
 
+        let field_number = parse_field_number(token_iter)?;
         let following_token = unwrap_or_error(token_iter.next())?;
 
         ret_val.push(match following_token.token_type {
@@ -221,10 +296,12 @@ fn parse_named_statement_list_children<'a>(
                 let struct_member_decl = StructMemberDeclaration {
                     name: name.clone(),
                     data_type: Box::new(parse_struct_member_type_declaration(token_iter)?),
+                    field_number,
+                    description,
                 };
                 ASTNode::StructMemberDeclaration(struct_member_decl)
             }
-            TokenType::Comma => {
+            TokenType::Comma if field_number.is_none() => {
                 ASTNode::EnumMemberDeclaration(EnumMemberDeclaration { name: name.clone() })
             }
             _ => {
@@ -242,6 +319,26 @@ fn parse_named_statement_list_children<'a>(
     Ok(ret_val)
 }
 
+/// Parses the optional field number following a member name, written as `@` and a number from 1
+/// to `MAX_FIELD_NUMBER`
+fn parse_field_number<'a>(
+    token_iter: &mut Peekable<impl Iterator<Item = &'a Token>>,
+) -> Result<Option<u32>, ParseError> {
+    if unwrap_peek_or_error(token_iter.peek())?.token_type != TokenType::At {
+        return Ok(None);
+    }
+    token_iter.next();
+
+    let number_token = unwrap_or_error(token_iter.next())?;
+    match number_token.token_type {
+        TokenType::Number(number) if (1..=MAX_FIELD_NUMBER).contains(&number) => Ok(Some(number)),
+        TokenType::Number(number) => Err(ParseError::InvalidFieldNumber(number_token.source_location.clone(), number)),
+        _ => Err(ParseError::UnexpectedToken(
+            number_token.source_location.clone(),
+        )),
+    }
+}
+
 /// Parses a struct member type declaration. This could either by a TypeLiteral, an inline struct
 /// definition, or an inline enum definition
 fn parse_struct_member_type_declaration<'a>(
@@ -251,10 +348,10 @@ fn parse_struct_member_type_declaration<'a>(
 
     match &token.token_type {
         TokenType::Struct => Ok(ASTNode::StructDeclaration(parse_named_statement_list(
-            token_iter,
+            token_iter, None,
         )?)),
         TokenType::Enum => Ok(ASTNode::EnumDeclaration(parse_named_statement_list(
-            token_iter,
+            token_iter, None,
         )?)),
         _ => Ok(ASTNode::TypeLiteral(parse_literal_type(token, token_iter)?)),
     }
@@ -324,17 +421,88 @@ mod tests {
                     ASTNode::StructMemberDeclaration(StructMemberDeclaration {
                         name: String::from("member1"),
                         data_type: Box::new(ASTNode::TypeLiteral(DataType::U32)),
+                        field_number: None,
+                        description: None,
                     }),
                     ASTNode::StructMemberDeclaration(StructMemberDeclaration {
                         name: String::from("member2"),
                         data_type: Box::new(ASTNode::TypeLiteral(DataType::Option(Box::new(
                             DataType::F32,
                         )))),
+                        field_number: None,
+                        description: None,
                     }),
                 ],
+                description: None,
             })],
         });
 
         assert_eq!(ast_start, expected_ast);
     }
+
+    #[test]
+    fn test_ast_json_round_trip() {
+        use crate::lexer::lex_tokens;
+        let tokens = lex_tokens(String::from(TEXT)).expect("should be able to lex");
+        let ast = parse_tokens(tokens).expect("should be able to parse");
+
+        let json = serde_json::to_string(&ast).expect("should be able to serialize");
+        let deserialized: ASTNode = serde_json::from_str(&json).expect("should be able to deserialize");
+
+        assert_eq!(deserialized, ast);
+    }
+
+    #[test]
+    fn test_parses_field_numbers() {
+        use crate::lexer::lex_tokens;
+        let tokens = lex_tokens(String::from("struct name { member1 @3: u32, member2: bool }")).expect("should be able to lex");
+
+        let ast = parse_tokens(tokens).expect("should be able to parse");
+        let field_numbers: Vec<Option<u32>> = match ast {
+            ASTNode::DataDefinition(definition) => match &definition.child_nodes[0] {
+                ASTNode::StructDeclaration(struct_declaration) => struct_declaration
+                    .child_nodes
+                    .iter()
+                    .map(|node| match node {
+                        ASTNode::StructMemberDeclaration(member) => member.field_number,
+                        _ => panic!("expected a struct member"),
+                    })
+                    .collect(),
+                _ => panic!("expected a struct"),
+            },
+            _ => panic!("expected a data definition"),
+        };
+        assert_eq!(field_numbers, vec![Some(3), None]);
+
+        for text in ["struct name { member @0: u32 }", "struct name { member @536870912: u32 }"] {
+            let tokens = lex_tokens(String::from(text)).expect("should be able to lex");
+            assert!(matches!(parse_tokens(tokens), Err(ParseError::InvalidFieldNumber(_, _))));
+        }
+    }
+
+    #[test]
+    fn test_parses_doc_comments() {
+        use crate::lexer::lex_tokens;
+        let text = "/// A person\n/// in the system\nstruct name {\n  /// Their age\n  member1: u32,\n  member2: bool\n}";
+        let tokens = lex_tokens(String::from(text)).expect("should be able to lex");
+
+        let ast = parse_tokens(tokens).expect("should be able to parse");
+        let struct_declaration = match ast {
+            ASTNode::DataDefinition(mut definition) => match definition.child_nodes.remove(0) {
+                ASTNode::StructDeclaration(struct_declaration) => struct_declaration,
+                _ => panic!("expected a struct"),
+            },
+            _ => panic!("expected a data definition"),
+        };
+        let descriptions: Vec<Option<String>> = struct_declaration
+            .child_nodes
+            .iter()
+            .map(|node| match node {
+                ASTNode::StructMemberDeclaration(member) => member.description.clone(),
+                _ => panic!("expected a struct member"),
+            })
+            .collect();
+        assert_eq!(struct_declaration.description.as_deref(), Some("A person\nin the system"));
+        assert_eq!(descriptions, vec![Some(String::from("Their age")), None]);
+    }
 }
@@ -0,0 +1,131 @@
+/// Project configuration
+///
+/// A `fruko.toml` file describes the inputs, outputs and target options of a project, so that the
+/// CLI can be run without any arguments. For example:
+/// ```toml
+/// inputs = ["schema/common", "schema/user.fruko", "schema/orders/*.fruko"]
+/// preamble_comments = ["Do not edit by hand"]
+///
+/// [targets.cxx]
+/// output = "generated/{name}.h"
+/// namespace = "api"
+/// header_guard = "pragma"
+///
+/// [targets.ts-mobx]
+/// output = "web/src/generated/{name}.ts"
+/// import_path = "mobx-state-tree"
+/// ```
+/// Inputs may be files, directories or glob patterns, as with the CLI. Targets are keyed by any of
+/// their names or extensions. Every key of a target table other than `output` is passed to the
+/// target as an option. `{name}` in an output path is replaced with the file stem of the input
+/// being compiled. Relative paths are relative to the directory containing the configuration file.
+use crate::batch::module_name;
+use crate::compilation_target::TargetOptions;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+pub const PROJECT_FILE_NAME: &str = "fruko.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    #[serde(default)]
+    pub inputs: Vec<PathBuf>,
+    #[serde(default)]
+    pub preamble_comments: Vec<String>,
+    #[serde(default)]
+    pub targets: BTreeMap<String, TargetConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TargetConfig {
+    pub output: PathBuf,
+    #[serde(flatten)]
+    pub options: TargetOptions,
+}
+
+/// Error type for loading a project configuration file
+#[derive(Debug)]
+pub enum ProjectError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+}
+
+impl Display for ProjectError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProjectError::Io(path, error) => write!(f, "Could not read {}: {}", path.display(), error),
+            ProjectError::Parse(path, error) => write!(f, "Invalid project file {}: {}", path.display(), error),
+        }
+    }
+}
+
+impl Error for ProjectError {}
+
+impl ProjectConfig {
+    /// Looks for a project file in the supplied directory and each of its ancestors, loading the
+    /// first one found
+    pub fn discover(directory: &Path) -> Result<Option<Self>, ProjectError> {
+        directory
+            .ancestors()
+            .map(|ancestor| ancestor.join(PROJECT_FILE_NAME))
+            .find(|path| path.is_file())
+            .map(|path| Self::load(&path))
+            .transpose()
+    }
+
+    /// Loads a project file, resolving the paths it contains against the directory it is in
+    pub fn load(path: &Path) -> Result<Self, ProjectError> {
+        let contents = std::fs::read_to_string(path).map_err(|error| ProjectError::Io(path.to_owned(), error))?;
+        let base_directory = path.parent().unwrap_or_else(|| Path::new(""));
+        Self::parse(&contents, base_directory).map_err(|error| ProjectError::Parse(path.to_owned(), error))
+    }
+
+    fn parse(contents: &str, base_directory: &Path) -> Result<Self, toml::de::Error> {
+        let mut config: Self = toml::from_str(contents)?;
+        for input in &mut config.inputs {
+            *input = base_directory.join(&input);
+        }
+        for target in config.targets.values_mut() {
+            target.output = base_directory.join(&target.output);
+        }
+        Ok(config)
+    }
+}
+
+/// Substitutes `{name}` in an output path with the file stem of the input file
+pub fn output_path(pattern: &Path, input_file: &Path) -> PathBuf {
+    PathBuf::from(pattern.to_string_lossy().replace("{name}", &module_name(input_file)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROJECT: &str = r#"
+inputs = ["schema/user.fruko"]
+
+[targets.cxx]
+output = "generated/{name}.h"
+namespace = "api"
+json = true
+"#;
+
+    #[test]
+    fn test_parse_project() {
+        let config = ProjectConfig::parse(PROJECT, Path::new("project")).expect("should parse");
+
+        assert_eq!(config.inputs, vec![PathBuf::from("project/schema/user.fruko")]);
+        let target = &config.targets["cxx"];
+        assert_eq!(
+            output_path(&target.output, &config.inputs[0]),
+            PathBuf::from("project/generated/user.h")
+        );
+        assert_eq!(target.options.get("namespace"), Some("api"));
+        assert_eq!(target.options.get("json"), Some("true"));
+        assert_eq!(target.options.get("output"), None);
+    }
+}
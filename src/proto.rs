@@ -0,0 +1,267 @@
+/// Generation for Protocol Buffers, in the proto3 syntax
+///
+/// Structs become messages and enums become enums, with `Option` members as `optional` fields and
+/// arrays as `repeated` fields. Fields are numbered by their explicit field number
+/// (`name @1: type`) where given, and otherwise in declaration order, skipping explicit numbers.
+/// Enum values are prefixed with the enum name and numbered from 1, after an added
+/// `<ENUM>_UNSPECIFIED` value 0, which proto3 requires.
+///
+/// Supported options:
+/// - `package`: the package the file declares, none by default
+use crate::compilation_target::{CompilationError, CompilationInfo, CompilationTarget};
+use crate::naming::screaming_snake_case;
+use crate::parser::{ASTNode, DataType};
+use crate::schema::{Declaration, EnumDefinition, Schema, StructDefinition, StructMember};
+
+/// Field numbers reserved by the protobuf implementation. The parser checks the range of every
+/// other field number
+const RESERVED_FIELD_NUMBERS: std::ops::RangeInclusive<u32> = 19000..=19999;
+
+pub struct ProtoGenerator;
+
+impl CompilationTarget for ProtoGenerator {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        let mut generator = ProtoSchemaGenerator::new(compilation_info.options.get("package"));
+        let declarations = generator.generate(&Schema::from_ast(ast)?)?;
+
+        Ok(format!(
+            "{}\nsyntax = \"proto3\";\n{}{}\n{}",
+            generate_preamble(compilation_info),
+            generate_package(generator.package),
+            generate_external_imports(compilation_info),
+            declarations
+        ))
+    }
+
+    fn warnings(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Vec<String> {
+        let mut generator = ProtoSchemaGenerator::new(compilation_info.options.get("package"));
+        match Schema::from_ast(ast).and_then(|schema| generator.generate(&schema)) {
+            Ok(_) => generator.warnings,
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+fn generate_preamble(compilation_info: &CompilationInfo) -> String {
+    compilation_info.preamble_comments.iter()
+        .map(|preamble| format!("// {}", preamble))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn generate_package(package: Option<&str>) -> String {
+    package
+        .map(|package| format!("\npackage {};\n", package))
+        .unwrap_or_default()
+}
+
+/// Imports the files generated for the other files of the batch which declare types used here
+fn generate_external_imports(compilation_info: &CompilationInfo) -> String {
+    let imports = compilation_info
+        .external_modules()
+        .keys()
+        .map(|module| format!("import \"{}.proto\";\n", module))
+        .collect::<String>();
+    if imports.is_empty() {
+        imports
+    } else {
+        format!("\n{}", imports)
+    }
+}
+
+/// Helper struct, collecting warnings about types which proto3 represents less precisely than
+/// declared
+struct ProtoSchemaGenerator<'a> {
+    package: Option<&'a str>,
+    warnings: Vec<String>,
+}
+
+impl<'a> ProtoSchemaGenerator<'a> {
+    fn new(package: Option<&'a str>) -> Self {
+        Self {
+            package,
+            warnings: Vec::new(),
+        }
+    }
+
+    fn generate(&mut self, schema: &Schema) -> Result<String, CompilationError> {
+        Ok(schema
+            .declarations
+            .iter()
+            .map(|declaration| match declaration {
+                Declaration::Struct(struct_definition) => self.generate_message(struct_definition),
+                Declaration::Enum(enum_definition) => Ok(generate_enum(enum_definition)),
+            })
+            .collect::<Result<Vec<String>, CompilationError>>()?
+            .join("\n"))
+    }
+
+    fn generate_message(&mut self, struct_definition: &StructDefinition) -> Result<String, CompilationError> {
+        let fields = struct_definition
+            .members
+            .iter()
+            .zip(struct_definition.field_numbers())
+            .map(|(member, field_number)| {
+                if member.field_number.is_some() && RESERVED_FIELD_NUMBERS.contains(&field_number) {
+                    return Err(CompilationError::InvalidFieldNumber(format!(
+                        "field number {} of '{}.{}' is not allowed by proto3",
+                        field_number, struct_definition.name, member.name
+                    )));
+                }
+                Ok(format!(
+                    "    {} {} = {};\n",
+                    self.generate_field_type(struct_definition, member)?,
+                    member.name,
+                    field_number
+                ))
+            })
+            .collect::<Result<String, CompilationError>>()?;
+        Ok(format!("message {} {{\n{}}}\n", struct_definition.name, fields))
+    }
+
+    /// Generates the type of a field, including its `optional` or `repeated` label. Proto3 cannot
+    /// label a field more than once, so options and arrays can only be nested in limited ways
+    fn generate_field_type(&mut self, struct_definition: &StructDefinition, member: &StructMember) -> Result<String, CompilationError> {
+        let member_path = format!("{}.{}", struct_definition.name, member.name);
+        let unsupported = |description: &str| {
+            CompilationError::UnsupportedType(format!("'{}' is {}, which proto3 cannot represent", member_path, description))
+        };
+
+        match &member.data_type {
+            DataType::Option(inner_type) => match inner_type.as_ref() {
+                DataType::Option(_) => Err(unsupported("a nested option")),
+                DataType::Array(array_type) if is_label_free(array_type) => {
+                    self.warnings.push(format!(
+                        "'{}' is an optional array, generated as repeated, so an absent array cannot be told apart from an empty one",
+                        member_path
+                    ));
+                    Ok(format!("repeated {}", self.generate_type_name(array_type, member, &member_path)))
+                }
+                DataType::Array(_) => Err(unsupported("an array of options or arrays")),
+                data_type => Ok(format!("optional {}", self.generate_type_name(data_type, member, &member_path))),
+            },
+            DataType::Array(inner_type) if is_label_free(inner_type) => {
+                Ok(format!("repeated {}", self.generate_type_name(inner_type, member, &member_path)))
+            }
+            DataType::Array(_) => Err(unsupported("an array of options or arrays")),
+            data_type => Ok(self.generate_type_name(data_type, member, &member_path)),
+        }
+    }
+
+    /// Generates the name of a scalar or user defined type. Integers narrower than 32 bits are
+    /// widened, as proto3 has no narrower integer types
+    fn generate_type_name(&mut self, data_type: &DataType, member: &StructMember, member_path: &str) -> String {
+        let (type_name, widened_from) = match data_type {
+            DataType::U8 => ("uint32", Some("u8")),
+            DataType::U16 => ("uint32", Some("u16")),
+            DataType::I8 => ("int32", Some("i8")),
+            DataType::I16 => ("int32", Some("i16")),
+            DataType::U32 => ("uint32", None),
+            DataType::I32 => ("int32", None),
+            DataType::U64 => ("uint64", None),
+            DataType::I64 => ("int64", None),
+            DataType::F32 => ("float", None),
+            DataType::F64 => ("double", None),
+            DataType::Char | DataType::String => ("string", None),
+            DataType::Bool => ("bool", None),
+            DataType::UserDefined(name) => return self.qualify(name, member),
+            DataType::Option(_) | DataType::Array(_) => unreachable!("labels are generated by generate_field_type"),
+        };
+        if let Some(declared_type) = widened_from {
+            self.warnings.push(format!("'{}' is a {}, widened to {}", member_path, declared_type, type_name));
+        }
+        type_name.to_owned()
+    }
+
+    /// Refers to a user defined type. A field named the same as its type hides the type within
+    /// the message, in which case the type is referred to by its fully qualified name
+    fn qualify(&self, name: &str, member: &StructMember) -> String {
+        if name != member.name {
+            return name.to_owned();
+        }
+        match self.package {
+            Some(package) => format!(".{}.{}", package, name),
+            None => format!(".{}", name),
+        }
+    }
+}
+
+fn generate_enum(enum_definition: &EnumDefinition) -> String {
+    let prefix = screaming_snake_case(&enum_definition.name);
+    let values = enum_definition
+        .members
+        .iter()
+        .enumerate()
+        .map(|(index, member)| format!("    {}_{} = {};\n", prefix, screaming_snake_case(member), index + 1))
+        .collect::<String>();
+    format!("enum {} {{\n    {}_UNSPECIFIED = 0;\n{}}}\n", enum_definition.name, prefix, values)
+}
+
+/// Whether a type can be labelled `optional` or `repeated`, not being an option or array itself
+fn is_label_free(data_type: &DataType) -> bool {
+    !matches!(data_type, DataType::Option(_) | DataType::Array(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+
+    const TEXT: &str = "struct person { name @1: string, age @4: option(u8), scores: array(f64), mood: enum mood { happy, veryHappy, } }";
+
+    const GENERATED_CODE: &str = "
+syntax = \"proto3\";
+
+enum mood {
+    MOOD_UNSPECIFIED = 0;
+    MOOD_HAPPY = 1;
+    MOOD_VERY_HAPPY = 2;
+}
+
+message person {
+    string name = 1;
+    optional uint32 age = 4;
+    repeated double scores = 2;
+    .mood mood = 3;
+}
+";
+
+    #[test]
+    fn test_generate_proto() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+
+        assert_eq!(
+            ProtoGenerator
+                .generate_code(&ast, &CompilationInfo::default())
+                .expect("should generate code"),
+            GENERATED_CODE
+        );
+        assert_eq!(
+            ProtoGenerator.warnings(&ast, &CompilationInfo::default()),
+            vec!["'person.age' is a u8, widened to uint32"]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_types() {
+        let ast = parse_tokens(lex_tokens("struct grid { cells: array(array(u32)) }".to_owned()).expect("should lex"))
+            .expect("should parse");
+
+        assert!(matches!(
+            ProtoGenerator.generate_code(&ast, &CompilationInfo::default()),
+            Err(CompilationError::UnsupportedType(_))
+        ));
+    }
+
+    #[test]
+    fn test_reserved_field_numbers() {
+        let ast = parse_tokens(lex_tokens("struct record { id @19000: u32 }".to_owned()).expect("should lex"))
+            .expect("should parse");
+
+        assert!(matches!(
+            ProtoGenerator.generate_code(&ast, &CompilationInfo::default()),
+            Err(CompilationError::InvalidFieldNumber(_))
+        ));
+    }
+}
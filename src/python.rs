@@ -0,0 +1,361 @@
+/// Generation for Python
+///
+/// Structs become classes with type hints, enums become `enum.Enum` subclasses whose values are
+/// the member names. Classes are declared after the classes they refer to where possible, and
+/// optional members default to `None`.
+///
+/// Names which are Python keywords get a trailing `_`, as do classes named like a name the module
+/// imports, which they would shadow. So do members named like either, or like a class of the
+/// module, as a member's default would shadow the class where pydantic resolves type hints. Such
+/// members keep their original name as the pydantic alias, or in the dataclass field metadata.
+///
+/// Supported options:
+/// - `style`: `dataclass` (the default) generates `@dataclass` classes, `pydantic` generates
+///   pydantic `BaseModel` subclasses
+use crate::compilation_target::{CompilationError, CompilationInfo, CompilationTarget};
+use crate::parser::{ASTNode, DataType};
+use crate::schema::{Declaration, EnumDefinition, Schema, StructDefinition};
+
+/// `keyword.kwlist`, the keywords which cannot name a class, field or enum member
+const KEYWORDS: [&str; 35] = [
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+    "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda", "nonlocal",
+    "not", "or", "pass", "raise", "return", "try", "while", "with", "yield",
+];
+
+/// The names the generated modules import, or refer to as builtins
+const MODULE_NAMES: [&str; 12] = [
+    "BaseModel", "Field", "Optional", "TYPE_CHECKING", "bool", "dataclass", "enum", "field", "float", "int", "list", "str",
+];
+
+pub struct PythonGenerator;
+
+impl CompilationTarget for PythonGenerator {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        let style = compilation_info.options.get_choice("style", &["dataclass", "pydantic"], "dataclass")?;
+        let schema = Schema::from_ast(ast)?;
+        let class_names = schema
+            .declarations
+            .iter()
+            .map(Declaration::name)
+            .chain(compilation_info.external_types.iter().map(|external_type| external_type.name.as_str()))
+            .collect::<Vec<&str>>();
+
+        let declarations = schema
+            .dependency_order()
+            .into_iter()
+            .map(|declaration| match declaration {
+                Declaration::Struct(struct_definition) => generate_class(struct_definition, style, &class_names),
+                Declaration::Enum(enum_definition) => generate_enum(enum_definition),
+            })
+            .collect::<Vec<String>>()
+            .join("\n\n");
+
+        // Fields are only needed to keep the original names of renamed members
+        let uses_fields = schema.structs().any(|struct_definition| {
+            struct_definition.members.iter().any(|member| is_renamed_member(&member.name, &class_names))
+        });
+
+        Ok(format!(
+            "{}\nfrom __future__ import annotations\n\n{}{}\n\n\n{}",
+            generate_preamble(compilation_info),
+            generate_imports(style, uses_fields),
+            generate_external_imports(compilation_info, style),
+            declarations
+        ))
+    }
+}
+
+fn generate_preamble(compilation_info: &CompilationInfo) -> String {
+    compilation_info.preamble_comments.iter()
+        .map(|preamble| format!("# {}", preamble))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn generate_imports(style: &str, uses_fields: bool) -> String {
+    let model_import = match (style, uses_fields) {
+        ("pydantic", false) => "from pydantic import BaseModel",
+        ("pydantic", true) => "from pydantic import BaseModel, Field",
+        (_, false) => "from dataclasses import dataclass",
+        (_, true) => "from dataclasses import dataclass, field",
+    };
+    format!("import enum\n{}\nfrom typing import Optional", model_import)
+}
+
+/// Imports the classes declared in other files of the batch from their sibling modules. Dataclass
+/// type hints are never evaluated at runtime, so their imports are guarded by TYPE_CHECKING, which
+/// allows files of the batch to refer to each other cyclically. Pydantic evaluates type hints, so
+/// requires the classes at runtime
+fn generate_external_imports(compilation_info: &CompilationInfo, style: &str) -> String {
+    let modules = compilation_info.external_modules();
+    if modules.is_empty() {
+        return String::new();
+    }
+
+    let (header, indent) = match style {
+        "pydantic" => ("", ""),
+        _ => ("from typing import TYPE_CHECKING\n\nif TYPE_CHECKING:\n", "    "),
+    };
+    let imports = modules
+        .iter()
+        .map(|(module, names)| {
+            let names = names.iter().map(|name| escape_name(name)).collect::<Vec<String>>();
+            format!("{}from .{} import {}\n", indent, module, names.join(", "))
+        })
+        .collect::<String>();
+    format!("\n{}{}", header, imports.trim_end())
+}
+
+fn generate_class(struct_definition: &StructDefinition, style: &str, class_names: &[&str]) -> String {
+    let name = escape_name(&struct_definition.name);
+    let header = match style {
+        "pydantic" => format!("class {}(BaseModel):\n", name),
+        _ => format!("@dataclass(kw_only=True)\nclass {}:\n", name),
+    };
+    let body = if struct_definition.members.is_empty() {
+        "    pass\n".to_owned()
+    } else {
+        struct_definition
+            .members
+            .iter()
+            .map(|member| {
+                let default = match member.data_type {
+                    DataType::Option(_) => Some("None"),
+                    _ => None,
+                };
+                let renamed = is_renamed_member(&member.name, class_names);
+                let field_name = if renamed { format!("{}_", member.name) } else { member.name.clone() };
+                format!(
+                    "    {}: {}{}\n",
+                    field_name,
+                    generate_type_name(&member.data_type),
+                    generate_default(&member.name, renamed, default, style)
+                )
+            })
+            .collect()
+    };
+    header + &body
+}
+
+/// The default of a field, which also keeps the original name of a renamed member
+fn generate_default(name: &str, renamed: bool, default: Option<&str>, style: &str) -> String {
+    if !renamed {
+        return default.map(|default| format!(" = {}", default)).unwrap_or_default();
+    }
+
+    let default = default.map(|default| format!("default={}, ", default)).unwrap_or_default();
+    match style {
+        "pydantic" => format!(" = Field({}alias=\"{}\")", default, name),
+        _ => format!(" = field({}metadata={{\"name\": \"{}\"}})", default, name),
+    }
+}
+
+fn generate_enum(enum_definition: &EnumDefinition) -> String {
+    let body = if enum_definition.members.is_empty() {
+        "    pass\n".to_owned()
+    } else {
+        enum_definition
+            .members
+            .iter()
+            .map(|member| format!("    {} = \"{}\"\n", escape_keyword(member), member))
+            .collect()
+    };
+    format!("class {}(enum.Enum):\n{}", escape_name(&enum_definition.name), body)
+}
+
+/// Escapes the name of a class
+fn escape_name(name: &str) -> String {
+    if MODULE_NAMES.contains(&name) {
+        format!("{}_", name)
+    } else {
+        escape_keyword(name)
+    }
+}
+
+fn is_renamed_member(name: &str, class_names: &[&str]) -> bool {
+    KEYWORDS.contains(&name) || MODULE_NAMES.contains(&name) || class_names.contains(&name)
+}
+
+fn escape_keyword(name: &str) -> String {
+    if KEYWORDS.contains(&name) {
+        format!("{}_", name)
+    } else {
+        name.to_owned()
+    }
+}
+
+/// Generates the type hints. Python has a single integer and a single float type, so the width
+/// of numeric types is not represented
+fn generate_type_name(data_type: &DataType) -> String {
+    match data_type {
+        DataType::U8
+        | DataType::I8
+        | DataType::U16
+        | DataType::I16
+        | DataType::U32
+        | DataType::I32
+        | DataType::U64
+        | DataType::I64 => "int".to_owned(),
+        DataType::F32 | DataType::F64 => "float".to_owned(),
+        DataType::Char | DataType::String => "str".to_owned(),
+        DataType::Bool => "bool".to_owned(),
+        DataType::Option(inner_type) => format!("Optional[{}]", generate_type_name(inner_type)),
+        DataType::Array(inner_type) => format!("list[{}]", generate_type_name(inner_type)),
+        DataType::UserDefined(name) => escape_name(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+
+    // Members named like a class or a builtin are renamed, as their defaults would shadow it
+    const TEXT: &str = "
+struct node { id: u64, kind: option(kind), tag: tag, children: array(node), parent: option(node), int: option(u8), count: u8 }
+enum kind { leaf, branch, }
+struct tag { name: string }
+";
+
+    const GENERATED_CODE: &str = "
+from __future__ import annotations
+
+import enum
+from dataclasses import dataclass, field
+from typing import Optional
+
+
+class kind(enum.Enum):
+    leaf = \"leaf\"
+    branch = \"branch\"
+
+
+@dataclass(kw_only=True)
+class tag:
+    name: str
+
+
+@dataclass(kw_only=True)
+class node:
+    id: int
+    kind_: Optional[kind] = field(default=None, metadata={\"name\": \"kind\"})
+    tag_: tag = field(metadata={\"name\": \"tag\"})
+    children: list[node]
+    parent: Optional[node] = None
+    int_: Optional[int] = field(default=None, metadata={\"name\": \"int\"})
+    count: int
+";
+
+    #[test]
+    fn test_generate_python() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+
+        assert_eq!(
+            PythonGenerator
+                .generate_code(&ast, &CompilationInfo::default())
+                .expect("should generate code"),
+            GENERATED_CODE
+        );
+    }
+
+    /// Validates JSON with the generated pydantic models, which resolve their type hints at runtime
+    #[test]
+    fn test_pydantic_models_validate() {
+        use crate::toolchain::{self, run, temporary_directory};
+        if !toolchain::require_python_module("test_pydantic_models_validate", "pydantic") {
+            return;
+        }
+        let text = format!("{} struct list {{ field: option(string), items: array(list), str: string }}", TEXT);
+        let ast = parse_tokens(lex_tokens(text).expect("should lex")).expect("should parse");
+        let mut compilation_info = CompilationInfo::default();
+        compilation_info.options.set("style", "pydantic");
+        let directory = temporary_directory("python-pydantic");
+        std::fs::write(
+            directory.join("models.py"),
+            PythonGenerator.generate_code(&ast, &compilation_info).expect("should generate code"),
+        )
+        .expect("should write module");
+        std::fs::write(
+            directory.join("main.py"),
+            r#"from models import list_, node
+
+value = node.model_validate({"id": 18446744073709551615, "kind": "leaf", "tag": {"name": "t"}, "children": [], "int": 3, "count": 4})
+print(value.model_dump_json(by_alias=True))
+print(list_.model_validate({"field": "f", "items": [{"items": [], "str": "b"}], "str": "a"}).model_dump_json(by_alias=True))
+"#,
+        )
+        .expect("should write program");
+
+        assert_eq!(
+            run("python3", &["main.py"], &directory),
+            "{\"id\":18446744073709551615,\"kind\":\"leaf\",\"tag\":{\"name\":\"t\"},\"children\":[],\"parent\":null,\"int\":3,\"count\":4}\n\
+             {\"field\":\"f\",\"items\":[{\"field\":null,\"items\":[],\"str\":\"b\"}],\"str\":\"a\"}\n"
+        );
+        std::fs::remove_dir_all(directory).expect("should remove directory");
+    }
+
+    #[test]
+    fn test_generate_pydantic() {
+        let ast = parse_tokens(lex_tokens("struct point { x: f32 }".to_owned()).expect("should lex")).expect("should parse");
+        let mut compilation_info = CompilationInfo::default();
+        compilation_info.options.set("style", "pydantic");
+
+        let generated_code = PythonGenerator
+            .generate_code(&ast, &compilation_info)
+            .expect("should generate code");
+        assert!(generated_code.contains("from pydantic import BaseModel\n"));
+        assert!(generated_code.ends_with("class point(BaseModel):\n    x: float\n"));
+    }
+
+    #[test]
+    fn test_escape_keywords() {
+        let text = "struct class { from: option(string), in: array(None), lambda: u8 } enum None { True, pass, }";
+        let ast = parse_tokens(lex_tokens(text.to_owned()).expect("should lex")).expect("should parse");
+        let mut compilation_info = CompilationInfo::default();
+
+        let generated_code = PythonGenerator
+            .generate_code(&ast, &compilation_info)
+            .expect("should generate code");
+        assert!(generated_code.contains("from dataclasses import dataclass, field\n"));
+        assert!(generated_code.contains("class None_(enum.Enum):\n    True_ = \"True\"\n    pass_ = \"pass\"\n"));
+        assert!(generated_code.contains(
+            "class class_:\n    from_: Optional[str] = field(default=None, metadata={\"name\": \"from\"})\n    \
+             in_: list[None_] = field(metadata={\"name\": \"in\"})\n"
+        ));
+
+        compilation_info.options.set("style", "pydantic");
+        let generated_code = PythonGenerator
+            .generate_code(&ast, &compilation_info)
+            .expect("should generate code");
+        assert!(generated_code.contains("from pydantic import BaseModel, Field\n"));
+        assert!(generated_code.contains("    from_: Optional[str] = Field(default=None, alias=\"from\")\n"));
+        assert!(generated_code.contains("    lambda_: int = Field(alias=\"lambda\")\n"));
+
+        compilation_info.options.set("style", "model");
+        assert!(matches!(
+            PythonGenerator.generate_code(&ast, &compilation_info),
+            Err(CompilationError::InvalidOption(_))
+        ));
+    }
+
+    #[test]
+    fn test_generate_external_imports() {
+        use crate::compilation_target::ExternalType;
+        use crate::schema::DeclarationKind;
+        let compilation_info = CompilationInfo {
+            external_types: vec![ExternalType { name: "order".to_owned(), module: "orders".to_owned(), kind: DeclarationKind::Struct }],
+            ..CompilationInfo::default()
+        };
+
+        assert_eq!(
+            generate_external_imports(&compilation_info, "dataclass"),
+            "\nfrom typing import TYPE_CHECKING\n\nif TYPE_CHECKING:\n    from .orders import order"
+        );
+        assert_eq!(
+            generate_external_imports(&compilation_info, "pydantic"),
+            "\nfrom .orders import order"
+        );
+    }
+}
@@ -0,0 +1,347 @@
+/// Resolved schema model
+///
+/// A flat view of a data definition: inline declarations are hoisted out of line, and struct
+/// members are reduced to their data types. Targets which do not mirror the declaration structure
+/// of the data definition are simpler to write against this than against the AST.
+use crate::compilation_target::{CompilationError, ExternalType};
+use crate::hoisting::hoist_inline_types;
+use crate::parser::{ASTNode, DataType, NamedStatementList};
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+
+#[derive(PartialEq, Eq, Debug, Clone, Serialize)]
+pub struct Schema {
+    pub declarations: Vec<Declaration>,
+}
+
+/// A top level declaration. Declarations are ordered such that inline declarations come before
+/// the struct they were declared in
+#[derive(PartialEq, Eq, Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Declaration {
+    Struct(StructDefinition),
+    Enum(EnumDefinition),
+}
+
+/// Whether a declaration is a struct or an enum, for when only its name is known otherwise
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeclarationKind {
+    #[default]
+    Struct,
+    Enum,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Serialize)]
+pub struct StructDefinition {
+    pub name: String,
+    pub members: Vec<StructMember>,
+    /// The documentation of the struct, from its doc comments
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Serialize)]
+pub struct StructMember {
+    pub name: String,
+    pub data_type: DataType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field_number: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Serialize)]
+pub struct EnumDefinition {
+    pub name: String,
+    pub members: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl Schema {
+    /// Builds the schema from a data definition AST
+    /// # Returns
+    /// The schema, or CompilationError::InvalidAST if the AST is not a valid data definition, e.g.
+    /// an enum containing struct members
+    pub fn from_ast(ast: &ASTNode) -> Result<Self, CompilationError> {
+        let declarations = match hoist_inline_types(ast)? {
+            ASTNode::DataDefinition(definition) => definition
+                .child_nodes
+                .iter()
+                .map(Declaration::from_ast)
+                .collect::<Result<Vec<Declaration>, CompilationError>>()?,
+            _ => return Err(CompilationError::InvalidAST),
+        };
+        Ok(Self { declarations })
+    }
+
+    pub fn structs(&self) -> impl Iterator<Item = &StructDefinition> {
+        self.declarations.iter().filter_map(|declaration| match declaration {
+            Declaration::Struct(struct_definition) => Some(struct_definition),
+            Declaration::Enum(_) => None,
+        })
+    }
+
+    pub fn enums(&self) -> impl Iterator<Item = &EnumDefinition> {
+        self.declarations.iter().filter_map(|declaration| match declaration {
+            Declaration::Enum(enum_definition) => Some(enum_definition),
+            Declaration::Struct(_) => None,
+        })
+    }
+
+    /// Finds the declaration of a user defined type
+    pub fn declaration(&self, name: &str) -> Option<&Declaration> {
+        self.declarations
+            .iter()
+            .find(|declaration| declaration.name() == name)
+    }
+
+    /// Whether a user defined type is a struct or an enum, looking it up in the schema and then
+    /// in the types declared by other files. Types which cannot be resolved are assumed to be
+    /// structs
+    pub fn declaration_kind(&self, name: &str, external_types: &[ExternalType]) -> DeclarationKind {
+        match self.declaration(name) {
+            Some(declaration) => declaration.kind(),
+            None => external_types
+                .iter()
+                .find(|external_type| external_type.name == name)
+                .map(|external_type| external_type.kind)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Orders the declarations such that every declaration comes after the declarations its
+    /// members refer to, where possible. Declarations which refer to each other cyclically cannot
+    /// be fully ordered; a reference to a declaration which comes later is then left in place.
+    /// Otherwise, the original declaration order is kept
+    pub fn dependency_order(&self) -> Vec<&Declaration> {
+        fn visit<'a>(
+            schema: &'a Schema,
+            declaration: &'a Declaration,
+            visited: &mut Vec<&'a str>,
+            ordered: &mut Vec<&'a Declaration>,
+        ) {
+            if visited.contains(&declaration.name()) {
+                return;
+            }
+            visited.push(declaration.name());
+            if let Declaration::Struct(struct_definition) = declaration {
+                for member in &struct_definition.members {
+                    for name in member.data_type.user_defined_types() {
+                        if let Some(dependency) = schema.declaration(name) {
+                            visit(schema, dependency, visited, ordered);
+                        }
+                    }
+                }
+            }
+            ordered.push(declaration);
+        }
+
+        let mut visited = Vec::new();
+        let mut ordered = Vec::new();
+        for declaration in &self.declarations {
+            visit(self, declaration, &mut visited, &mut ordered);
+        }
+        ordered
+    }
+}
+
+impl DataType {
+    /// The names of the user defined types a data type refers to, looking through options and
+    /// arrays
+    pub fn user_defined_types(&self) -> Vec<&str> {
+        match self {
+            DataType::Option(inner_type) | DataType::Array(inner_type) => inner_type.user_defined_types(),
+            DataType::UserDefined(name) => vec![name.as_str()],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Declaration {
+    pub fn name(&self) -> &str {
+        match self {
+            Declaration::Struct(struct_definition) => &struct_definition.name,
+            Declaration::Enum(enum_definition) => &enum_definition.name,
+        }
+    }
+
+    pub fn kind(&self) -> DeclarationKind {
+        match self {
+            Declaration::Struct(_) => DeclarationKind::Struct,
+            Declaration::Enum(_) => DeclarationKind::Enum,
+        }
+    }
+
+    /// Converts a hoisted top level declaration
+    fn from_ast(ast: &ASTNode) -> Result<Self, CompilationError> {
+        match ast {
+            ASTNode::StructDeclaration(struct_declaration) => {
+                Ok(Declaration::Struct(StructDefinition::from_ast(struct_declaration)?))
+            }
+            ASTNode::EnumDeclaration(enum_declaration) => {
+                Ok(Declaration::Enum(EnumDefinition::from_ast(enum_declaration)?))
+            }
+            _ => Err(CompilationError::InvalidAST),
+        }
+    }
+}
+
+impl StructDefinition {
+    /// The field number of every member, in member order. Members without an explicit field
+    /// number are numbered in declaration order, starting from 1 and skipping the numbers which
+    /// are assigned explicitly
+    pub fn field_numbers(&self) -> Vec<u32> {
+        let explicit: Vec<u32> = self.members.iter().filter_map(|member| member.field_number).collect();
+        let mut next = 1;
+        self.members
+            .iter()
+            .map(|member| match member.field_number {
+                Some(field_number) => field_number,
+                None => {
+                    while explicit.contains(&next) {
+                        next += 1;
+                    }
+                    next += 1;
+                    next - 1
+                }
+            })
+            .collect()
+    }
+
+    fn from_ast(struct_declaration: &NamedStatementList) -> Result<Self, CompilationError> {
+        let members = struct_declaration
+            .child_nodes
+            .iter()
+            .map(|node| match node {
+                ASTNode::StructMemberDeclaration(member) => match member.data_type.borrow() {
+                    ASTNode::TypeLiteral(data_type) => Ok(StructMember {
+                        name: member.name.clone(),
+                        data_type: data_type.clone(),
+                        field_number: member.field_number,
+                        description: member.description.clone(),
+                    }),
+                    _ => Err(CompilationError::InvalidAST),
+                },
+                _ => Err(CompilationError::InvalidAST),
+            })
+            .collect::<Result<Vec<StructMember>, CompilationError>>()?;
+
+        for (index, member) in members.iter().enumerate() {
+            if let Some(field_number) = member.field_number {
+                if members[..index].iter().any(|other| other.field_number == Some(field_number)) {
+                    return Err(CompilationError::InvalidFieldNumber(format!(
+                        "field number {} is used more than once in '{}'",
+                        field_number, struct_declaration.name
+                    )));
+                }
+            }
+        }
+
+        Ok(Self {
+            name: struct_declaration.name.clone(),
+            members,
+            description: struct_declaration.description.clone(),
+        })
+    }
+}
+
+impl EnumDefinition {
+    fn from_ast(enum_declaration: &NamedStatementList) -> Result<Self, CompilationError> {
+        let members = enum_declaration
+            .child_nodes
+            .iter()
+            .map(|node| match node {
+                ASTNode::EnumMemberDeclaration(member) => Ok(member.name.clone()),
+                _ => Err(CompilationError::InvalidAST),
+            })
+            .collect::<Result<Vec<String>, CompilationError>>()?;
+
+        Ok(Self {
+            name: enum_declaration.name.clone(),
+            members,
+            description: enum_declaration.description.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+
+    // Inline declarations nest, and are hoisted innermost first, keeping their descriptions
+    const TEXT: &str = "
+/// A node of a tree
+struct node {
+  /// Where the node is
+  place: struct place { kind: enum kind { leaf, branch, }, depth @2: u8 },
+  parent: option(node),
+}
+";
+
+    #[test]
+    fn test_schema_from_ast() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+        let member = |name: &str, data_type: DataType, field_number: Option<u32>, description: Option<&str>| StructMember {
+            name: name.to_owned(),
+            data_type,
+            field_number,
+            description: description.map(str::to_owned),
+        };
+
+        let expected_schema = Schema {
+            declarations: vec![
+                Declaration::Enum(EnumDefinition {
+                    name: "kind".to_owned(),
+                    members: vec!["leaf".to_owned(), "branch".to_owned()],
+                    description: None,
+                }),
+                Declaration::Struct(StructDefinition {
+                    name: "place".to_owned(),
+                    members: vec![
+                        member("kind", DataType::UserDefined("kind".to_owned()), None, None),
+                        member("depth", DataType::U8, Some(2), None),
+                    ],
+                    description: None,
+                }),
+                Declaration::Struct(StructDefinition {
+                    name: "node".to_owned(),
+                    members: vec![
+                        member("place", DataType::UserDefined("place".to_owned()), None, Some("Where the node is")),
+                        member("parent", DataType::Option(Box::new(DataType::UserDefined("node".to_owned()))), None, None),
+                    ],
+                    description: Some("A node of a tree".to_owned()),
+                }),
+            ],
+        };
+
+        assert_eq!(Schema::from_ast(&ast).expect("should build schema"), expected_schema);
+    }
+
+    #[test]
+    fn test_dependency_order() {
+        let text = "struct order { buyer: person, next: option(order) } struct person { friend: option(person), role: role } enum role { admin, }";
+        let ast = parse_tokens(lex_tokens(text.to_owned()).expect("should lex")).expect("should parse");
+        let schema = Schema::from_ast(&ast).expect("should build schema");
+
+        let names: Vec<&str> = schema.dependency_order().iter().map(|declaration| declaration.name()).collect();
+        assert_eq!(names, vec!["role", "person", "order"]);
+    }
+
+    #[test]
+    fn test_field_numbers() {
+        let text = "struct person { name: string, id @1: u64, age: u8, email @3: string, phone: string }";
+        let ast = parse_tokens(lex_tokens(text.to_owned()).expect("should lex")).expect("should parse");
+        let schema = Schema::from_ast(&ast).expect("should build schema");
+
+        let person = schema.structs().next().expect("should have a struct");
+        assert_eq!(person.field_numbers(), vec![2, 1, 4, 3, 5]);
+
+        let duplicate = "struct person { id @1: u64, age @1: u8 }";
+        let ast = parse_tokens(lex_tokens(duplicate.to_owned()).expect("should lex")).expect("should parse");
+        assert!(matches!(Schema::from_ast(&ast), Err(CompilationError::InvalidFieldNumber(_))));
+    }
+}
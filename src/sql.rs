@@ -0,0 +1,514 @@
+/// Generation for SQL, as `CREATE TABLE` statements
+///
+/// Every struct becomes a table, with a column per member. Names are converted to `snake_case`,
+/// and quoted where they are reserved words. `Option` members are nullable, and every other column
+/// is `NOT NULL`. A member named `id` is the primary key of its table; tables without one get a
+/// generated `id` key instead, which foreign keys to tables declared in other files are assumed to
+/// refer to.
+///
+/// Members of other structs are either flattened into the table, as columns prefixed with the
+/// member name, or stored in the table of their struct and referred to by a `<member>_id` foreign
+/// key. Structs which refer to themselves, directly or not, and structs declared in other files,
+/// are always referred to by foreign key.
+///
+/// Arrays are stored in a join table named `<table>_<member>`, keyed by the owning row and the
+/// `position` of the element. Elements are a `value` column, or a `value_id` foreign key for
+/// structs. An absent `Option` of an array is stored the same as an empty array.
+///
+/// In SQLite, every integer is a signed 64-bit `INTEGER`, which cannot hold the largest `u64` values.
+///
+/// Supported options:
+/// - `dialect`: `postgres` (the default) or `sqlite`
+/// - `enums`: `check` (the default) stores enums as text, constrained to the member names, and
+///   `native` uses a Postgres enum type
+/// - `nested`: `flatten` (the default) or `table`, referring to nested structs by foreign key
+use crate::compilation_target::{CompilationError, CompilationInfo, CompilationTarget};
+use crate::naming::snake_case;
+use crate::parser::{ASTNode, DataType};
+use crate::schema::{Declaration, DeclarationKind, EnumDefinition, Schema, StructDefinition};
+
+/// Reserved words which are quoted where they name a table or column: the reserved key words of
+/// Postgres, including those which can be types or functions, and the key words of SQLite
+const RESERVED_WORDS: [&str; 183] = [
+    "abort", "action", "add", "after", "all", "alter", "always", "analyse", "analyze", "and", "any", "array", "as",
+    "asc", "asymmetric", "attach", "authorization", "autoincrement", "before", "begin", "between", "binary", "both",
+    "by", "cascade", "case", "cast", "check", "collate", "collation", "column", "commit", "concurrently",
+    "conflict", "constraint", "create", "cross", "current", "current_catalog", "current_date", "current_role",
+    "current_schema", "current_time", "current_timestamp", "current_user", "database", "default", "deferrable",
+    "deferred", "delete", "desc", "detach", "distinct", "do", "drop", "each", "else", "end", "escape", "except",
+    "exclude", "exclusive", "exists", "explain", "fail", "false", "fetch", "filter", "first", "following", "for",
+    "foreign", "freeze", "from", "full", "generated", "glob", "grant", "group", "groups", "having", "if", "ignore",
+    "ilike", "immediate", "in", "index", "indexed", "initially", "inner", "insert", "instead", "intersect", "into",
+    "is", "isnull", "join", "key", "last", "lateral", "leading", "left", "like", "limit", "localtime",
+    "localtimestamp", "match", "materialized", "natural", "no", "not", "nothing", "notnull", "null", "nulls", "of",
+    "offset", "on", "only", "or", "order", "others", "outer", "over", "overlaps", "partition", "placing", "plan",
+    "pragma", "preceding", "primary", "query", "raise", "range", "recursive", "references", "regexp", "reindex",
+    "release", "rename", "replace", "restrict", "returning", "right", "rollback", "row", "rows", "savepoint",
+    "select", "session_user", "set", "similar", "some", "symmetric", "system_user", "table", "tablesample", "temp",
+    "temporary", "then", "ties", "to", "trailing", "transaction", "trigger", "true", "unbounded", "union", "unique",
+    "update", "user", "using", "vacuum", "values", "variadic", "verbose", "view", "virtual", "when", "where",
+    "window", "with", "without",
+];
+
+pub struct SqlGenerator;
+
+impl CompilationTarget for SqlGenerator {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        let schema = Schema::from_ast(ast)?;
+        let mut generator = SqlSchemaGenerator::new(&schema, compilation_info)?;
+        let statements = generator.generate()?;
+
+        Ok(format!("{}\n{}", generate_preamble(compilation_info), statements))
+    }
+
+    fn warnings(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Vec<String> {
+        let Ok(schema) = Schema::from_ast(ast) else {
+            return Vec::new();
+        };
+        match SqlSchemaGenerator::new(&schema, compilation_info) {
+            Ok(mut generator) => match generator.generate() {
+                Ok(_) => generator.warnings,
+                Err(_) => Vec::new(),
+            },
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+fn generate_preamble(compilation_info: &CompilationInfo) -> String {
+    compilation_info.preamble_comments.iter()
+        .map(|preamble| format!("-- {}", preamble))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Quotes an identifier if it is a reserved word
+fn identifier(name: &str) -> String {
+    if RESERVED_WORDS.contains(&name) {
+        format!("\"{}\"", name)
+    } else {
+        name.to_owned()
+    }
+}
+
+fn table_name(struct_name: &str) -> String {
+    snake_case(struct_name)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Dialect {
+    Postgres,
+    Sqlite,
+}
+
+struct Column {
+    name: String,
+    definition: String,
+}
+
+struct SqlSchemaGenerator<'a> {
+    schema: &'a Schema,
+    compilation_info: &'a CompilationInfo,
+    dialect: Dialect,
+    native_enums: bool,
+    flatten: bool,
+    /// The tables created so far, as foreign keys to a table created later cannot be declared
+    /// with the column in Postgres
+    created_tables: Vec<String>,
+    /// Statements adding the foreign keys to tables created later
+    deferred_statements: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl<'a> SqlSchemaGenerator<'a> {
+    fn new(schema: &'a Schema, compilation_info: &'a CompilationInfo) -> Result<Self, CompilationError> {
+        let options = &compilation_info.options;
+        let dialect = match options.get_choice("dialect", &["postgres", "sqlite"], "postgres")? {
+            "sqlite" => Dialect::Sqlite,
+            _ => Dialect::Postgres,
+        };
+        let native_enums = options.get_choice("enums", &["check", "native"], "check")? == "native";
+        if native_enums && dialect == Dialect::Sqlite {
+            return Err(CompilationError::InvalidOption(
+                "native enums are not supported by sqlite, expected enums=check".to_owned(),
+            ));
+        }
+        let flatten = options.get_choice("nested", &["flatten", "table"], "flatten")? == "flatten";
+
+        Ok(Self {
+            schema,
+            compilation_info,
+            dialect,
+            native_enums,
+            flatten,
+            created_tables: Vec::new(),
+            deferred_statements: Vec::new(),
+            warnings: Vec::new(),
+        })
+    }
+
+    fn generate(&mut self) -> Result<String, CompilationError> {
+        let mut statements = Vec::new();
+        for declaration in self.schema.dependency_order() {
+            match declaration {
+                Declaration::Struct(struct_definition) => statements.append(&mut self.generate_tables(struct_definition)?),
+                Declaration::Enum(enum_definition) => {
+                    if enum_definition.members.is_empty() {
+                        return Err(CompilationError::UnsupportedType(format!(
+                            "'{}' has no members, which SQL cannot constrain a column to",
+                            enum_definition.name
+                        )));
+                    }
+                    if self.native_enums {
+                        statements.push(generate_enum_type(enum_definition));
+                    }
+                }
+            }
+        }
+        statements.append(&mut self.deferred_statements);
+
+        Ok(statements.join("\n"))
+    }
+
+    fn is_struct(&self, name: &str) -> bool {
+        self.schema.declaration_kind(name, &self.compilation_info.external_types) == DeclarationKind::Struct
+    }
+
+    /// The column type of the primary key of a struct's table, and so of foreign keys to it
+    fn key_type(&self, struct_name: &str) -> String {
+        let key_member = match self.schema.declaration(struct_name) {
+            Some(Declaration::Struct(struct_definition)) => {
+                struct_definition.members.iter().find(|member| member.name == "id")
+            }
+            _ => None,
+        };
+        match key_member {
+            Some(member) => self.column_type(&member.data_type),
+            None => match self.dialect {
+                Dialect::Postgres => "BIGINT".to_owned(),
+                Dialect::Sqlite => "INTEGER".to_owned(),
+            },
+        }
+    }
+
+    /// Generates the table of a struct, followed by the join tables of its arrays
+    fn generate_tables(&mut self, struct_definition: &'a StructDefinition) -> Result<Vec<String>, CompilationError> {
+        let table = table_name(&struct_definition.name);
+        // Registered first, so that references of the table to itself are declared in place
+        self.created_tables.push(table.clone());
+
+        let mut columns = Vec::new();
+        let mut join_tables = Vec::new();
+        match struct_definition.members.iter().find(|member| member.name == "id") {
+            Some(member) => match &member.data_type {
+                DataType::Option(_) | DataType::Array(_) => {
+                    return Err(CompilationError::UnsupportedType(format!(
+                        "'id' of '{}' is its primary key, so cannot be an option or array",
+                        struct_definition.name
+                    )))
+                }
+                DataType::UserDefined(name) if self.is_struct(name) => {
+                    return Err(CompilationError::UnsupportedType(format!(
+                        "'id' of '{}' is its primary key, so cannot be a struct",
+                        struct_definition.name
+                    )))
+                }
+                _ => {}
+            },
+            None => columns.push(Column {
+                name: "id".to_owned(),
+                definition: match self.dialect {
+                    Dialect::Postgres => "BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY".to_owned(),
+                    Dialect::Sqlite => "INTEGER PRIMARY KEY".to_owned(),
+                },
+            }),
+        }
+        let mut flattened = vec![struct_definition.name.as_str()];
+        self.generate_columns(struct_definition, &struct_definition.name, "", false, &mut flattened, &mut columns, &mut join_tables)?;
+
+        let column_definitions = columns
+            .iter()
+            .map(|column| format!("    {} {}", identifier(&column.name), column.definition))
+            .collect::<Vec<String>>()
+            .join(",\n");
+        let mut statements = vec![format!("CREATE TABLE {} (\n{}\n);\n", identifier(&table), column_definitions)];
+        statements.append(&mut join_tables);
+        Ok(statements)
+    }
+
+    /// Generates the columns of the members of a struct, which may be flattened into the table of
+    /// another struct. `flattened` holds the structs being flattened, to refer to them by foreign
+    /// key rather than flatten them endlessly
+    #[allow(clippy::too_many_arguments)]
+    fn generate_columns(
+        &mut self,
+        struct_definition: &'a StructDefinition,
+        owner: &str,
+        prefix: &str,
+        nullable: bool,
+        flattened: &mut Vec<&'a str>,
+        columns: &mut Vec<Column>,
+        join_tables: &mut Vec<String>,
+    ) -> Result<(), CompilationError> {
+        for member in &struct_definition.members {
+            let name = format!("{}{}", prefix, snake_case(&member.name));
+            let (data_type, nullable) = match &member.data_type {
+                DataType::Option(inner_type) => (inner_type.as_ref(), true),
+                data_type => (data_type, nullable),
+            };
+
+            match data_type {
+                DataType::Option(_) => {
+                    return Err(CompilationError::UnsupportedType(format!(
+                        "'{}' is an option of an option, which SQL cannot represent",
+                        member.name
+                    )))
+                }
+                DataType::Array(element_type) => {
+                    join_tables.push(self.generate_join_table(owner, &name, element_type)?);
+                }
+                DataType::UserDefined(type_name) if self.is_struct(type_name) => {
+                    let nested_definition = match self.schema.declaration(type_name) {
+                        Some(Declaration::Struct(nested_definition)) if self.flatten => Some(nested_definition),
+                        _ => None,
+                    };
+                    match nested_definition {
+                        Some(nested_definition) if !flattened.contains(&type_name.as_str()) => {
+                            flattened.push(type_name);
+                            self.generate_columns(
+                                nested_definition,
+                                owner,
+                                &format!("{}_", name),
+                                nullable,
+                                flattened,
+                                columns,
+                                join_tables,
+                            )?;
+                            flattened.pop();
+                        }
+                        _ => {
+                            let column = format!("{}_id", name);
+                            let definition = self.generate_foreign_key(&table_name(owner), &column, type_name, nullable);
+                            columns.push(Column { name: column, definition });
+                        }
+                    }
+                }
+                data_type => {
+                    let mut definition = self.column_type(data_type);
+                    if name == "id" && prefix.is_empty() {
+                        definition += " PRIMARY KEY";
+                    } else if !nullable {
+                        definition += " NOT NULL";
+                    }
+                    definition += &self.generate_check(&name, data_type);
+                    columns.push(Column { name, definition });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Generates the definition of a foreign key column, or defers the reference until the
+    /// referenced table has been created
+    fn generate_foreign_key(&mut self, table: &str, column: &str, struct_name: &str, nullable: bool) -> String {
+        let referenced_table = table_name(struct_name);
+        let mut definition = self.key_type(struct_name);
+        if !nullable {
+            definition += " NOT NULL";
+        }
+
+        let declared_later = self.schema.declaration(struct_name).is_some() && !self.created_tables.contains(&referenced_table);
+        if declared_later && self.dialect == Dialect::Postgres {
+            self.deferred_statements.push(format!(
+                "ALTER TABLE {} ADD FOREIGN KEY ({}) REFERENCES {} (id);\n",
+                identifier(table),
+                identifier(column),
+                identifier(&referenced_table)
+            ));
+        } else {
+            definition += &format!(" REFERENCES {} (id)", identifier(&referenced_table));
+        }
+        definition
+    }
+
+    /// Generates the join table storing the elements of an array member of the owning struct
+    fn generate_join_table(&mut self, owner: &str, name: &str, element_type: &DataType) -> Result<String, CompilationError> {
+        let table = table_name(owner);
+        let join_table = format!("{}_{}", table, name);
+        let owner_column = format!("{}_id", table);
+        let (element_type, nullable) = match element_type {
+            DataType::Option(inner_type) => (inner_type.as_ref(), true),
+            element_type => (element_type, false),
+        };
+
+        let value_column = match element_type {
+            DataType::Option(_) | DataType::Array(_) => {
+                return Err(CompilationError::UnsupportedType(format!(
+                    "'{}' is an array of nested options or arrays, which SQL cannot represent",
+                    name
+                )))
+            }
+            DataType::UserDefined(type_name) if self.is_struct(type_name) => Column {
+                name: "value_id".to_owned(),
+                definition: self.generate_foreign_key(&join_table, "value_id", type_name, nullable),
+            },
+            element_type => {
+                let mut definition = self.column_type(element_type);
+                if !nullable {
+                    definition += " NOT NULL";
+                }
+                definition += &self.generate_check("value", element_type);
+                Column { name: "value".to_owned(), definition }
+            }
+        };
+
+        Ok(format!(
+            "CREATE TABLE {} (\n    {} {} NOT NULL REFERENCES {} (id) ON DELETE CASCADE,\n    position INTEGER NOT NULL,\n    {} {},\n    PRIMARY KEY ({}, position)\n);\n",
+            identifier(&join_table),
+            identifier(&owner_column),
+            self.key_type(owner),
+            identifier(&table),
+            value_column.name,
+            value_column.definition,
+            identifier(&owner_column)
+        ))
+    }
+
+    /// Generates the `CHECK` constraint of a column storing an enum as text
+    fn generate_check(&mut self, column: &str, data_type: &DataType) -> String {
+        let DataType::UserDefined(name) = data_type else {
+            return String::new();
+        };
+        if self.native_enums {
+            return String::new();
+        }
+        match self.schema.declaration(name) {
+            Some(Declaration::Enum(enum_definition)) => {
+                let values = enum_definition
+                    .members
+                    .iter()
+                    .map(|member| format!("'{}'", member))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                format!(" CHECK ({} IN ({}))", identifier(column), values)
+            }
+            _ => {
+                self.warnings.push(format!(
+                    "the values of enum '{}' are declared in another file, so column '{}' is not constrained to them",
+                    name, column
+                ));
+                String::new()
+            }
+        }
+    }
+
+    fn column_type(&self, data_type: &DataType) -> String {
+        let postgres = self.dialect == Dialect::Postgres;
+        match data_type {
+            DataType::U8 | DataType::I8 | DataType::I16 if postgres => "SMALLINT".to_owned(),
+            DataType::U16 | DataType::I32 if postgres => "INTEGER".to_owned(),
+            DataType::U32 | DataType::I64 if postgres => "BIGINT".to_owned(),
+            DataType::U64 if postgres => "NUMERIC(20)".to_owned(),
+            DataType::U8
+            | DataType::I8
+            | DataType::U16
+            | DataType::I16
+            | DataType::U32
+            | DataType::I32
+            | DataType::U64
+            | DataType::I64 => "INTEGER".to_owned(),
+            DataType::F32 => "REAL".to_owned(),
+            DataType::F64 if postgres => "DOUBLE PRECISION".to_owned(),
+            DataType::F64 => "REAL".to_owned(),
+            DataType::Char if postgres => "CHAR(1)".to_owned(),
+            DataType::Char | DataType::String => "TEXT".to_owned(),
+            DataType::Bool if postgres => "BOOLEAN".to_owned(),
+            DataType::Bool => "INTEGER".to_owned(),
+            DataType::UserDefined(name) if self.native_enums => identifier(&snake_case(name)),
+            DataType::UserDefined(_) => "TEXT".to_owned(),
+            DataType::Option(inner_type) | DataType::Array(inner_type) => self.column_type(inner_type),
+        }
+    }
+}
+
+fn generate_enum_type(enum_definition: &EnumDefinition) -> String {
+    let values = enum_definition
+        .members
+        .iter()
+        .map(|member| format!("'{}'", member))
+        .collect::<Vec<String>>()
+        .join(", ");
+    format!("CREATE TYPE {} AS ENUM ({});\n", identifier(&snake_case(&enum_definition.name)), values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+
+    const TEXT: &str = "struct user { name: string, nickname: option(string), mood: enum mood { happy, verySad, }, address: struct address { street: string, zipCode: option(u32) }, tags: array(string), manager: option(user) }";
+
+    const GENERATED_CODE: &str = "
+CREATE TABLE address (
+    id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    street TEXT NOT NULL,
+    zip_code BIGINT
+);
+
+CREATE TABLE \"user\" (
+    id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    name TEXT NOT NULL,
+    nickname TEXT,
+    mood TEXT NOT NULL CHECK (mood IN ('happy', 'verySad')),
+    address_street TEXT NOT NULL,
+    address_zip_code BIGINT,
+    manager_id BIGINT REFERENCES \"user\" (id)
+);
+
+CREATE TABLE user_tags (
+    user_id BIGINT NOT NULL REFERENCES \"user\" (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (user_id, position)
+);
+";
+
+    #[test]
+    fn test_generate_sql() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+
+        assert_eq!(
+            SqlGenerator
+                .generate_code(&ast, &CompilationInfo::default())
+                .expect("should generate code"),
+            GENERATED_CODE
+        );
+    }
+
+    #[test]
+    fn test_generate_sql_tables() {
+        let ast = parse_tokens(
+            lex_tokens("struct order { id: u32, items: array(item) } struct item { id: string, kind: enum kind { book, }, }".to_owned())
+                .expect("should lex"),
+        )
+        .expect("should parse");
+        let mut compilation_info = CompilationInfo::default();
+        compilation_info.options.set("nested", "table");
+        compilation_info.options.set("enums", "native");
+
+        let generated_code = SqlGenerator
+            .generate_code(&ast, &compilation_info)
+            .expect("should generate code");
+        assert!(generated_code.contains("CREATE TABLE \"order\" (\n    id BIGINT PRIMARY KEY\n);\n"));
+        assert!(generated_code.contains("    value_id TEXT NOT NULL REFERENCES item (id),\n"));
+        assert!(generated_code.contains("CREATE TYPE kind AS ENUM ('book');\n"));
+        assert!(generated_code.contains("    kind kind NOT NULL\n"));
+
+        let ast = parse_tokens(lex_tokens("struct t { union: string, when: u8 }".to_owned()).expect("should lex")).expect("should parse");
+        let generated_code = SqlGenerator
+            .generate_code(&ast, &CompilationInfo::default())
+            .expect("should generate code");
+        assert!(generated_code.contains("    \"union\" TEXT NOT NULL,\n    \"when\" SMALLINT NOT NULL\n"));
+    }
+}
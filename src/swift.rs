@@ -0,0 +1,313 @@
+/// Generation for Swift
+///
+/// Structs become `Codable`, `Equatable` structs and enums become `String` backed `Codable`
+/// enums, named in Swift conventions with `CodingKeys` mapping renamed properties to their
+/// original names. A struct cannot store itself, so recursive structs become final classes.
+///
+/// Supported options:
+/// - `access_level`: `internal` (the default) or `public`. Public structs also get a public
+///   memberwise initializer, as the synthesized one is internal
+use crate::compilation_target::{CompilationError, CompilationInfo, CompilationTarget};
+use crate::naming::{camel_case, pascal_case};
+use crate::parser::{ASTNode, DataType};
+use crate::schema::{Declaration, EnumDefinition, Schema, StructDefinition};
+
+/// Keywords which cannot name a property or case unless escaped with backticks
+const KEYWORDS: [&str; 38] = [
+    "as", "associatedtype", "break", "case", "catch", "class", "continue", "default", "defer", "deinit", "do", "else",
+    "enum", "extension", "fallthrough", "false", "fileprivate", "for", "func", "guard", "if", "import", "in", "init",
+    "inout", "internal", "is", "let", "nil", "operator", "private", "protocol", "public", "repeat", "return", "self",
+    "static", "struct",
+];
+
+pub struct SwiftGenerator;
+
+impl CompilationTarget for SwiftGenerator {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        let access_level = compilation_info.options.get_choice("access_level", &["internal", "public"], "internal")?;
+        let modifier = match access_level {
+            "public" => "public ",
+            _ => "",
+        };
+        let schema = Schema::from_ast(ast)?;
+
+        let declarations = schema
+            .declarations
+            .iter()
+            .map(|declaration| match declaration {
+                Declaration::Struct(struct_definition) => {
+                    generate_struct(struct_definition, modifier, is_recursive(&schema, struct_definition))
+                }
+                Declaration::Enum(enum_definition) => generate_enum(enum_definition, modifier),
+            })
+            .map(|declaration| format!("\n{}", declaration))
+            .collect::<String>();
+
+        Ok(format!(
+            "{}\nimport Foundation\n{}",
+            generate_preamble(compilation_info),
+            declarations
+        ))
+    }
+}
+
+fn generate_preamble(compilation_info: &CompilationInfo) -> String {
+    compilation_info.preamble_comments.iter()
+        .map(|preamble| format!("// {}", preamble))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// The user defined type a member stores inline, through any options. Arrays store their elements
+/// out of line
+fn inline_type(data_type: &DataType) -> Option<&str> {
+    match data_type {
+        DataType::Option(inner_type) => inline_type(inner_type),
+        DataType::UserDefined(name) => Some(name),
+        _ => None,
+    }
+}
+
+/// Whether a struct stores itself inline, directly or through other structs
+fn is_recursive(schema: &Schema, struct_definition: &StructDefinition) -> bool {
+    let mut visited = Vec::new();
+    let mut pending = vec![struct_definition];
+    while let Some(current) = pending.pop() {
+        for name in current.members.iter().filter_map(|member| inline_type(&member.data_type)) {
+            if name == struct_definition.name {
+                return true;
+            }
+            if let Some(Declaration::Struct(member_struct)) = schema.declaration(name) {
+                if !visited.contains(&name) {
+                    visited.push(name);
+                    pending.push(member_struct);
+                }
+            }
+        }
+    }
+    false
+}
+
+fn generate_struct(struct_definition: &StructDefinition, modifier: &str, recursive: bool) -> String {
+    let properties = struct_definition
+        .members
+        .iter()
+        .map(|member| {
+            format!(
+                "    {}let {}: {}\n",
+                modifier,
+                escape_keyword(camel_case(&member.name)),
+                generate_type_name(&member.data_type)
+            )
+        })
+        .collect::<String>();
+
+    let name = pascal_case(&struct_definition.name);
+    let mut body = properties;
+    // Classes get neither a memberwise initializer nor `==` synthesized
+    if recursive || (!modifier.is_empty() && !struct_definition.members.is_empty()) {
+        body += &generate_initializer(struct_definition, modifier);
+    }
+    if recursive {
+        body += &generate_equality(struct_definition, &name, modifier);
+    }
+    if struct_definition.members.iter().any(|member| camel_case(&member.name) != member.name) {
+        body += &generate_coding_keys(struct_definition);
+    }
+    let keyword = match recursive {
+        true => "final class",
+        false => "struct",
+    };
+    format!("{}{} {}: Codable, Equatable {{\n{}}}\n", modifier, keyword, name, body)
+}
+
+fn generate_equality(struct_definition: &StructDefinition, name: &str, modifier: &str) -> String {
+    let comparison = match struct_definition.members.is_empty() {
+        true => "true".to_owned(),
+        false => struct_definition
+            .members
+            .iter()
+            .map(|member| {
+                let property = escape_keyword(camel_case(&member.name));
+                format!("lhs.{} == rhs.{}", property, property)
+            })
+            .collect::<Vec<String>>()
+            .join(" && "),
+    };
+    format!(
+        "\n    {}static func == (lhs: {}, rhs: {}) -> Bool {{\n        {}\n    }}\n",
+        modifier, name, name, comparison
+    )
+}
+
+/// Generates the memberwise initializer, which Swift only synthesizes as internal
+fn generate_initializer(struct_definition: &StructDefinition, modifier: &str) -> String {
+    let parameters = struct_definition
+        .members
+        .iter()
+        .map(|member| {
+            let default = match member.data_type {
+                DataType::Option(_) => " = nil",
+                _ => "",
+            };
+            format!(
+                "{}: {}{}",
+                escape_keyword(camel_case(&member.name)),
+                generate_type_name(&member.data_type),
+                default
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+    let assignments = struct_definition
+        .members
+        .iter()
+        .map(|member| {
+            let name = camel_case(&member.name);
+            format!("        self.{} = {}\n", name, escape_keyword(name.clone()))
+        })
+        .collect::<String>();
+    format!("\n    {}init({}) {{\n{}    }}\n", modifier, parameters, assignments)
+}
+
+fn generate_coding_keys(struct_definition: &StructDefinition) -> String {
+    let cases = struct_definition
+        .members
+        .iter()
+        .map(|member| generate_case(&member.name, "        "))
+        .collect::<String>();
+    format!("\n    enum CodingKeys: String, CodingKey {{\n{}    }}\n", cases)
+}
+
+fn generate_enum(enum_definition: &EnumDefinition, modifier: &str) -> String {
+    let name = pascal_case(&enum_definition.name);
+    if enum_definition.members.is_empty() {
+        // An enum without cases cannot have a raw type
+        return format!("{}enum {}: Codable {{\n}}\n", modifier, name);
+    }
+
+    let cases = enum_definition
+        .members
+        .iter()
+        .map(|member| generate_case(member, "    "))
+        .collect::<String>();
+    format!("{}enum {}: String, Codable {{\n{}}}\n", modifier, name, cases)
+}
+
+/// Generates an enum case named in `camelCase`, with the original name as its raw value if the
+/// name changes
+fn generate_case(name: &str, indentation: &str) -> String {
+    let case_name = camel_case(name);
+    if case_name == name {
+        format!("{}case {}\n", indentation, escape_keyword(case_name))
+    } else {
+        format!("{}case {} = \"{}\"\n", indentation, escape_keyword(case_name), name)
+    }
+}
+
+fn escape_keyword(name: String) -> String {
+    if KEYWORDS.contains(&name.as_str()) {
+        format!("`{}`", name)
+    } else {
+        name
+    }
+}
+
+/// Generates the type names. `Character` is not `Codable`, so chars are strings
+fn generate_type_name(data_type: &DataType) -> String {
+    match data_type {
+        DataType::U8 => "UInt8".to_owned(),
+        DataType::I8 => "Int8".to_owned(),
+        DataType::U16 => "UInt16".to_owned(),
+        DataType::I16 => "Int16".to_owned(),
+        DataType::U32 => "UInt32".to_owned(),
+        DataType::I32 => "Int32".to_owned(),
+        DataType::U64 => "UInt64".to_owned(),
+        DataType::I64 => "Int64".to_owned(),
+        DataType::F32 => "Float".to_owned(),
+        DataType::F64 => "Double".to_owned(),
+        DataType::Char | DataType::String => "String".to_owned(),
+        DataType::Bool => "Bool".to_owned(),
+        DataType::Option(inner_type) => format!("{}?", generate_type_name(inner_type)),
+        DataType::Array(inner_type) => format!("[{}]", generate_type_name(inner_type)),
+        DataType::UserDefined(name) => pascal_case(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+
+    const TEXT: &str = "struct person { UserId: u64, nickname: option(string), scores: array(f32), mood: enum mood { happy, VerySad, default, } }";
+
+    const GENERATED_CODE: &str = "
+import Foundation
+
+enum Mood: String, Codable {
+    case happy
+    case verySad = \"VerySad\"
+    case `default`
+}
+
+struct Person: Codable, Equatable {
+    let userId: UInt64
+    let nickname: String?
+    let scores: [Float]
+    let mood: Mood
+
+    enum CodingKeys: String, CodingKey {
+        case userId = \"UserId\"
+        case nickname
+        case scores
+        case mood
+    }
+}
+";
+
+    #[test]
+    fn test_generate_swift() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+
+        assert_eq!(
+            SwiftGenerator
+                .generate_code(&ast, &CompilationInfo::default())
+                .expect("should generate code"),
+            GENERATED_CODE
+        );
+    }
+
+    #[test]
+    fn test_generate_swift_public() {
+        let ast = parse_tokens(lex_tokens("struct point { x: f64, label: option(string) }".to_owned()).expect("should lex"))
+            .expect("should parse");
+        let mut compilation_info = CompilationInfo::default();
+        compilation_info.options.set("access_level", "public");
+
+        let generated_code = SwiftGenerator
+            .generate_code(&ast, &compilation_info)
+            .expect("should generate code");
+        assert!(generated_code.contains("public struct Point: Codable, Equatable {\n    public let x: Double\n"));
+        assert!(generated_code.contains(
+            "    public init(x: Double, label: String? = nil) {\n        self.x = x\n        self.label = label\n    }\n"
+        ));
+    }
+
+    #[test]
+    fn test_generate_swift_recursive() {
+        let text = "struct node { value: u8, next: option(node), children: array(leaf) } struct leaf { value: u8, children: array(leaf) }";
+        let ast = parse_tokens(lex_tokens(text.to_owned()).expect("should lex")).expect("should parse");
+
+        let generated_code = SwiftGenerator
+            .generate_code(&ast, &CompilationInfo::default())
+            .expect("should generate code");
+        assert!(generated_code.contains(
+            "final class Node: Codable, Equatable {\n    let value: UInt8\n    let next: Node?\n    let children: [Leaf]\n\n    \
+             init(value: UInt8, next: Node? = nil, children: [Leaf]) {\n        self.value = value\n        self.next = next\n        \
+             self.children = children\n    }\n\n    static func == (lhs: Node, rhs: Node) -> Bool {\n        \
+             lhs.value == rhs.value && lhs.next == rhs.next && lhs.children == rhs.children\n    }\n}\n"
+        ));
+        assert!(generated_code.contains("struct Leaf: Codable, Equatable {\n    let value: UInt8\n    let children: [Leaf]\n}\n"));
+    }
+}
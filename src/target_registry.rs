@@ -0,0 +1,279 @@
+/// Registry of the compilation targets known to the CLI
+///
+/// Targets are registered under any number of names (used with `--target`) and output file
+/// extensions (used to pick a target for an output file). The built in targets are registered by
+/// `TargetRegistry::default()`, and further targets may be registered by other crates, loaded from
+/// a dynamic library, or run as an external executable.
+///
+/// Later registrations take precedence over earlier ones, so a registered target may replace a
+/// built in target of the same name. Names of the form `template:<path>` are not registered, but
+/// always resolve to the template target at that path.
+use crate::avro::AvroGenerator;
+use crate::c::{CHeaderGenerator, CSourceGenerator};
+use crate::capnp::CapnpGenerator;
+use crate::compilation_target::{CompilationError, CompilationInfo, CompilationTarget, SupportFile, Target};
+use crate::csharp::CSharpGenerator;
+use crate::cxx::CXXGenerator;
+use crate::diagram::{DotGenerator, MermaidGenerator};
+use crate::docs::DocsGenerator;
+use crate::external_target::ExternalTarget;
+use crate::flatbuffers::FlatBuffersGenerator;
+use crate::go::GoGenerator;
+use crate::graphql::GraphQLGenerator;
+use crate::kotlin::KotlinGenerator;
+use crate::openapi::OpenApiGenerator;
+use crate::parser::ASTNode;
+use crate::proto::ProtoGenerator;
+use crate::python::PythonGenerator;
+use crate::sql::SqlGenerator;
+use crate::swift::SwiftGenerator;
+use crate::template::{TemplateTarget, TEMPLATE_TARGET_PREFIX};
+use crate::ts_mobx::TSMobXGenerator;
+use crate::typescript::TypeScriptGenerator;
+use crate::zod::ZodGenerator;
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::rc::Rc;
+
+/// Name of the function a target library must export. It must have the signature of
+/// `RegisterTargetsFn`, e.g.
+/// ```ignore
+/// #[no_mangle]
+/// pub fn fruko_register_targets(registry: &mut TargetRegistry) {
+///     registry.register(&["lua"], &["lua"], Box::new(LuaGenerator));
+/// }
+/// ```
+/// As this is a Rust ABI function, the library must be built with the same compiler and the same
+/// version of this crate as the CLI loading it.
+pub const REGISTER_TARGETS_SYMBOL: &str = "fruko_register_targets";
+
+pub type RegisterTargetsFn = fn(&mut TargetRegistry);
+
+struct Registration {
+    names: Vec<String>,
+    extensions: Vec<String>,
+    target: Rc<dyn CompilationTarget>,
+}
+
+pub struct TargetRegistry {
+    registrations: Vec<Registration>,
+}
+
+/// Error type for loading targets from a dynamic library or an external executable
+#[derive(Debug)]
+pub enum TargetLoadError {
+    Library(libloading::Error),
+    /// The command of an external target could not be split into a program and its arguments
+    Command(String),
+}
+
+impl Display for TargetLoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TargetLoadError::Library(error) => {
+                write!(f, "Could not load target library: {}", error)
+            }
+            TargetLoadError::Command(error) => {
+                write!(f, "Invalid external target command: {}", error)
+            }
+        }
+    }
+}
+
+impl Error for TargetLoadError {}
+
+impl Default for TargetRegistry {
+    /// Creates a registry containing all of the built in targets
+    fn default() -> Self {
+        let mut registry = Self::new();
+        // The C header has no extension, as `.h` outputs are C++ headers, so its output must be named
+        registry.register(&["c", "c11"], &[], Box::new(CHeaderGenerator));
+        registry.register(&["c-source", "c11-source"], &["c"], Box::new(CSourceGenerator));
+        registry.register(&["cxx", "c++"], &["h", "cpp"], Box::new(CXXGenerator {}));
+        // Registered before the MobX target, so that `.ts` outputs keep using MobX
+        registry.register(&["zod", "ts-zod"], &["ts"], Box::new(ZodGenerator {}));
+        registry.register(&["typescript", "ts-interfaces"], &["ts"], Box::new(TypeScriptGenerator {}));
+        registry.register(&["ts-mobx", "typescript-mobx"], &["ts"], Box::new(TSMobXGenerator {}));
+        registry.register(&["python", "py"], &["py"], Box::new(PythonGenerator {}));
+        registry.register(&["proto3", "proto"], &["proto"], Box::new(ProtoGenerator {}));
+        registry.register(&["go", "golang"], &["go"], Box::new(GoGenerator {}));
+        registry.register(&["csharp", "c#"], &["cs"], Box::new(CSharpGenerator {}));
+        registry.register(&["kotlin", "kt"], &["kt"], Box::new(KotlinGenerator {}));
+        registry.register(&["swift"], &["swift"], Box::new(SwiftGenerator {}));
+        registry.register(&["graphql", "gql"], &["graphql", "gql"], Box::new(GraphQLGenerator));
+        registry.register(&["sql"], &["sql"], Box::new(SqlGenerator));
+        registry.register(&["openapi", "openapi-yaml"], &["yaml", "yml"], Box::new(OpenApiGenerator { json: false }));
+        registry.register(&["openapi-json"], &["json"], Box::new(OpenApiGenerator { json: true }));
+        registry.register(&["avro", "avsc"], &["avsc"], Box::new(AvroGenerator));
+        registry.register(&["flatbuffers", "fbs"], &["fbs"], Box::new(FlatBuffersGenerator));
+        registry.register(&["capnp", "capnproto"], &["capnp"], Box::new(CapnpGenerator));
+        registry.register(&["docs", "docs-markdown"], &["md"], Box::new(DocsGenerator { html: false }));
+        registry.register(&["docs-html"], &["html"], Box::new(DocsGenerator { html: true }));
+        registry.register(&["dot", "graphviz"], &["dot", "gv"], Box::new(DotGenerator));
+        registry.register(&["mermaid"], &["mmd", "mermaid"], Box::new(MermaidGenerator));
+        registry
+    }
+}
+
+impl TargetRegistry {
+    /// Creates a registry without any targets
+    pub fn new() -> Self {
+        Self {
+            registrations: Vec::new(),
+        }
+    }
+
+    /// Registers a target under the supplied names and output file extensions
+    pub fn register(
+        &mut self,
+        names: &[&str],
+        extensions: &[&str],
+        target: Box<dyn CompilationTarget>,
+    ) {
+        self.registrations.push(Registration {
+            names: names.iter().map(|name| name.to_string()).collect(),
+            extensions: extensions.iter().map(|extension| extension.to_string()).collect(),
+            target: Rc::from(target),
+        });
+    }
+
+    /// Registers an executable as a target under the supplied name. See `ExternalTarget` for the
+    /// protocol used to communicate with the executable
+    pub fn register_external(&mut self, name: &str, command: &str) -> Result<(), TargetLoadError> {
+        self.register(&[name], &[], Box::new(ExternalTarget::new(command)?));
+        Ok(())
+    }
+
+    /// Loads a dynamic library, and registers the targets it exports through its
+    /// `REGISTER_TARGETS_SYMBOL` function
+    pub fn load_library(&mut self, path: &Path) -> Result<(), TargetLoadError> {
+        // SAFETY: loading a library runs its initialisers, and the exported function is trusted to
+        // match RegisterTargetsFn. Both are the responsibility of whoever supplied the library
+        let library = Rc::new(unsafe { libloading::Library::new(path) }.map_err(TargetLoadError::Library)?);
+        let register_targets = unsafe { library.get::<RegisterTargetsFn>(REGISTER_TARGETS_SYMBOL.as_bytes()) }
+            .map_err(TargetLoadError::Library)?;
+
+        let mut library_registry = Self::new();
+        register_targets(&mut library_registry);
+
+        for registration in library_registry.registrations {
+            self.registrations.push(Registration {
+                target: Rc::new(LibraryTarget {
+                    target: registration.target,
+                    _library: library.clone(),
+                }),
+                ..registration
+            });
+        }
+        Ok(())
+    }
+
+    /// Looks up a target by name, and failing that by output file extension
+    pub fn target(&self, name_or_extension: &str) -> Result<Target, CompilationError> {
+        if let Some(template_path) = name_or_extension.strip_prefix(TEMPLATE_TARGET_PREFIX) {
+            let target = TemplateTarget::from_file(Path::new(template_path))?;
+            return Ok(Target::new(name_or_extension, None, Rc::new(target)));
+        }
+
+        self.find(|registration| registration.names.iter().any(|name| name == name_or_extension))
+            .or_else(|| {
+                self.find(|registration| {
+                    registration.extensions.iter().any(|extension| extension == name_or_extension)
+                })
+            })
+            .ok_or_else(|| CompilationError::UnknownTarget(name_or_extension.to_owned()))
+    }
+
+    /// Looks up the target for an output file from its extension
+    pub fn target_for_file(&self, path: &Path) -> Result<Target, CompilationError> {
+        let extension = path.extension().and_then(OsStr::to_str).ok_or_else(|| {
+            CompilationError::UnknownTarget(path.to_string_lossy().into_owned())
+        })?;
+        self.target(extension)
+    }
+
+    /// The names of every registered target, in registration order
+    pub fn target_names(&self) -> Vec<&str> {
+        self.registrations
+            .iter()
+            .flat_map(|registration| registration.names.iter().map(String::as_str))
+            .collect()
+    }
+
+    fn find(&self, predicate: impl Fn(&Registration) -> bool) -> Option<Target> {
+        self.registrations
+            .iter()
+            .rev()
+            .find(|registration| predicate(registration))
+            .map(|registration| {
+                let name = registration.names.first().map(String::as_str).unwrap_or_default();
+                let extension = registration.extensions.first().map(String::as_str);
+                Target::new(name, extension, registration.target.clone())
+            })
+    }
+}
+
+/// Keeps the library a target was loaded from alive for as long as the target itself.
+/// The target is declared first so that it is dropped before the library is unloaded
+struct LibraryTarget {
+    target: Rc<dyn CompilationTarget>,
+    _library: Rc<libloading::Library>,
+}
+
+impl CompilationTarget for LibraryTarget {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        self.target.generate_code(ast, compilation_info)
+    }
+
+    fn warnings(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Vec<String> {
+        self.target.warnings(ast, compilation_info)
+    }
+
+    fn support_files(&self, compilation_info: &CompilationInfo) -> Vec<SupportFile> {
+        self.target.support_files(compilation_info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::DataDefinition;
+
+    struct TestGenerator;
+
+    impl CompilationTarget for TestGenerator {
+        fn generate_code(&self, _ast: &ASTNode, _compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+            Ok("test output".to_owned())
+        }
+    }
+
+    fn generate(target: Target) -> String {
+        target
+            .generate_code(
+                &ASTNode::DataDefinition(DataDefinition::default()),
+                &CompilationInfo::default(),
+            )
+            .expect("should be able to generate")
+    }
+
+    #[test]
+    fn test_builtin_targets_registered() {
+        let registry = TargetRegistry::default();
+        assert_eq!(registry.target("cxx").expect("should find by name").name(), "cxx");
+        assert_eq!(registry.target_for_file(Path::new("out.h")).expect("should find by extension").name(), "cxx");
+        assert_eq!(registry.target_for_file(Path::new("out.ts")).expect("should find by extension").name(), "ts-mobx");
+        assert_eq!(registry.target("c").expect("should find by name").extension(), None);
+        assert!(matches!(registry.target("lua"), Err(CompilationError::UnknownTarget(_))));
+    }
+
+    #[test]
+    fn test_registered_target_overrides_builtin() {
+        let mut registry = TargetRegistry::default();
+        registry.register(&["lua"], &["lua", "h"], Box::new(TestGenerator));
+
+        assert_eq!(generate(registry.target("lua").expect("should find by name")), "test output");
+        assert_eq!(generate(registry.target_for_file(Path::new("out.h")).expect("should find by extension")), "test output");
+    }
+}
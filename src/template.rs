@@ -0,0 +1,345 @@
+/// Template driven compilation targets
+///
+/// A template target is a MiniJinja (Jinja2/Tera-like) template file, selected with
+/// `template:<path>` as the target name. The template is rendered with the following context:
+/// - `source_file_name`, `preamble_comments`, the target `options` and `external_types`, from the
+///   CompilationInfo. Each external type has the `name` of the type and the `module` declaring it
+/// - `declarations`, every struct and enum of the resolved `Schema` in declaration order, with
+///   inline declarations hoisted out of line. Each has a `kind` ("struct" or "enum"), a `name` and
+///   `members`. Struct members have a `name`, their mapped `type`, the original `data_type`, and
+///   `optional` and `array` flags. Enum members have a `name`
+/// - `structs` and `enums`, the declarations of each kind
+///
+/// The template may start with a TOML front matter block delimited by `+++` lines. Its `[types]`
+/// table maps the data definition types to the names used in the output, e.g.
+/// ```toml
+/// +++
+/// [types]
+/// u32 = "integer"
+/// string = "string"
+/// option = "{}|nil"
+/// array = "{{}}"
+/// +++
+/// ```
+/// `option`, `array` and `user_defined` mappings may contain `{}`, which is replaced with the
+/// mapped inner type or the type name. `{{` and `}}` are written as literal braces. Unmapped
+/// types keep the name used in the data definition.
+use crate::compilation_target::{
+    CompilationError, CompilationInfo, CompilationTarget, ExternalType, TargetOptions,
+};
+use crate::parser::{ASTNode, DataType};
+use crate::schema::{Declaration, Schema};
+use minijinja::Environment;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Prefix of target names which refer to a template file
+pub const TEMPLATE_TARGET_PREFIX: &str = "template:";
+
+const FRONT_MATTER_DELIMITER: &str = "+++";
+
+pub struct TemplateTarget {
+    template: String,
+    type_mappings: TypeMappings,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct FrontMatter {
+    #[serde(default)]
+    types: TypeMappings,
+}
+
+/// Per target type names, keyed by the type keyword used in the data definition
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct TypeMappings {
+    u8: Option<String>,
+    i8: Option<String>,
+    u16: Option<String>,
+    i16: Option<String>,
+    u32: Option<String>,
+    i32: Option<String>,
+    u64: Option<String>,
+    i64: Option<String>,
+    f32: Option<String>,
+    f64: Option<String>,
+    char: Option<String>,
+    string: Option<String>,
+    bool: Option<String>,
+    option: Option<String>,
+    array: Option<String>,
+    user_defined: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TemplateContext<'a> {
+    source_file_name: &'a str,
+    preamble_comments: &'a [String],
+    options: &'a TargetOptions,
+    external_types: &'a [ExternalType],
+    declarations: Vec<TemplateDeclaration<'a>>,
+    structs: Vec<TemplateDeclaration<'a>>,
+    enums: Vec<TemplateDeclaration<'a>>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum TemplateDeclaration<'a> {
+    Struct {
+        name: &'a str,
+        members: Vec<TemplateStructMember<'a>>,
+    },
+    Enum {
+        name: &'a str,
+        members: Vec<TemplateEnumMember<'a>>,
+    },
+}
+
+#[derive(Serialize, Clone)]
+struct TemplateStructMember<'a> {
+    name: &'a str,
+    #[serde(rename = "type")]
+    type_name: String,
+    data_type: &'a DataType,
+    optional: bool,
+    array: bool,
+}
+
+#[derive(Serialize, Clone)]
+struct TemplateEnumMember<'a> {
+    name: &'a str,
+}
+
+impl TemplateTarget {
+    /// Loads a template target from a file
+    pub fn from_file(path: &Path) -> Result<Self, CompilationError> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|error| CompilationError::Template(format!("{}: {}", path.display(), error)))?;
+        Self::from_source(&source)
+    }
+
+    /// Creates a template target from the contents of a template file, including any front matter
+    pub fn from_source(source: &str) -> Result<Self, CompilationError> {
+        let (front_matter, template) = split_front_matter(source)?;
+        let front_matter: FrontMatter = toml::from_str(front_matter)
+            .map_err(|error| CompilationError::Template(error.to_string()))?;
+
+        // Checked up front so a broken template is reported before anything is generated
+        environment()
+            .template_from_str(template)
+            .map_err(|error| CompilationError::Template(error.to_string()))?;
+
+        Ok(Self {
+            template: template.to_owned(),
+            type_mappings: front_matter.types,
+        })
+    }
+}
+
+impl CompilationTarget for TemplateTarget {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        let schema = Schema::from_ast(ast)?;
+        let declarations: Vec<TemplateDeclaration> = schema
+            .declarations
+            .iter()
+            .map(|declaration| self.template_declaration(declaration))
+            .collect();
+        let context = TemplateContext {
+            source_file_name: &compilation_info.source_file_name,
+            preamble_comments: &compilation_info.preamble_comments,
+            options: &compilation_info.options,
+            external_types: &compilation_info.external_types,
+            structs: declarations
+                .iter()
+                .filter(|declaration| matches!(declaration, TemplateDeclaration::Struct { .. }))
+                .cloned()
+                .collect(),
+            enums: declarations
+                .iter()
+                .filter(|declaration| matches!(declaration, TemplateDeclaration::Enum { .. }))
+                .cloned()
+                .collect(),
+            declarations,
+        };
+
+        environment()
+            .render_str(&self.template, context)
+            .map_err(|error| CompilationError::Template(error.to_string()))
+    }
+}
+
+impl TemplateTarget {
+    fn template_declaration<'a>(&self, declaration: &'a Declaration) -> TemplateDeclaration<'a> {
+        match declaration {
+            Declaration::Struct(struct_definition) => TemplateDeclaration::Struct {
+                name: &struct_definition.name,
+                members: struct_definition
+                    .members
+                    .iter()
+                    .map(|member| TemplateStructMember {
+                        name: &member.name,
+                        type_name: self.type_mappings.map_type(&member.data_type),
+                        data_type: &member.data_type,
+                        optional: matches!(member.data_type, DataType::Option(_)),
+                        array: matches!(member.data_type, DataType::Array(_)),
+                    })
+                    .collect(),
+            },
+            Declaration::Enum(enum_definition) => TemplateDeclaration::Enum {
+                name: &enum_definition.name,
+                members: enum_definition
+                    .members
+                    .iter()
+                    .map(|member| TemplateEnumMember { name: member })
+                    .collect(),
+            },
+        }
+    }
+}
+
+impl TypeMappings {
+    /// Maps a data type to its name in the output. Option and array types are mapped recursively
+    fn map_type(&self, data_type: &DataType) -> String {
+        let mapped = |mapping: &Option<String>, default: &str| {
+            mapping.clone().unwrap_or_else(|| default.to_owned())
+        };
+        match data_type {
+            DataType::U8 => mapped(&self.u8, "u8"),
+            DataType::I8 => mapped(&self.i8, "i8"),
+            DataType::U16 => mapped(&self.u16, "u16"),
+            DataType::I16 => mapped(&self.i16, "i16"),
+            DataType::U32 => mapped(&self.u32, "u32"),
+            DataType::I32 => mapped(&self.i32, "i32"),
+            DataType::U64 => mapped(&self.u64, "u64"),
+            DataType::I64 => mapped(&self.i64, "i64"),
+            DataType::F32 => mapped(&self.f32, "f32"),
+            DataType::F64 => mapped(&self.f64, "f64"),
+            DataType::Char => mapped(&self.char, "char"),
+            DataType::String => mapped(&self.string, "string"),
+            DataType::Bool => mapped(&self.bool, "bool"),
+            DataType::Option(inner_type) => {
+                substitute(self.option.as_deref().unwrap_or("option({})"), &self.map_type(inner_type))
+            }
+            DataType::Array(inner_type) => {
+                substitute(self.array.as_deref().unwrap_or("array({})"), &self.map_type(inner_type))
+            }
+            DataType::UserDefined(name) => {
+                substitute(self.user_defined.as_deref().unwrap_or("{}"), name)
+            }
+        }
+    }
+}
+
+/// Replaces `{}` in a type mapping with the supplied value, and unescapes `{{` and `}}`
+fn substitute(pattern: &str, value: &str) -> String {
+    let mut result = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(char) = chars.next() {
+        match (char, chars.peek()) {
+            ('{', Some('}')) => {
+                chars.next();
+                result.push_str(value);
+            }
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                result.push(char);
+            }
+            _ => result.push(char),
+        }
+    }
+    result
+}
+
+/// Splits a template file into its TOML front matter, which is empty if there is none, and the
+/// template itself
+fn split_front_matter(source: &str) -> Result<(&str, &str), CompilationError> {
+    let Some(rest) = source.strip_prefix(FRONT_MATTER_DELIMITER) else {
+        return Ok(("", source));
+    };
+    let end = rest
+        .find(&format!("\n{}", FRONT_MATTER_DELIMITER))
+        .ok_or_else(|| CompilationError::Template("unterminated front matter".to_owned()))?;
+    let template = &rest[end + 1 + FRONT_MATTER_DELIMITER.len()..];
+    let template = template
+        .strip_prefix("\r\n")
+        .or_else(|| template.strip_prefix('\n'))
+        .unwrap_or(template);
+    Ok((&rest[..end], template))
+}
+
+/// The environment templates are rendered in. Block tags do not leave behind the whitespace
+/// around them, which makes writing templates for whitespace sensitive languages bearable
+fn environment() -> Environment<'static> {
+    let mut environment = Environment::new();
+    environment.set_trim_blocks(true);
+    environment.set_lstrip_blocks(true);
+    environment.set_keep_trailing_newline(true);
+    environment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+
+    // Mappings nest, user defined types are mapped, and unmapped types keep their names
+    const TEXT: &str = "struct node { id: u64, labels: array(option(string)), parent: option(node), kind: enum kind { leaf, branch, } }";
+
+    const TEMPLATE: &str = r#"+++
+[types]
+string = "string"
+option = "{}|nil"
+array = "{{ {} }}"
+user_defined = "tree.{}"
++++
+-- {{ source_file_name }}
+local {{ options.module }} = {}
+{% for declaration in declarations %}
+{% if declaration.kind == "struct" %}
+-- @class {{ declaration.name }}
+{% for member in declaration.members %}
+-- @field {{ member.name }}{% if member.optional %}?{% endif %} {{ member.type }}{% if member.array %} (array){% endif %}
+
+{% endfor %}
+{% else %}
+{{ options.module }}.{{ declaration.name }} = { {% for member in declaration.members %}"{{ member.name }}", {% endfor %}}
+{% endif %}
+{% endfor %}
+"#;
+
+    const GENERATED_CODE: &str = r#"-- input.fruko
+local tree = {}
+tree.kind = { "leaf", "branch", }
+-- @class node
+-- @field id u64
+-- @field labels { string|nil } (array)
+-- @field parent? tree.node|nil
+-- @field kind tree.kind
+"#;
+
+    #[test]
+    fn test_template_generation() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+        let mut compilation_info = CompilationInfo {
+            source_file_name: "input.fruko".to_owned(),
+            ..CompilationInfo::default()
+        };
+        compilation_info.options.set("module", "tree");
+
+        let target = TemplateTarget::from_source(TEMPLATE).expect("should load template");
+        assert_eq!(
+            target.generate_code(&ast, &compilation_info).expect("should generate code"),
+            GENERATED_CODE
+        );
+    }
+
+    #[test]
+    fn test_unknown_type_mapping() {
+        assert!(matches!(
+            TemplateTarget::from_source("+++\n[types]\nu128 = \"integer\"\n+++\n"),
+            Err(CompilationError::Template(_))
+        ));
+    }
+}
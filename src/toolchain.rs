@@ -0,0 +1,87 @@
+/// Helpers for the tests which compile or run the generated code with the toolchain of its
+/// language. A test is skipped, with a message, when a tool it needs is not installed, unless
+/// the `FRUKO_REQUIRE_TOOLCHAINS` environment variable is set, in which case it fails instead
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The environment variable which turns a missing tool into a test failure
+const REQUIRE_VARIABLE: &str = "FRUKO_REQUIRE_TOOLCHAINS";
+
+/// Checks that a test can go ahead without a requirement which is not met
+/// # Returns
+/// Whether the test should go ahead
+fn require(test: &str, requirement: &str, met: bool) -> bool {
+    if !met {
+        assert!(std::env::var_os(REQUIRE_VARIABLE).is_none(), "{} requires {}", test, requirement);
+        eprintln!("skipping {}: {} is not available", test, requirement);
+    }
+    met
+}
+
+/// Checks that programs are installed, by running them with `--version`
+/// # Returns
+/// Whether the test should go ahead
+pub fn require_programs(test: &str, programs: &[&str]) -> bool {
+    programs.iter().all(|program| {
+        let installed = Command::new(program).arg("--version").output().is_ok_and(|output| output.status.success());
+        require(test, program, installed)
+    })
+}
+
+/// Checks that `g++` is installed and finds a header, which may be on `CPLUS_INCLUDE_PATH`
+/// # Returns
+/// Whether the test should go ahead
+pub fn require_cxx_header(test: &str, header: &str) -> bool {
+    if !require_programs(test, &["g++"]) {
+        return false;
+    }
+    let directory = temporary_directory(&format!("{}-header", test));
+    std::fs::write(directory.join("probe.cpp"), format!("#include <{}>\n", header)).expect("should write probe");
+    let found = Command::new("g++")
+        .args(["-std=c++17", "-fsyntax-only", "probe.cpp"])
+        .current_dir(&directory)
+        .output()
+        .is_ok_and(|output| output.status.success());
+    std::fs::remove_dir_all(directory).expect("should remove directory");
+    require(test, header, found)
+}
+
+/// Checks that `python3` is installed and imports a module
+/// # Returns
+/// Whether the test should go ahead
+pub fn require_python_module(test: &str, module: &str) -> bool {
+    if !require_programs(test, &["python3"]) {
+        return false;
+    }
+    let found = Command::new("python3")
+        .args(["-c", &format!("import {}", module)])
+        .output()
+        .is_ok_and(|output| output.status.success());
+    require(test, module, found)
+}
+
+/// Runs a command, failing the test if it does not succeed
+/// # Returns
+/// The standard output
+pub fn run(program: &str, arguments: &[&str], directory: &Path) -> String {
+    let output = Command::new(program)
+        .args(arguments)
+        .current_dir(directory)
+        .output()
+        .unwrap_or_else(|error| panic!("could not run {}: {}", program, error));
+    assert!(
+        output.status.success(),
+        "{} failed: {}{}",
+        program,
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).expect("output should be UTF-8")
+}
+
+/// Creates a directory for the files of a test, unique to the process
+pub fn temporary_directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("fruko-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&directory).expect("should create directory");
+    directory
+}
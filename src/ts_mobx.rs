@@ -6,19 +6,33 @@
 /// The root level data definitions come with some slight extras, exporting both the regular
 /// type using the MobX types, as well as a snapshot type, derived from the regular type.
 ///
+/// The module MobX is imported from can be changed with the `import_path` option.
+///
 /// NOTE: currently, using a non-inline enum definition may not work as expected.
 use crate::compilation_target::{CompilationError, CompilationInfo, CompilationTarget};
 use crate::parser::{ASTNode, DataType};
 use std::borrow::Borrow;
 
+const DEFAULT_IMPORT_PATH: &str = "mobx-state-tree";
+
 pub struct TSMobXGenerator;
 
 impl CompilationTarget for TSMobXGenerator {
     fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
-        Ok(format!("{}\nimport 'mobx-state-tree';\n{}", generate_preamble(compilation_info), generate_code(ast)?))
+        let import_path = compilation_info.options.get("import_path").unwrap_or(DEFAULT_IMPORT_PATH);
+        Ok(format!("{}\nimport '{}';\n{}{}", generate_preamble(compilation_info), import_path, generate_external_imports(compilation_info), generate_code(ast)?))
     }
 }
 
+/// Imports the types declared in other files of the batch from their generated modules
+fn generate_external_imports(compilation_info: &CompilationInfo) -> String {
+    compilation_info
+        .external_modules()
+        .iter()
+        .map(|(module, names)| format!("import {{ {} }} from './{}';\n", names.join(", "), module))
+        .collect()
+}
+
 fn generate_preamble(compilation_info: &CompilationInfo) -> String {
     compilation_info.preamble_comments.iter()
         .map(|preamble| format!("// {}", preamble))
@@ -119,16 +133,22 @@ mod tests {
                     ASTNode::StructMemberDeclaration(StructMemberDeclaration {
                         name: "member1".to_owned(),
                         data_type: Box::new(ASTNode::TypeLiteral(DataType::U32)),
+                        field_number: None,
+                        description: None,
                     }),
                     ASTNode::StructMemberDeclaration(StructMemberDeclaration {
                         name: "member2".to_owned(),
                         data_type: Box::new(ASTNode::TypeLiteral(DataType::F64)),
+                        field_number: None,
+                        description: None,
                     }),
                     ASTNode::StructMemberDeclaration(StructMemberDeclaration {
                         name: "member3".to_owned(),
                         data_type: Box::new(ASTNode::TypeLiteral(DataType::Option(Box::new(
                             DataType::String,
                         )))),
+                        field_number: None,
+                        description: None,
                     }),
                     ASTNode::StructMemberDeclaration(StructMemberDeclaration {
                         name: "member4".to_owned(),
@@ -138,11 +158,17 @@ mod tests {
                                 StructMemberDeclaration {
                                     name: "member1".to_owned(),
                                     data_type: Box::new(ASTNode::TypeLiteral(DataType::Bool)),
+                                    field_number: None,
+                                    description: None,
                                 },
                             )],
+                            description: None,
                         })),
+                        field_number: None,
+                        description: None,
                     }),
                 ],
+                description: None,
             })],
         })
     }
@@ -156,4 +182,23 @@ mod tests {
             GENERATED_CODE.to_owned()
         )
     }
+
+    #[test]
+    fn test_generate_external_imports() {
+        use crate::compilation_target::ExternalType;
+        use crate::schema::DeclarationKind;
+        let compilation_info = CompilationInfo {
+            external_types: vec![
+                ExternalType { name: "order".to_owned(), module: "orders".to_owned(), kind: DeclarationKind::Struct },
+                ExternalType { name: "role".to_owned(), module: "user".to_owned(), kind: DeclarationKind::Enum },
+                ExternalType { name: "refund".to_owned(), module: "orders".to_owned(), kind: DeclarationKind::Struct },
+            ],
+            ..CompilationInfo::default()
+        };
+
+        assert_eq!(
+            generate_external_imports(&compilation_info),
+            "import { order, refund } from './orders';\nimport { role } from './user';\n"
+        );
+    }
 }
@@ -0,0 +1,663 @@
+/// Generation for plain Typescript interfaces
+///
+/// Structs become exported interfaces, with `Option` members marked optional with `?`. Inline
+/// declarations are hoisted out of line into their own exported types. Type names which are
+/// reserved words get a trailing `_`.
+///
+/// Supported options:
+/// - `enum_style`: `union` (the default) generates enums as a union of string literal types,
+///   `enum` generates string valued Typescript enums
+/// - `int64`: `bigint` (the default) or `number`, the type used for 64 bit integers. A `number`
+///   can only represent integers up to 2^53 exactly
+/// - `wire`: `false` (the default) or `true`, generating `encode` and `decode` functions for every
+///   type, converting to and from the compact binary wire format described in `crate::wire` with
+///   a `DataView`. They import the classes reading and writing the format from a `wire.ts` module,
+///   written once beside the outputs, so that a `WireError` thrown by any module of a batch is an
+///   instance of the same class. With `int64` set to `number`, 64 bit integers which are not safe
+///   integers throw a `WireError` rather than losing precision
+use crate::compilation_target::{CompilationError, CompilationInfo, CompilationTarget, SupportFile};
+use crate::naming::pascal_case;
+use crate::parser::{ASTNode, DataType};
+use crate::schema::{Declaration, EnumDefinition, Schema, StructDefinition};
+
+/// Reserved words, including those of strict mode, and the predefined types, which cannot name a
+/// type. Members may be named by any of them
+const RESERVED_WORDS: [&str; 56] = [
+    "any", "await", "bigint", "boolean", "break", "case", "catch", "class", "const", "continue", "debugger", "default",
+    "delete", "do", "else", "enum", "export", "extends", "false", "finally", "for", "function", "if", "implements",
+    "import", "in", "instanceof", "interface", "let", "never", "new", "null", "number", "object", "package",
+    "private", "protected", "public", "return", "static", "string", "super", "switch", "symbol", "this", "throw",
+    "true", "try", "typeof", "undefined", "unknown", "var", "void", "while", "with", "yield",
+];
+
+pub struct TypeScriptGenerator;
+
+impl CompilationTarget for TypeScriptGenerator {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        let options = TypeScriptOptions {
+            enum_style: compilation_info.options.get_choice("enum_style", &["union", "enum"], "union")?,
+            int64_type: compilation_info.options.get_choice("int64", &["bigint", "number"], "bigint")?,
+        };
+        let wire = compilation_info.options.get_choice("wire", &["false", "true"], "false")? == "true";
+        let schema = Schema::from_ast(ast)?;
+
+        let mut code = format!(
+            "{}\n{}{}",
+            generate_preamble(compilation_info),
+            generate_external_imports(compilation_info, wire),
+            generate_declarations(&schema, &options)
+        );
+        if wire {
+            code += &generate_wire_functions(&schema, &options);
+        }
+        Ok(code)
+    }
+
+    fn support_files(&self, compilation_info: &CompilationInfo) -> Vec<SupportFile> {
+        if compilation_info.options.get("wire") == Some("true") {
+            vec![SupportFile {
+                name: format!("{}.ts", WIRE_MODULE),
+                contents: WIRE_SUPPORT.trim_start().to_owned(),
+            }]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// The name of the module holding `WIRE_SUPPORT`, written beside the modules importing it
+const WIRE_MODULE: &str = "wire";
+
+struct TypeScriptOptions<'a> {
+    enum_style: &'a str,
+    int64_type: &'a str,
+}
+
+fn generate_preamble(compilation_info: &CompilationInfo) -> String {
+    compilation_info.preamble_comments.iter()
+        .map(|preamble| format!("// {}", preamble))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Imports the types declared in other files of the batch from their generated modules, and their
+/// wire format functions along with the wire support classes if generated
+fn generate_external_imports(compilation_info: &CompilationInfo, wire: bool) -> String {
+    let mut imports = if wire {
+        format!("import {{ WireError, WireReader, WireWriter }} from './{}';\n", WIRE_MODULE)
+    } else {
+        String::new()
+    };
+    for (module, names) in compilation_info.external_modules() {
+        let types = names.iter().map(|name| escape_type_name(name)).collect::<Vec<String>>();
+        imports += &format!("import type {{ {} }} from './{}';\n", types.join(", "), module);
+        if wire {
+            let functions = names
+                .iter()
+                .flat_map(|name| [format!("read{}", pascal_case(name)), format!("write{}", pascal_case(name))])
+                .collect::<Vec<String>>();
+            imports += &format!("import {{ {} }} from './{}';\n", functions.join(", "), module);
+        }
+    }
+    imports
+}
+
+fn generate_declarations(schema: &Schema, options: &TypeScriptOptions) -> String {
+    schema
+        .declarations
+        .iter()
+        .map(|declaration| match declaration {
+            Declaration::Struct(struct_definition) => generate_interface(struct_definition, options),
+            Declaration::Enum(enum_definition) => generate_enum(enum_definition, options),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn generate_interface(struct_definition: &StructDefinition, options: &TypeScriptOptions) -> String {
+    let members = struct_definition
+        .members
+        .iter()
+        .map(|member| match &member.data_type {
+            DataType::Option(inner_type) => {
+                format!("    {}?: {};\n", member.name, generate_type_name(inner_type, options))
+            }
+            data_type => format!("    {}: {};\n", member.name, generate_type_name(data_type, options)),
+        })
+        .collect::<String>();
+    format!("export interface {} {{\n{}}}\n", escape_type_name(&struct_definition.name), members)
+}
+
+fn generate_enum(enum_definition: &EnumDefinition, options: &TypeScriptOptions) -> String {
+    if options.enum_style == "enum" {
+        let members = enum_definition
+            .members
+            .iter()
+            .map(|member| format!("    {} = '{}',\n", member, member))
+            .collect::<String>();
+        format!("export enum {} {{\n{}}}\n", escape_type_name(&enum_definition.name), members)
+    } else {
+        let members = enum_definition
+            .members
+            .iter()
+            .map(|member| format!("'{}'", member))
+            .collect::<Vec<String>>()
+            .join(" | ");
+        format!("export type {} = {};\n", escape_type_name(&enum_definition.name), members)
+    }
+}
+
+pub(crate) fn escape_type_name(name: &str) -> String {
+    if RESERVED_WORDS.contains(&name) {
+        format!("{}_", name)
+    } else {
+        name.to_owned()
+    }
+}
+
+/// Generates the type names. Options nested within another type cannot be expressed with `?`, so
+/// are generated as a union with `null`
+fn generate_type_name(data_type: &DataType, options: &TypeScriptOptions) -> String {
+    match data_type {
+        DataType::U8
+        | DataType::I8
+        | DataType::U16
+        | DataType::I16
+        | DataType::U32
+        | DataType::I32
+        | DataType::F32
+        | DataType::F64 => "number".to_owned(),
+        DataType::U64 | DataType::I64 => options.int64_type.to_owned(),
+        DataType::Char | DataType::String => "string".to_owned(),
+        DataType::Bool => "boolean".to_owned(),
+        DataType::Option(inner_type) => format!("{} | null", generate_type_name(inner_type, options)),
+        DataType::Array(inner_type) => match inner_type.as_ref() {
+            DataType::Option(_) => format!("({})[]", generate_type_name(inner_type, options)),
+            _ => format!("{}[]", generate_type_name(inner_type, options)),
+        },
+        DataType::UserDefined(name) => escape_type_name(name),
+    }
+}
+
+/// The classes writing and reading the values of the wire format, the contents of the module
+/// imported by every module with wire functions
+const WIRE_SUPPORT: &str = r#"
+export class WireError extends Error {}
+
+// The most options, arrays and structs a decoded value may have nested within each other
+const MAX_DEPTH = 128;
+
+export class WireWriter {
+    bytes = new Uint8Array(64);
+    view = new DataView(this.bytes.buffer);
+    length = 0;
+
+    reserve(size: number): void {
+        if (this.length + size <= this.bytes.length) {
+            return;
+        }
+        const bytes = new Uint8Array(Math.max(this.bytes.length * 2, this.length + size));
+        bytes.set(this.bytes.subarray(0, this.length));
+        this.bytes = bytes;
+        this.view = new DataView(bytes.buffer);
+    }
+
+    writeByte(value: number): void {
+        this.reserve(1);
+        this.bytes[this.length++] = value;
+    }
+
+    writeVarint(value: bigint): void {
+        if (value < 0n || value >= 1n << 64n) {
+            throw new WireError(`${value} is out of range of a varint`);
+        }
+        while (value >= 0x80n) {
+            this.writeByte(Number(value & 0x7fn) | 0x80);
+            value >>= 7n;
+        }
+        this.writeByte(Number(value));
+    }
+
+    writeZigzag(value: bigint): void {
+        if (value < -(1n << 63n) || value >= 1n << 63n) {
+            throw new WireError(`${value} is out of range of a 64 bit integer`);
+        }
+        this.writeVarint(BigInt.asUintN(64, (value << 1n) ^ (value >> 63n)));
+    }
+
+    // Integers held in a number are only exact up to 2^53, which limits 64 bit integers
+    checkSafeInteger(value: number): void {
+        if (!Number.isSafeInteger(value)) {
+            throw new WireError(`${value} is not a safe integer`);
+        }
+    }
+
+    writeUnsigned(value: number, bits: number): void {
+        this.checkSafeInteger(value);
+        if (value < 0 || value >= 2 ** bits) {
+            throw new WireError(`${value} is out of range of a ${bits} bit unsigned integer`);
+        }
+        this.writeVarint(BigInt(value));
+    }
+
+    writeSigned(value: number, bits: number): void {
+        this.checkSafeInteger(value);
+        if (value < -(2 ** (bits - 1)) || value >= 2 ** (bits - 1)) {
+            throw new WireError(`${value} is out of range of a ${bits} bit integer`);
+        }
+        this.writeZigzag(BigInt(value));
+    }
+
+    writeF32(value: number): void {
+        this.reserve(4);
+        this.view.setFloat32(this.length, value, true);
+        this.length += 4;
+    }
+
+    writeF64(value: number): void {
+        this.reserve(8);
+        this.view.setFloat64(this.length, value, true);
+        this.length += 8;
+    }
+
+    writeBool(value: boolean): void {
+        this.writeByte(value ? 1 : 0);
+    }
+
+    writeChar(value: string): void {
+        const scalar = value.codePointAt(0);
+        if (scalar === undefined || String.fromCodePoint(scalar) !== value || (scalar >= 0xd800 && scalar <= 0xdfff)) {
+            throw new WireError(`'${value}' is not a single character`);
+        }
+        this.writeVarint(BigInt(scalar));
+    }
+
+    writeString(value: string): void {
+        const bytes = new TextEncoder().encode(value);
+        this.writeVarint(BigInt(bytes.length));
+        this.reserve(bytes.length);
+        this.bytes.set(bytes, this.length);
+        this.length += bytes.length;
+    }
+
+    writeOption<T>(value: T | null | undefined, write: (value: T) => void): void {
+        if (value === null || value === undefined) {
+            this.writeByte(0);
+        } else {
+            this.writeByte(1);
+            write(value);
+        }
+    }
+
+    writeArray<T>(values: T[], write: (value: T) => void): void {
+        this.writeVarint(BigInt(values.length));
+        for (const value of values) {
+            write(value);
+        }
+    }
+
+    finish(): Uint8Array {
+        return this.bytes.slice(0, this.length);
+    }
+}
+
+export class WireReader {
+    bytes: Uint8Array;
+    view: DataView;
+    offset = 0;
+    depth = 0;
+
+    constructor(bytes: Uint8Array) {
+        this.bytes = bytes;
+        this.view = new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+    }
+
+    need(size: number): void {
+        if (size > this.bytes.length - this.offset) {
+            throw new WireError(`the payload ends unexpectedly at offset ${this.bytes.length}`);
+        }
+    }
+
+    readByte(): number {
+        this.need(1);
+        return this.bytes[this.offset++];
+    }
+
+    readVarint(): bigint {
+        const start = this.offset;
+        let value = 0n;
+        for (let shift = 0n; ; shift += 7n) {
+            const byte = this.readByte();
+            // The tenth byte holds the highest bit only
+            if (shift === 63n && byte > 1) {
+                throw new WireError(`the varint at offset ${start} is longer than 64 bits`);
+            }
+            value |= BigInt(byte & 0x7f) << shift;
+            if (byte < 0x80) {
+                return value;
+            }
+        }
+    }
+
+    readZigzag(): bigint {
+        const zigzag = this.readVarint();
+        return (zigzag >> 1n) ^ -(zigzag & 1n);
+    }
+
+    readUnsigned(bits: number): number {
+        const start = this.offset;
+        const value = this.readVarint();
+        if (value >= 1n << BigInt(bits)) {
+            throw new WireError(`${value} at offset ${start} is out of range`);
+        }
+        return this.safeInteger(value, start);
+    }
+
+    readSigned(bits: number): number {
+        const start = this.offset;
+        const value = this.readZigzag();
+        const limit = 1n << BigInt(bits - 1);
+        if (value < -limit || value >= limit) {
+            throw new WireError(`${value} at offset ${start} is out of range`);
+        }
+        return this.safeInteger(value, start);
+    }
+
+    safeInteger(value: bigint, start: number): number {
+        if (value > BigInt(Number.MAX_SAFE_INTEGER) || value < BigInt(Number.MIN_SAFE_INTEGER)) {
+            throw new WireError(`${value} at offset ${start} is not a safe integer`);
+        }
+        return Number(value);
+    }
+
+    readF32(): number {
+        this.need(4);
+        const value = this.view.getFloat32(this.offset, true);
+        this.offset += 4;
+        return value;
+    }
+
+    readF64(): number {
+        this.need(8);
+        const value = this.view.getFloat64(this.offset, true);
+        this.offset += 8;
+        return value;
+    }
+
+    readFlag(kind: string): boolean {
+        const start = this.offset;
+        const byte = this.readByte();
+        if (byte > 1) {
+            throw new WireError(`${byte} at offset ${start} is not a valid ${kind} byte`);
+        }
+        return byte === 1;
+    }
+
+    readBool(): boolean {
+        return this.readFlag('bool');
+    }
+
+    readChar(): string {
+        const start = this.offset;
+        const scalar = this.readUnsigned(32);
+        if (scalar > 0x10ffff || (scalar >= 0xd800 && scalar <= 0xdfff)) {
+            throw new WireError(`${scalar} at offset ${start} is not a Unicode scalar value`);
+        }
+        return String.fromCodePoint(scalar);
+    }
+
+    readString(): string {
+        const length = this.readUnsigned(32);
+        this.need(length);
+        const start = this.offset;
+        this.offset += length;
+        try {
+            return new TextDecoder('utf-8', { fatal: true, ignoreBOM: true }).decode(this.bytes.subarray(start, this.offset));
+        } catch {
+            throw new WireError(`the string at offset ${start} is not valid UTF-8`);
+        }
+    }
+
+    // Reads an option, array or struct, which is nested one level deeper than its parent
+    nested<T>(read: () => T): T {
+        if (this.depth === MAX_DEPTH) {
+            throw new WireError(`the value at offset ${this.offset} is nested more than ${MAX_DEPTH} levels deep`);
+        }
+        this.depth++;
+        try {
+            return read();
+        } finally {
+            this.depth--;
+        }
+    }
+
+    readOption<T>(read: () => T): T | null {
+        return this.nested(() => (this.readFlag('presence') ? read() : null));
+    }
+
+    readArray<T>(read: () => T): T[] {
+        return this.nested(() => {
+            const length = this.readUnsigned(32);
+            return Array.from({ length }, () => read());
+        });
+    }
+
+    finish(): void {
+        if (this.offset !== this.bytes.length) {
+            throw new WireError(`the payload has unexpected bytes from offset ${this.offset}`);
+        }
+    }
+}
+"#;
+
+/// Generates the wire format functions of every declaration: `encodeX` and `decodeX` converting
+/// whole payloads, and `writeX` and `readX` converting values within a payload
+fn generate_wire_functions(schema: &Schema, options: &TypeScriptOptions) -> String {
+    schema
+        .declarations
+        .iter()
+        .map(|declaration| {
+            let name = escape_type_name(declaration.name());
+            let function_name = pascal_case(declaration.name());
+            let functions = match declaration {
+                Declaration::Struct(struct_definition) => generate_struct_wire_functions(struct_definition, options),
+                Declaration::Enum(enum_definition) => generate_enum_wire_functions(enum_definition, options),
+            };
+            format!(
+                "\n{functions}\n\
+                 export function encode{function_name}(value: {name}): Uint8Array {{\n    const writer = new WireWriter();\n    \
+                 write{function_name}(writer, value);\n    return writer.finish();\n}}\n\n\
+                 export function decode{function_name}(bytes: Uint8Array): {name} {{\n    const reader = new WireReader(bytes);\n    \
+                 const value = read{function_name}(reader);\n    reader.finish();\n    return value;\n}}\n"
+            )
+        })
+        .collect()
+}
+
+fn generate_struct_wire_functions(struct_definition: &StructDefinition, options: &TypeScriptOptions) -> String {
+    let name = escape_type_name(&struct_definition.name);
+    let function_name = pascal_case(&struct_definition.name);
+    let writes = struct_definition
+        .members
+        .iter()
+        .map(|member| format!("    {};\n", generate_write(&member.data_type, &format!("value.{}", member.name), 1, options)))
+        .collect::<String>();
+    let reads = struct_definition
+        .members
+        .iter()
+        .map(|member| match member.data_type {
+            // Optional members are undefined, rather than null, when absent
+            DataType::Option(_) => format!("        {}: {} ?? undefined,\n", member.name, generate_read(&member.data_type, options)),
+            _ => format!("        {}: {},\n", member.name, generate_read(&member.data_type, options)),
+        })
+        .collect::<String>();
+
+    format!(
+        "export function write{function_name}(writer: WireWriter, value: {name}): void {{\n{writes}}}\n\n\
+         export function read{function_name}(reader: WireReader): {name} {{\n    return reader.nested(() => ({{\n{reads}    }}));\n}}\n"
+    )
+}
+
+fn generate_enum_wire_functions(enum_definition: &EnumDefinition, options: &TypeScriptOptions) -> String {
+    let name = escape_type_name(&enum_definition.name);
+    let function_name = pascal_case(&enum_definition.name);
+    let original_name = &enum_definition.name;
+    let member_value = |member: &str| match options.enum_style {
+        "enum" => format!("{}.{}", name, member),
+        _ => format!("'{}'", member),
+    };
+    let writes = enum_definition
+        .members
+        .iter()
+        .enumerate()
+        .map(|(index, member)| format!("        case {}: writer.writeVarint({}n); break;\n", member_value(member), index))
+        .collect::<String>();
+    let reads = enum_definition
+        .members
+        .iter()
+        .enumerate()
+        .map(|(index, member)| format!("        case {}n: return {};\n", index, member_value(member)))
+        .collect::<String>();
+
+    format!(
+        "export function write{function_name}(writer: WireWriter, value: {name}): void {{\n    switch (value) {{\n{writes}        \
+         default: throw new WireError(`'${{value}}' is not a member of '{original_name}'`);\n    }}\n}}\n\n\
+         export function read{function_name}(reader: WireReader): {name} {{\n    const start = reader.offset;\n    \
+         const index = reader.readVarint();\n    switch (index) {{\n{reads}        \
+         default: throw new WireError(`${{index}} at offset ${{start}} is not a member index of '{original_name}'`);\n    }}\n}}\n"
+    )
+}
+
+/// Generates the statement writing the value of `expression`. `depth` numbers the parameters of
+/// nested callbacks, which would otherwise shadow each other
+fn generate_write(data_type: &DataType, expression: &str, depth: usize, options: &TypeScriptOptions) -> String {
+    let bigint = options.int64_type == "bigint";
+    match data_type {
+        DataType::U8 => format!("writer.writeUnsigned({}, 8)", expression),
+        DataType::U16 => format!("writer.writeUnsigned({}, 16)", expression),
+        DataType::U32 => format!("writer.writeUnsigned({}, 32)", expression),
+        DataType::I8 => format!("writer.writeSigned({}, 8)", expression),
+        DataType::I16 => format!("writer.writeSigned({}, 16)", expression),
+        DataType::I32 => format!("writer.writeSigned({}, 32)", expression),
+        DataType::U64 if bigint => format!("writer.writeVarint({})", expression),
+        DataType::U64 => format!("writer.writeUnsigned({}, 64)", expression),
+        DataType::I64 if bigint => format!("writer.writeZigzag({})", expression),
+        DataType::I64 => format!("writer.writeSigned({}, 64)", expression),
+        DataType::F32 => format!("writer.writeF32({})", expression),
+        DataType::F64 => format!("writer.writeF64({})", expression),
+        DataType::Char => format!("writer.writeChar({})", expression),
+        DataType::String => format!("writer.writeString({})", expression),
+        DataType::Bool => format!("writer.writeBool({})", expression),
+        DataType::Option(inner_type) => {
+            let parameter = format!("value{}", depth);
+            format!(
+                "writer.writeOption({}, ({}) => {})",
+                expression,
+                parameter,
+                generate_write(inner_type, &parameter, depth + 1, options)
+            )
+        }
+        DataType::Array(inner_type) => {
+            let parameter = format!("value{}", depth);
+            format!(
+                "writer.writeArray({}, ({}) => {})",
+                expression,
+                parameter,
+                generate_write(inner_type, &parameter, depth + 1, options)
+            )
+        }
+        DataType::UserDefined(name) => format!("write{}(writer, {})", pascal_case(name), expression),
+    }
+}
+
+/// Generates the expression reading a value
+fn generate_read(data_type: &DataType, options: &TypeScriptOptions) -> String {
+    let bigint = options.int64_type == "bigint";
+    match data_type {
+        DataType::U8 => "reader.readUnsigned(8)".to_owned(),
+        DataType::U16 => "reader.readUnsigned(16)".to_owned(),
+        DataType::U32 => "reader.readUnsigned(32)".to_owned(),
+        DataType::I8 => "reader.readSigned(8)".to_owned(),
+        DataType::I16 => "reader.readSigned(16)".to_owned(),
+        DataType::I32 => "reader.readSigned(32)".to_owned(),
+        DataType::U64 if bigint => "reader.readVarint()".to_owned(),
+        DataType::U64 => "reader.readUnsigned(64)".to_owned(),
+        DataType::I64 if bigint => "reader.readZigzag()".to_owned(),
+        DataType::I64 => "reader.readSigned(64)".to_owned(),
+        DataType::F32 => "reader.readF32()".to_owned(),
+        DataType::F64 => "reader.readF64()".to_owned(),
+        DataType::Char => "reader.readChar()".to_owned(),
+        DataType::String => "reader.readString()".to_owned(),
+        DataType::Bool => "reader.readBool()".to_owned(),
+        DataType::Option(inner_type) => format!("reader.readOption(() => {})", generate_read(inner_type, options)),
+        DataType::Array(inner_type) => format!("reader.readArray(() => {})", generate_read(inner_type, options)),
+        DataType::UserDefined(name) => format!("read{}(reader)", pascal_case(name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+
+    // Reserved words may name members but not types, and nested options cannot be `?`
+    const TEXT: &str = "struct class { id: u64, default: option(class), tags: array(option(string)), kind: enum default { in, out, } }";
+
+    const GENERATED_CODE: &str = "
+export type default_ = 'in' | 'out';
+
+export interface class_ {
+    id: bigint;
+    default?: class_;
+    tags: (string | null)[];
+    kind: default_;
+}
+";
+
+    #[test]
+    fn test_generate_typescript() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+
+        assert_eq!(
+            TypeScriptGenerator
+                .generate_code(&ast, &CompilationInfo::default())
+                .expect("should generate code"),
+            GENERATED_CODE
+        );
+    }
+
+    #[test]
+    fn test_generate_typescript_enum_style() {
+        let ast = parse_tokens(lex_tokens("enum mood { happy, sad, }".to_owned()).expect("should lex")).expect("should parse");
+        let mut compilation_info = CompilationInfo::default();
+        compilation_info.options.set("enum_style", "enum");
+
+        assert_eq!(
+            TypeScriptGenerator
+                .generate_code(&ast, &compilation_info)
+                .expect("should generate code"),
+            "\nexport enum mood {\n    happy = 'happy',\n    sad = 'sad',\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_typescript_wire_module() {
+        let ast = parse_tokens(lex_tokens("struct counter { total: u64 }".to_owned()).expect("should lex")).expect("should parse");
+        let mut compilation_info = CompilationInfo::default();
+        compilation_info.options.set("wire", "true");
+        compilation_info.options.set("int64", "number");
+
+        let generated_code = TypeScriptGenerator.generate_code(&ast, &compilation_info).expect("should generate code");
+        assert!(generated_code.starts_with("\nimport { WireError, WireReader, WireWriter } from './wire';\n"));
+        assert!(!generated_code.contains("class WireError"));
+        assert!(generated_code.contains("    writer.writeUnsigned(value.total, 64);\n"));
+        assert!(generated_code.contains("        total: reader.readUnsigned(64),\n"));
+
+        let support_files = TypeScriptGenerator.support_files(&compilation_info);
+        assert_eq!(support_files.len(), 1);
+        assert_eq!(support_files[0].name, "wire.ts");
+        assert!(support_files[0].contents.starts_with("export class WireError extends Error {}\n"));
+        assert!(TypeScriptGenerator.support_files(&CompilationInfo::default()).is_empty());
+    }
+}
@@ -0,0 +1,454 @@
+/// Validation of JSON values against a data definition, without generating any code
+///
+/// JSON represents values as the generated code does: integers and floats as numbers, chars as
+/// strings of one character, enums as the names of their members, and structs as objects. An
+/// absent option is `null`, or left out of its object. Members of an object which the struct does
+/// not declare are ignored, as generated decoders ignore them.
+///
+/// Errors are reported at the JSON pointer of the invalid value, e.g. `/team/0/name`, with `~` and
+/// `/` in member names escaped as `~0` and `~1`, and all of the errors of a value are reported
+/// rather than only the first.
+use crate::parser::{ASTNode, DataDefinition, DataType};
+use crate::schema::{Declaration, Schema};
+use std::fmt::{Display, Formatter};
+
+/// A value of a type of a data definition
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// A `u8`, `u16`, `u32` or `u64`
+    Unsigned(u64),
+    /// An `i8`, `i16`, `i32` or `i64`
+    Signed(i64),
+    /// An `f32` or `f64`
+    Float(f64),
+    Bool(bool),
+    Char(char),
+    String(String),
+    Option(Option<Box<Value>>),
+    Array(Vec<Value>),
+    /// The name of the member of an enum
+    Enum(String),
+    /// The members of a struct, in declaration order
+    Struct(Vec<(String, Value)>),
+}
+
+impl From<&Value> for serde_json::Value {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Unsigned(integer) => serde_json::Value::from(*integer),
+            Value::Signed(integer) => serde_json::Value::from(*integer),
+            Value::Float(number) => serde_json::Value::from(*number),
+            Value::Bool(boolean) => serde_json::Value::from(*boolean),
+            Value::Char(character) => serde_json::Value::from(character.to_string()),
+            Value::String(string) | Value::Enum(string) => serde_json::Value::from(string.as_str()),
+            Value::Option(Some(inner_value)) => serde_json::Value::from(inner_value.as_ref()),
+            Value::Option(None) => serde_json::Value::Null,
+            Value::Array(elements) => serde_json::Value::Array(elements.iter().map(serde_json::Value::from).collect()),
+            Value::Struct(members) => serde_json::Value::Object(
+                members
+                    .iter()
+                    .map(|(name, member_value)| (name.clone(), serde_json::Value::from(member_value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// An error in a JSON value, at the JSON pointer `path`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    /// The data definition is not valid, e.g. an enum containing struct members
+    InvalidDefinition(String),
+    /// A type which is not declared in the data definition
+    UnknownType { path: String, name: String },
+    /// A value of the wrong JSON type, where `expected` describes the expected value
+    WrongType { path: String, expected: String, found: String },
+    /// A number outside of the range of its data type
+    OutOfRange { path: String, value: String, data_type: DataType },
+    /// A char which is not a string of exactly one character
+    InvalidChar { path: String, value: String },
+    /// A string which is not a member of its enum
+    UnknownEnumMember { path: String, enum_name: String, member: String },
+    /// A member which is required, as it is not an option, but is missing
+    MissingMember { path: String },
+}
+
+impl ValidationError {
+    /// The JSON pointer of the invalid value, which is empty for the root value
+    pub fn path(&self) -> &str {
+        match self {
+            ValidationError::InvalidDefinition(_) => "",
+            ValidationError::UnknownType { path, .. }
+            | ValidationError::WrongType { path, .. }
+            | ValidationError::OutOfRange { path, .. }
+            | ValidationError::InvalidChar { path, .. }
+            | ValidationError::UnknownEnumMember { path, .. }
+            | ValidationError::MissingMember { path } => path,
+        }
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let ValidationError::InvalidDefinition(reason) = self {
+            return write!(f, "Invalid data definition: {}", reason);
+        }
+        // The root value has an empty path, which would otherwise be hard to read
+        match self.path() {
+            "" => write!(f, "(root): ")?,
+            path => write!(f, "{}: ", path)?,
+        }
+        match self {
+            ValidationError::InvalidDefinition(_) => Ok(()),
+            ValidationError::UnknownType { name, .. } => write!(f, "type '{}' is not declared", name),
+            ValidationError::WrongType { expected, found, .. } => write!(f, "expected {}, found {}", expected, found),
+            ValidationError::OutOfRange { value, data_type, .. } => write!(f, "{} is out of range of {}", value, data_type),
+            ValidationError::InvalidChar { value, .. } => write!(f, "expected a single character, found '{}'", value),
+            ValidationError::UnknownEnumMember { enum_name, member, .. } => {
+                write!(f, "'{}' is not a member of enum '{}'", member, enum_name)
+            }
+            ValidationError::MissingMember { .. } => write!(f, "missing required member"),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Checks a JSON value against `root_type`, a struct or enum declared in `definition`
+/// # Returns
+/// Every error in the value, which is empty if the value is valid
+pub fn validate(json: &serde_json::Value, definition: &DataDefinition, root_type: &str) -> Vec<ValidationError> {
+    match parse(json, definition, root_type) {
+        Ok(_) => Vec::new(),
+        Err(errors) => errors,
+    }
+}
+
+/// Converts a JSON value to a value of `root_type`, a struct or enum declared in `definition`
+/// # Returns
+/// The value, or every error in the JSON value
+pub fn parse(json: &serde_json::Value, definition: &DataDefinition, root_type: &str) -> Result<Value, Vec<ValidationError>> {
+    let schema = Schema::from_ast(&ASTNode::DataDefinition(definition.clone()))
+        .map_err(|error| vec![ValidationError::InvalidDefinition(error.to_string())])?;
+    let mut validator = Validator {
+        schema: &schema,
+        errors: Vec::new(),
+    };
+    match validator.parse(&DataType::UserDefined(root_type.to_owned()), json, "") {
+        Some(value) if validator.errors.is_empty() => Ok(value),
+        _ => Err(validator.errors),
+    }
+}
+
+/// Escapes a member name as a JSON pointer reference token, as RFC 6901 requires
+fn pointer_token(name: &str) -> String {
+    name.replace('~', "~0").replace('/', "~1")
+}
+
+struct Validator<'a> {
+    schema: &'a Schema,
+    errors: Vec<ValidationError>,
+}
+
+impl Validator<'_> {
+    /// Converts a JSON value, recording its errors
+    /// # Returns
+    /// The value, or None if it has errors
+    fn parse(&mut self, data_type: &DataType, json: &serde_json::Value, path: &str) -> Option<Value> {
+        match data_type {
+            DataType::U8 | DataType::U16 | DataType::U32 | DataType::U64 => {
+                let max = match data_type {
+                    DataType::U8 => u8::MAX.into(),
+                    DataType::U16 => u16::MAX.into(),
+                    DataType::U32 => u32::MAX.into(),
+                    _ => u64::MAX,
+                };
+                match json.as_u64() {
+                    Some(integer) if integer <= max => Some(Value::Unsigned(integer)),
+                    _ => self.integer_error(data_type, json, path),
+                }
+            }
+            DataType::I8 | DataType::I16 | DataType::I32 | DataType::I64 => {
+                let (min, max) = match data_type {
+                    DataType::I8 => (i8::MIN.into(), i8::MAX.into()),
+                    DataType::I16 => (i16::MIN.into(), i16::MAX.into()),
+                    DataType::I32 => (i32::MIN.into(), i32::MAX.into()),
+                    _ => (i64::MIN, i64::MAX),
+                };
+                match json.as_i64() {
+                    Some(integer) if integer >= min && integer <= max => Some(Value::Signed(integer)),
+                    _ => self.integer_error(data_type, json, path),
+                }
+            }
+            DataType::F32 => match json.as_f64() {
+                Some(number) if (number as f32).is_finite() => Some(Value::Float(number)),
+                Some(_) => self.out_of_range(data_type, json, path),
+                None => self.wrong_type("a number", json, path),
+            },
+            DataType::F64 => match json.as_f64() {
+                Some(number) => Some(Value::Float(number)),
+                None => self.wrong_type("a number", json, path),
+            },
+            DataType::Bool => match json.as_bool() {
+                Some(boolean) => Some(Value::Bool(boolean)),
+                None => self.wrong_type("a boolean", json, path),
+            },
+            DataType::Char => match json.as_str() {
+                Some(string) => {
+                    let mut characters = string.chars();
+                    match (characters.next(), characters.next()) {
+                        (Some(character), None) => Some(Value::Char(character)),
+                        _ => {
+                            self.errors.push(ValidationError::InvalidChar {
+                                path: path.to_owned(),
+                                value: string.to_owned(),
+                            });
+                            None
+                        }
+                    }
+                }
+                None => self.wrong_type("a string of one character", json, path),
+            },
+            DataType::String => match json.as_str() {
+                Some(string) => Some(Value::String(string.to_owned())),
+                None => self.wrong_type("a string", json, path),
+            },
+            DataType::Option(inner_type) => match json {
+                serde_json::Value::Null => Some(Value::Option(None)),
+                json => Some(Value::Option(Some(Box::new(self.parse(inner_type, json, path)?)))),
+            },
+            DataType::Array(inner_type) => match json.as_array() {
+                Some(elements) => {
+                    let values = elements
+                        .iter()
+                        .enumerate()
+                        .map(|(index, element)| self.parse(inner_type, element, &format!("{}/{}", path, index)))
+                        .collect::<Vec<Option<Value>>>();
+                    values.into_iter().collect::<Option<Vec<Value>>>().map(Value::Array)
+                }
+                None => self.wrong_type("an array", json, path),
+            },
+            DataType::UserDefined(name) => match self.schema.declaration(name) {
+                Some(Declaration::Struct(struct_definition)) => {
+                    let Some(object) = json.as_object() else {
+                        return self.wrong_type(&format!("a '{}' object", name), json, path);
+                    };
+                    let members = struct_definition
+                        .members
+                        .iter()
+                        .map(|member| {
+                            let member_path = format!("{}/{}", path, pointer_token(&member.name));
+                            let member_value = match (object.get(&member.name), &member.data_type) {
+                                (Some(member_json), data_type) => self.parse(data_type, member_json, &member_path),
+                                (None, DataType::Option(_)) => Some(Value::Option(None)),
+                                (None, _) => {
+                                    self.errors.push(ValidationError::MissingMember { path: member_path });
+                                    None
+                                }
+                            };
+                            member_value.map(|member_value| (member.name.clone(), member_value))
+                        })
+                        .collect::<Vec<Option<(String, Value)>>>();
+                    members.into_iter().collect::<Option<Vec<(String, Value)>>>().map(Value::Struct)
+                }
+                Some(Declaration::Enum(enum_definition)) => match json.as_str() {
+                    Some(member) if enum_definition.members.iter().any(|enum_member| enum_member == member) => {
+                        Some(Value::Enum(member.to_owned()))
+                    }
+                    Some(member) => {
+                        self.errors.push(ValidationError::UnknownEnumMember {
+                            path: path.to_owned(),
+                            enum_name: name.clone(),
+                            member: member.to_owned(),
+                        });
+                        None
+                    }
+                    None => self.wrong_type(&format!("a member of enum '{}'", name), json, path),
+                },
+                None => {
+                    self.errors.push(ValidationError::UnknownType {
+                        path: path.to_owned(),
+                        name: name.clone(),
+                    });
+                    None
+                }
+            },
+        }
+    }
+
+    /// Records the error of a JSON value which is not an integer in range of `data_type`. Numbers
+    /// with a fractional part are of the wrong type, while other numbers are out of range
+    fn integer_error(&mut self, data_type: &DataType, json: &serde_json::Value, path: &str) -> Option<Value> {
+        match json.as_f64() {
+            Some(number) if number.fract() == 0.0 => self.out_of_range(data_type, json, path),
+            _ => self.wrong_type("an integer", json, path),
+        }
+    }
+
+    fn out_of_range(&mut self, data_type: &DataType, json: &serde_json::Value, path: &str) -> Option<Value> {
+        self.errors.push(ValidationError::OutOfRange {
+            path: path.to_owned(),
+            value: json.to_string(),
+            data_type: data_type.clone(),
+        });
+        None
+    }
+
+    fn wrong_type(&mut self, expected: &str, json: &serde_json::Value, path: &str) -> Option<Value> {
+        let found = match json {
+            serde_json::Value::Null => "null".to_owned(),
+            serde_json::Value::Bool(_) => "a boolean".to_owned(),
+            serde_json::Value::Number(number) => format!("the number {}", number),
+            serde_json::Value::String(string) => format!("the string {:?}", string),
+            serde_json::Value::Array(_) => "an array".to_owned(),
+            serde_json::Value::Object(_) => "an object".to_owned(),
+        };
+        self.errors.push(ValidationError::WrongType {
+            path: path.to_owned(),
+            expected: expected.to_owned(),
+            found,
+        });
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_tokens;
+    use crate::parser::{NamedStatementList, StructMemberDeclaration, parse_tokens};
+    use serde_json::json;
+
+    /// A recursive struct, with 64 bit integers whose limits JSON numbers cannot all hold exactly
+    const TEXT: &str = "
+struct node {
+  id: u64, offset: i64, small: i8, ratio: f32, initial: char, label: option(string),
+  kind: enum kind { leaf, branch, }, children: array(node), parent: option(node),
+}
+";
+
+    fn definition() -> DataDefinition {
+        match parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse") {
+            ASTNode::DataDefinition(definition) => definition,
+            _ => panic!("should parse a data definition"),
+        }
+    }
+
+    #[test]
+    fn test_parse_valid_value() {
+        let json = json!({
+            "id": u64::MAX, "offset": i64::MIN, "small": -128, "ratio": 1.5, "initial": "é", "kind": "branch",
+            "children": [{
+                "id": 0, "offset": 0, "small": 0, "ratio": 0.5, "initial": "a", "label": "x", "kind": "leaf",
+                "children": [], "parent": null, "unknown": true,
+            }],
+        });
+        let leaf = Value::Struct(vec![
+            ("id".to_owned(), Value::Unsigned(0)),
+            ("offset".to_owned(), Value::Signed(0)),
+            ("small".to_owned(), Value::Signed(0)),
+            ("ratio".to_owned(), Value::Float(0.5)),
+            ("initial".to_owned(), Value::Char('a')),
+            ("label".to_owned(), Value::Option(Some(Box::new(Value::String("x".to_owned()))))),
+            ("kind".to_owned(), Value::Enum("leaf".to_owned())),
+            ("children".to_owned(), Value::Array(Vec::new())),
+            ("parent".to_owned(), Value::Option(None)),
+        ]);
+
+        let value = parse(&json, &definition(), "node").expect("should be valid");
+        assert_eq!(
+            value,
+            Value::Struct(vec![
+                ("id".to_owned(), Value::Unsigned(u64::MAX)),
+                ("offset".to_owned(), Value::Signed(i64::MIN)),
+                ("small".to_owned(), Value::Signed(-128)),
+                ("ratio".to_owned(), Value::Float(1.5)),
+                ("initial".to_owned(), Value::Char('é')),
+                ("label".to_owned(), Value::Option(None)),
+                ("kind".to_owned(), Value::Enum("branch".to_owned())),
+                ("children".to_owned(), Value::Array(vec![leaf])),
+                ("parent".to_owned(), Value::Option(None)),
+            ])
+        );
+        assert_eq!(
+            serde_json::Value::from(&value),
+            json!({
+                "id": u64::MAX, "offset": i64::MIN, "small": -128, "ratio": 1.5, "initial": "é", "label": null,
+                "kind": "branch",
+                "children": [{
+                    "id": 0, "offset": 0, "small": 0, "ratio": 0.5, "initial": "a", "label": "x", "kind": "leaf",
+                    "children": [], "parent": null,
+                }],
+                "parent": null,
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_errors() {
+        let leaf = json!({ "id": 1, "offset": 1, "small": 1, "ratio": 1, "initial": "a", "kind": "leaf", "children": [] });
+        let json = json!({
+            "id": 18446744073709551616.0, "offset": 9223372036854775808u64, "small": 1.5, "ratio": 1e39,
+            "initial": "ab", "label": 3, "kind": "angry",
+            "children": [leaf, null, { "small": 128, "initial": "b", "kind": "leaf", "children": [] }],
+            "parent": { "id": -1, "offset": 0, "small": 0, "ratio": 0, "initial": "", "kind": 0, "children": {}, "parent": leaf },
+        });
+
+        let errors = validate(&json, &definition(), "node")
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<String>>();
+        assert_eq!(
+            errors,
+            [
+                "/id: 1.8446744073709552e+19 is out of range of u64",
+                "/offset: 9223372036854775808 is out of range of i64",
+                "/small: expected an integer, found the number 1.5",
+                "/ratio: 1e+39 is out of range of f32",
+                "/initial: expected a single character, found 'ab'",
+                "/label: expected a string, found the number 3",
+                "/kind: 'angry' is not a member of enum 'kind'",
+                "/children/1: expected a 'node' object, found null",
+                "/children/2/id: missing required member",
+                "/children/2/offset: missing required member",
+                "/children/2/small: 128 is out of range of i8",
+                "/children/2/ratio: missing required member",
+                "/parent/id: -1 is out of range of u64",
+                "/parent/initial: expected a single character, found ''",
+                "/parent/kind: expected a member of enum 'kind', found the number 0",
+                "/parent/children: expected an array, found an object",
+            ]
+        );
+        let errors = validate(&json!("leaf"), &definition(), "tree");
+        assert_eq!(
+            errors,
+            [ValidationError::UnknownType {
+                path: "".to_owned(),
+                name: "tree".to_owned(),
+            }]
+        );
+        assert_eq!(errors[0].to_string(), "(root): type 'tree' is not declared");
+    }
+
+    /// Member names cannot hold `~` or `/` in the language, but definitions built in code may
+    #[test]
+    fn test_validate_escapes_paths() {
+        let definition = DataDefinition {
+            child_nodes: vec![ASTNode::StructDeclaration(NamedStatementList {
+                name: "range".to_owned(),
+                child_nodes: vec![ASTNode::StructMemberDeclaration(StructMemberDeclaration {
+                    name: "min/max~".to_owned(),
+                    data_type: Box::new(ASTNode::TypeLiteral(DataType::U8)),
+                    field_number: None,
+                    description: None,
+                })],
+                description: None,
+            })],
+        };
+
+        let errors = validate(&json!({ "min/max~": 256 }), &definition, "range");
+        assert_eq!(errors[0].path(), "/min~1max~0");
+        let errors = validate(&json!({}), &definition, "range");
+        assert_eq!(errors[0].to_string(), "/min~1max~0: missing required member");
+    }
+}
@@ -0,0 +1,648 @@
+/// The compact binary wire format, and its reference implementation
+///
+/// A payload is the encoding of a single value of a root type, which both sides know from the
+/// schema, so it carries no type information, member names or field numbers. Values are encoded
+/// as follows:
+/// - `u8`, `u16`, `u32` and `u64`: an unsigned LEB128 varint. Each byte holds 7 bits of the value,
+///   least significant first, with the high bit set on every byte but the last. A varint is at
+///   most 10 bytes long
+/// - `i8`, `i16`, `i32` and `i64`: the zigzag encoding of the value as a varint, such that 0, -1,
+///   1, -2 are encoded as 0, 1, 2, 3
+/// - `f32` and `f64`: the IEEE 754 binary32 or binary64 value, in 4 or 8 little endian bytes
+/// - `bool`: a single byte, 0 for false and 1 for true
+/// - `char`: the Unicode scalar value as a varint. A C++ `char` is a single byte, so only holds
+///   ASCII characters
+/// - `string`: the length in bytes as a varint, followed by the UTF-8 bytes of the string
+/// - `array(T)`: the number of elements as a varint, followed by each element
+/// - `option(T)`: a presence byte, 0 if the option is absent and 1 if present, followed by the
+///   value if present
+/// - enums: the index of the member, in declaration order, as a varint
+/// - structs: each member in declaration order, with nothing in between
+///
+/// A decoder rejects a payload which ends early or has bytes left over, a varint too long or out
+/// of range of its type, a bool or presence byte other than 0 or 1, an enum index without a
+/// member, and invalid UTF-8 or Unicode scalar values. It also rejects values with more than
+/// `MAX_DEPTH` nested options, arrays and structs, so that a payload cannot exhaust the stack of
+/// a recursive decoder.
+///
+/// The reference implementation converts between payloads and JSON, which represents values as
+/// the other targets do: integers and floats as numbers, chars as strings of one character, enums
+/// as the names of their members, and structs as objects. An absent option is `null` or left out
+/// of its object. JSON cannot tell an option of an absent option apart from an absent option, so
+/// such a value is decoded as absent. JSON has no infinities or NaN, so the reference decoder
+/// rejects them, although the format and the generated encoders and decoders carry them.
+///
+/// Generated encoders and decoders are enabled with the `wire` option of the `cxx` and
+/// `typescript` targets.
+use crate::parser::DataType;
+use crate::schema::{Declaration, Schema};
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// The most options, arrays and structs a decoded value may have nested within each other,
+/// counting the root struct
+pub const MAX_DEPTH: usize = 128;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum WireError {
+    /// A type which is not declared in the schema
+    UnknownType(String),
+    /// The payload ended at the offset, before the value did
+    UnexpectedEnd(usize),
+    InvalidData { offset: usize, reason: String },
+    /// The value ended at the offset, before the payload did
+    TrailingBytes(usize),
+    /// A JSON value to encode does not match the schema, at the JSON pointer `path`
+    InvalidValue { path: String, reason: String },
+}
+
+impl Display for WireError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WireError::UnknownType(name) => write!(f, "Type '{}' is not declared", name),
+            WireError::UnexpectedEnd(offset) => write!(f, "The payload ends unexpectedly at offset {}", offset),
+            WireError::InvalidData { offset, reason } => write!(f, "Invalid payload at offset {}: {}", offset, reason),
+            WireError::TrailingBytes(offset) => write!(f, "The payload has unexpected bytes from offset {}", offset),
+            WireError::InvalidValue { path, reason } => write!(f, "Invalid value at '{}': {}", path, reason),
+        }
+    }
+}
+
+impl Error for WireError {}
+
+/// Decodes a payload holding a value of `root_type`, a struct or enum declared in `schema`
+/// # Returns
+/// The value as JSON, or a WireError if the payload is not a valid encoding of `root_type`
+pub fn decode(schema: &Schema, root_type: &str, payload: &[u8]) -> Result<Value, WireError> {
+    let mut decoder = Decoder {
+        schema,
+        payload,
+        offset: 0,
+        depth: 0,
+    };
+    let value = decoder.decode(&DataType::UserDefined(root_type.to_owned()))?;
+    if decoder.offset != payload.len() {
+        return Err(WireError::TrailingBytes(decoder.offset));
+    }
+    Ok(value)
+}
+
+/// Encodes a JSON value of `root_type`, a struct or enum declared in `schema`
+/// # Returns
+/// The payload, or a WireError if the value does not match `root_type`
+pub fn encode(schema: &Schema, root_type: &str, value: &Value) -> Result<Vec<u8>, WireError> {
+    let mut encoder = Encoder {
+        schema,
+        payload: Vec::new(),
+    };
+    encoder.encode(&DataType::UserDefined(root_type.to_owned()), value, "")?;
+    Ok(encoder.payload)
+}
+
+struct Decoder<'a> {
+    schema: &'a Schema,
+    payload: &'a [u8],
+    offset: usize,
+    /// The number of options, arrays and structs being decoded
+    depth: usize,
+}
+
+impl Decoder<'_> {
+    fn decode(&mut self, data_type: &DataType) -> Result<Value, WireError> {
+        Ok(match data_type {
+            DataType::U8 => Value::from(self.unsigned(u8::MAX.into())?),
+            DataType::U16 => Value::from(self.unsigned(u16::MAX.into())?),
+            DataType::U32 => Value::from(self.unsigned(u32::MAX.into())?),
+            DataType::U64 => Value::from(self.varint()?),
+            DataType::I8 => Value::from(self.signed(i8::MIN.into(), i8::MAX.into())?),
+            DataType::I16 => Value::from(self.signed(i16::MIN.into(), i16::MAX.into())?),
+            DataType::I32 => Value::from(self.signed(i32::MIN.into(), i32::MAX.into())?),
+            DataType::I64 => Value::from(self.signed(i64::MIN, i64::MAX)?),
+            DataType::F32 => {
+                let offset = self.offset;
+                let number = f32::from_le_bytes(self.bytes(4)?.try_into().expect("should be 4 bytes"));
+                self.finite(offset, number.into())?
+            }
+            DataType::F64 => {
+                let offset = self.offset;
+                let number = f64::from_le_bytes(self.bytes(8)?.try_into().expect("should be 8 bytes"));
+                self.finite(offset, number)?
+            }
+            DataType::Bool => Value::from(self.flag("bool")?),
+            DataType::Char => {
+                let offset = self.offset;
+                let scalar = self.unsigned(u32::MAX.into())?;
+                match char::from_u32(scalar as u32) {
+                    Some(character) => Value::from(character.to_string()),
+                    None => return Err(self.invalid(offset, format!("{} is not a Unicode scalar value", scalar))),
+                }
+            }
+            DataType::String => {
+                let length = self.length()?;
+                let offset = self.offset;
+                let bytes = self.bytes(length)?;
+                match std::str::from_utf8(bytes) {
+                    Ok(string) => Value::from(string),
+                    Err(_) => return Err(self.invalid(offset, "the string is not valid UTF-8".to_owned())),
+                }
+            }
+            DataType::Option(inner_type) => self.nested(|decoder| match decoder.flag("presence")? {
+                true => decoder.decode(inner_type),
+                false => Ok(Value::Null),
+            })?,
+            DataType::Array(inner_type) => self.nested(|decoder| {
+                let length = decoder.length()?;
+                let mut elements = Vec::with_capacity(length.min(decoder.payload.len() - decoder.offset));
+                for _ in 0..length {
+                    elements.push(decoder.decode(inner_type)?);
+                }
+                Ok(Value::Array(elements))
+            })?,
+            DataType::UserDefined(name) => match self.schema.declaration(name) {
+                Some(Declaration::Struct(struct_definition)) => self.nested(|decoder| {
+                    let mut object = Map::new();
+                    for member in &struct_definition.members {
+                        object.insert(member.name.clone(), decoder.decode(&member.data_type)?);
+                    }
+                    Ok(Value::Object(object))
+                })?,
+                Some(Declaration::Enum(enum_definition)) => {
+                    let offset = self.offset;
+                    let index = self.varint()?;
+                    match enum_definition.members.get(index as usize) {
+                        Some(member) => Value::from(member.as_str()),
+                        None => return Err(self.invalid(offset, format!("{} is not a member index of '{}'", index, name))),
+                    }
+                }
+                None => return Err(WireError::UnknownType(name.clone())),
+            },
+        })
+    }
+
+    fn invalid(&self, offset: usize, reason: String) -> WireError {
+        WireError::InvalidData { offset, reason }
+    }
+
+    /// JSON has no infinities or NaN, which would otherwise be decoded as null
+    fn finite(&self, offset: usize, number: f64) -> Result<Value, WireError> {
+        match number.is_finite() {
+            true => Ok(Value::from(number)),
+            false => Err(self.invalid(offset, format!("{} is not a number JSON can represent", number))),
+        }
+    }
+
+    /// Decodes an option, array or struct, which is nested one level deeper than its parent
+    fn nested(&mut self, decode: impl FnOnce(&mut Self) -> Result<Value, WireError>) -> Result<Value, WireError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.invalid(self.offset, format!("the value is nested more than {} levels deep", MAX_DEPTH)));
+        }
+        self.depth += 1;
+        let value = decode(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&[u8], WireError> {
+        if self.payload.len() - self.offset < length {
+            return Err(WireError::UnexpectedEnd(self.payload.len()));
+        }
+        self.offset += length;
+        Ok(&self.payload[self.offset - length..self.offset])
+    }
+
+    fn varint(&mut self) -> Result<u64, WireError> {
+        let offset = self.offset;
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.bytes(1)?[0];
+            // The tenth byte holds the highest bit only
+            if shift == 63 && byte > 1 {
+                return Err(self.invalid(offset, "the varint is longer than 64 bits".to_owned()));
+            }
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        unreachable!("the tenth byte of a varint should end it")
+    }
+
+    fn unsigned(&mut self, max: u64) -> Result<u64, WireError> {
+        let offset = self.offset;
+        let value = self.varint()?;
+        if value > max {
+            return Err(self.invalid(offset, format!("{} is out of range, the maximum being {}", value, max)));
+        }
+        Ok(value)
+    }
+
+    fn signed(&mut self, min: i64, max: i64) -> Result<i64, WireError> {
+        let offset = self.offset;
+        let zigzag = self.varint()?;
+        let value = (zigzag >> 1) as i64 ^ -((zigzag & 1) as i64);
+        if value < min || value > max {
+            return Err(self.invalid(offset, format!("{} is out of range of {} to {}", value, min, max)));
+        }
+        Ok(value)
+    }
+
+    fn length(&mut self) -> Result<usize, WireError> {
+        Ok(self.unsigned(u32::MAX.into())? as usize)
+    }
+
+    /// Decodes a byte which must be 0 or 1, being a `kind` such as a bool
+    fn flag(&mut self, kind: &str) -> Result<bool, WireError> {
+        let offset = self.offset;
+        match self.bytes(1)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            byte => Err(self.invalid(offset, format!("{} is not a valid {} byte", byte, kind))),
+        }
+    }
+}
+
+struct Encoder<'a> {
+    schema: &'a Schema,
+    payload: Vec<u8>,
+}
+
+impl Encoder<'_> {
+    fn encode(&mut self, data_type: &DataType, value: &Value, path: &str) -> Result<(), WireError> {
+        let invalid = |reason: String| WireError::InvalidValue {
+            path: path.to_owned(),
+            reason,
+        };
+        let expected = |description: &str| invalid(format!("expected {}, found {}", description, value));
+
+        match data_type {
+            DataType::U8 | DataType::U16 | DataType::U32 | DataType::U64 => {
+                let max = match data_type {
+                    DataType::U8 => u8::MAX.into(),
+                    DataType::U16 => u16::MAX.into(),
+                    DataType::U32 => u32::MAX.into(),
+                    _ => u64::MAX,
+                };
+                match value.as_u64() {
+                    Some(integer) if integer <= max => self.varint(integer),
+                    _ => return Err(expected(&format!("an integer from 0 to {}", max))),
+                }
+            }
+            DataType::I8 | DataType::I16 | DataType::I32 | DataType::I64 => {
+                let (min, max) = match data_type {
+                    DataType::I8 => (i8::MIN.into(), i8::MAX.into()),
+                    DataType::I16 => (i16::MIN.into(), i16::MAX.into()),
+                    DataType::I32 => (i32::MIN.into(), i32::MAX.into()),
+                    _ => (i64::MIN, i64::MAX),
+                };
+                match value.as_i64() {
+                    Some(integer) if integer >= min && integer <= max => self.varint(((integer << 1) ^ (integer >> 63)) as u64),
+                    _ => return Err(expected(&format!("an integer from {} to {}", min, max))),
+                }
+            }
+            DataType::F32 => match value.as_f64() {
+                Some(number) => self.payload.extend((number as f32).to_le_bytes()),
+                None => return Err(expected("a number")),
+            },
+            DataType::F64 => match value.as_f64() {
+                Some(number) => self.payload.extend(number.to_le_bytes()),
+                None => return Err(expected("a number")),
+            },
+            DataType::Bool => match value.as_bool() {
+                Some(boolean) => self.payload.push(boolean.into()),
+                None => return Err(expected("a boolean")),
+            },
+            DataType::Char => {
+                let mut characters = value.as_str().map(str::chars).into_iter().flatten();
+                match (characters.next(), characters.next()) {
+                    (Some(character), None) => self.varint(character.into()),
+                    _ => return Err(expected("a string of one character")),
+                }
+            }
+            DataType::String => match value.as_str() {
+                Some(string) => {
+                    self.varint(string.len() as u64);
+                    self.payload.extend(string.as_bytes());
+                }
+                None => return Err(expected("a string")),
+            },
+            DataType::Option(inner_type) => match value {
+                Value::Null => self.payload.push(0),
+                value => {
+                    self.payload.push(1);
+                    self.encode(inner_type, value, path)?;
+                }
+            },
+            DataType::Array(inner_type) => match value.as_array() {
+                Some(elements) => {
+                    self.varint(elements.len() as u64);
+                    for (index, element) in elements.iter().enumerate() {
+                        self.encode(inner_type, element, &format!("{}/{}", path, index))?;
+                    }
+                }
+                None => return Err(expected("an array")),
+            },
+            DataType::UserDefined(name) => match self.schema.declaration(name) {
+                Some(Declaration::Struct(struct_definition)) => {
+                    let Some(object) = value.as_object() else {
+                        return Err(expected(&format!("a '{}' object", name)));
+                    };
+                    for member in &struct_definition.members {
+                        let member_path = format!("{}/{}", path, member.name);
+                        match (object.get(&member.name), &member.data_type) {
+                            (Some(member_value), data_type) => self.encode(data_type, member_value, &member_path)?,
+                            (None, DataType::Option(_)) => self.payload.push(0),
+                            (None, _) => {
+                                return Err(WireError::InvalidValue {
+                                    path: member_path,
+                                    reason: "missing required member".to_owned(),
+                                })
+                            }
+                        }
+                    }
+                }
+                Some(Declaration::Enum(enum_definition)) => {
+                    match enum_definition.members.iter().position(|member| value.as_str() == Some(member)) {
+                        Some(index) => self.varint(index as u64),
+                        None => return Err(expected(&format!("one of {}", enum_definition.members.join(", ")))),
+                    }
+                }
+                None => return Err(WireError::UnknownType(name.clone())),
+            },
+        }
+        Ok(())
+    }
+
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.payload.push((value & 0x7f) as u8 | 0x80);
+            value >>= 7;
+        }
+        self.payload.push(value as u8);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+    use crate::toolchain::{self, run, temporary_directory};
+    use serde_json::json;
+
+    /// The varints and zigzags of 64 bit limits, an option of an option, which JSON cannot tell
+    /// apart from an absent option when absent, and a recursive struct
+    const TEXT: &str = "
+struct node {
+  id: u64, offset: i64, initial: char, ratio: f64, maybe: option(option(u8)), labels: array(option(string)),
+  kind: enum kind { leaf, branch, }, children: array(node),
+}
+";
+
+    fn schema() -> Schema {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+        Schema::from_ast(&ast).expect("should build schema")
+    }
+
+    #[test]
+    fn test_encode_and_decode() {
+        let value = json!({
+            "id": u64::MAX, "offset": -1, "initial": "é", "ratio": 0.5, "maybe": null, "labels": ["a", null],
+            "kind": "branch",
+            "children": [{
+                "id": 0, "offset": i64::MIN, "initial": "a", "ratio": 2.0, "maybe": 7, "labels": [], "kind": "leaf",
+                "children": [],
+            }],
+        });
+        let payload = encode(&schema(), "node", &value).expect("should encode");
+
+        assert_eq!(
+            payload,
+            vec![
+                0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01, // id
+                1, // offset
+                0xe9, 0x01, // initial
+                0, 0, 0, 0, 0, 0, 0xe0, 0x3f, // ratio
+                0, // maybe
+                2, 1, 1, b'a', 0, // labels
+                1, // kind
+                1, // children
+                0, // id
+                0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01, // offset
+                b'a', // initial
+                0, 0, 0, 0, 0, 0, 0, 0x40, // ratio
+                1, 1, 7, // maybe
+                0, // labels
+                0, // kind
+                0, // children
+            ]
+        );
+        assert_eq!(decode(&schema(), "node", &payload).expect("should decode"), value);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let schema = schema();
+        let invalid_data = |payload: &[u8]| match decode(&schema, "node", payload) {
+            Err(WireError::InvalidData { offset, .. }) => offset,
+            result => panic!("expected invalid data, found {:?}", result),
+        };
+        // The id, offset, initial and ratio of a node
+        let start = [0, 0, b'a', 0, 0, 0, 0, 0, 0, 0, 0];
+
+        assert_eq!(decode(&schema, "node", &[0xff]), Err(WireError::UnexpectedEnd(1)));
+        assert_eq!(invalid_data(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02]), 0);
+        assert_eq!(invalid_data(&[0, 0, 0x80, 0xb0, 0x03]), 2);
+        assert_eq!(invalid_data(&[&start[..], &[1, 2]].concat()), 12);
+        assert_eq!(
+            decode(&schema, "node", &[0, 0, b'a', 0, 0, 0, 0, 0, 0, 0xf8, 0x7f]),
+            Err(WireError::InvalidData {
+                offset: 3,
+                reason: "NaN is not a number JSON can represent".to_owned(),
+            })
+        );
+        assert_eq!(invalid_data(&[0, 0, b'a', 0, 0, 0, 0, 0, 0, 0xf0, 0xff]), 3);
+        assert_eq!(invalid_data(&[&start[..], &[0, 0, 2]].concat()), 13);
+        assert_eq!(
+            decode(&schema, "node", &[&start[..], &[0, 0, 0, 0, 0xff]].concat()),
+            Err(WireError::TrailingBytes(15))
+        );
+        assert!(matches!(
+            encode(&schema, "node", &json!({
+                "id": 1, "offset": 1, "initial": "a", "ratio": 1, "labels": [], "kind": "leaf", "children": [{}],
+            })),
+            Err(WireError::InvalidValue { path, .. }) if path == "/children/0/id"
+        ));
+    }
+
+    #[test]
+    fn test_decode_depth_limit() {
+        let ast = parse_tokens(lex_tokens("struct n { next: option(n) }".to_owned()).expect("should lex")).expect("should parse");
+        let schema = Schema::from_ast(&ast).expect("should build schema");
+
+        // Each struct and option is a level, so 63 present options and the absent one nest 128 deep
+        let mut payload = vec![1; MAX_DEPTH / 2 - 1];
+        payload.push(0);
+        assert!(decode(&schema, "n", &payload).is_ok());
+        payload.insert(0, 1);
+        assert_eq!(
+            decode(&schema, "n", &payload),
+            Err(WireError::InvalidData {
+                offset: MAX_DEPTH / 2,
+                reason: format!("the value is nested more than {} levels deep", MAX_DEPTH),
+            })
+        );
+        assert!(matches!(
+            decode(&schema, "n", &vec![1; 1 << 20]),
+            Err(WireError::InvalidData { .. })
+        ));
+    }
+
+    /// A schema using every type, for the round trips through the generated encoders and decoders
+    const ROUND_TRIP_TEXT: &str = "
+struct manager { name: string, level: u16 }
+struct person {
+  id: u32, small: i8, medium: i16, count: u16, offset: i32, big: i64, huge: u64, tiny: u8,
+  score: f32, ratio: f64, active: bool, initial: char, name: string,
+  nickname: option(string), missing: option(u8), scores: array(option(f32)), grid: array(array(u8)),
+  feeling: enum mood { happy, sad, }, boss: option(manager), team: array(manager),
+}
+struct tree { children: array(tree) }
+";
+
+    /// The error of the generated decoders for a payload of `tree`s nested too deep
+    const DEPTH_ERROR: &str = "the value at offset 64 is nested more than 128 levels deep";
+
+    fn round_trip_payload() -> Vec<u8> {
+        let ast = parse_tokens(lex_tokens(ROUND_TRIP_TEXT.to_owned()).expect("should lex")).expect("should parse");
+        let schema = Schema::from_ast(&ast).expect("should build schema");
+        let value = json!({
+            "id": 300, "small": -128, "medium": -300, "count": 65535, "offset": i32::MIN, "big": i64::MIN,
+            "huge": u64::MAX, "tiny": 255, "score": 1.5, "ratio": 0.1, "active": true, "initial": "A",
+            "name": "Zo\u{eb} \u{1f980}", "nickname": "Z", "missing": null, "scores": [1.5, null], "grid": [[1, 2], []],
+            "feeling": "sad", "boss": { "name": "Bob", "level": 3 }, "team": [{ "name": "Cy", "level": 0 }],
+        });
+        let payload = encode(&schema, "person", &value).expect("should encode");
+        assert_eq!(decode(&schema, "person", &payload).expect("should decode"), value);
+        payload
+    }
+
+    fn generate(target: &dyn crate::compilation_target::CompilationTarget) -> String {
+        let ast = parse_tokens(lex_tokens(ROUND_TRIP_TEXT.to_owned()).expect("should lex")).expect("should parse");
+        let mut compilation_info = crate::compilation_target::CompilationInfo::default();
+        compilation_info.options.set("wire", "true");
+        target.generate_code(&ast, &compilation_info).expect("should generate code")
+    }
+
+    fn hex(payload: &[u8]) -> String {
+        payload.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    #[test]
+    fn test_cxx_round_trip() {
+        if !toolchain::require_programs("test_cxx_round_trip", &["g++"]) {
+            return;
+        }
+        let payload = round_trip_payload();
+        let directory = temporary_directory("wire-cxx");
+        std::fs::write(directory.join("person.h"), generate(&crate::cxx::CXXGenerator)).expect("should write header");
+        std::fs::write(
+            directory.join("main.cpp"),
+            r#"#include "person.h"
+#include <cstdio>
+#include <string>
+
+int main(int, char** argv) {
+    std::string hex = argv[1];
+    std::vector<std::uint8_t> bytes;
+    for (std::size_t index = 0; index < hex.size(); index += 2) {
+        bytes.push_back(static_cast<std::uint8_t>(std::stoi(hex.substr(index, 2), nullptr, 16)));
+    }
+    person decoded;
+    decode(bytes, decoded);
+
+    person expected;
+    expected.id = 300; expected.small = -128; expected.medium = -300; expected.count = 65535;
+    expected.offset = INT32_MIN; expected.big = INT64_MIN; expected.huge = UINT64_MAX; expected.tiny = 255;
+    expected.score = 1.5f; expected.ratio = 0.1; expected.active = true; expected.initial = 'A';
+    expected.name = "Zoë \U0001F980"; expected.nickname = "Z"; expected.scores = {1.5f, std::nullopt};
+    expected.grid = {{1, 2}, {}}; expected.feeling = mood::sad; expected.boss = manager{"Bob", 3};
+    expected.team = {manager{"Cy", 0}};
+
+    for (std::vector<std::uint8_t> encoded : {encode(decoded), encode(expected)}) {
+        for (std::uint8_t byte : encoded) std::printf("%02x", byte);
+        std::printf("\n");
+    }
+
+    try {
+        tree deep;
+        decode(std::vector<std::uint8_t>(1 << 20, 1), deep);
+    } catch (const fruko_wire::error& error) {
+        std::printf("%s\n", error.what());
+    }
+}
+"#,
+        )
+        .expect("should write program");
+
+        run("g++", &["-std=c++17", "-Wall", "-Wextra", "-pedantic", "-Werror", "main.cpp", "-o", "main"], &directory);
+        let output = run("./main", &[&hex(&payload)], &directory);
+        assert_eq!(output, format!("{hex}\n{hex}\n{DEPTH_ERROR}\n", hex = hex(&payload)));
+        std::fs::remove_dir_all(directory).expect("should remove directory");
+    }
+
+    /// Type checks the generated module and the wire module it imports with `tsc --strict`, and
+    /// runs the compiled Javascript
+    #[test]
+    fn test_typescript_round_trip() {
+        if !toolchain::require_programs("test_typescript_round_trip", &["tsc", "node"]) {
+            return;
+        }
+        let payload = round_trip_payload();
+        use crate::compilation_target::CompilationTarget;
+        let directory = temporary_directory("wire-typescript");
+        let target = crate::typescript::TypeScriptGenerator;
+        std::fs::write(directory.join("person.ts"), generate(&target)).expect("should write module");
+        let mut compilation_info = crate::compilation_target::CompilationInfo::default();
+        compilation_info.options.set("wire", "true");
+        for support_file in target.support_files(&compilation_info) {
+            std::fs::write(directory.join(support_file.name), support_file.contents).expect("should write support module");
+        }
+        std::fs::write(
+            directory.join("main.ts"),
+            format!(
+                r#"import {{ decodePerson, decodeTree, encodePerson, person }} from './person';
+import {{ WireError }} from './wire';
+
+const hex = (bytes: Uint8Array): string => Array.from(bytes, (byte) => byte.toString(16).padStart(2, '0')).join('');
+const bytes = Uint8Array.from('{}'.match(/../g) ?? [], (byte) => parseInt(byte, 16));
+const expected: person = {{
+    id: 300, small: -128, medium: -300, count: 65535, offset: -2147483648, big: -9223372036854775808n,
+    huge: 18446744073709551615n, tiny: 255, score: 1.5, ratio: 0.1, active: true, initial: 'A',
+    name: 'Zoë \u{{1f980}}', nickname: 'Z', scores: [1.5, null], grid: [[1, 2], []], feeling: 'sad',
+    boss: {{ name: 'Bob', level: 3 }}, team: [{{ name: 'Cy', level: 0 }}],
+}};
+console.log(hex(encodePerson(decodePerson(bytes))));
+console.log(hex(encodePerson(expected)));
+try {{
+    decodeTree(new Uint8Array(1 << 20).fill(1));
+}} catch (error) {{
+    console.log(error instanceof WireError ? error.message : error);
+}}
+"#,
+                hex(&payload)
+            ),
+        )
+        .expect("should write program");
+
+        // Generated modules import each other without an extension, which CommonJS resolves
+        run(
+            "tsc",
+            &["--strict", "--target", "es2020", "--module", "commonjs", "--lib", "es2020,dom", "person.ts", "main.ts"],
+            &directory,
+        );
+        let output = run("node", &["main.js"], &directory);
+        assert_eq!(output, format!("{hex}\n{hex}\n{DEPTH_ERROR}\n", hex = hex(&payload)));
+        std::fs::remove_dir_all(directory).expect("should remove directory");
+    }
+}
@@ -0,0 +1,271 @@
+/// Generation for Zod schemas, validating data at runtime in Typescript
+///
+/// Every struct and enum is exported as a schema, along with the type inferred from it under the
+/// same name. Schemas are declared after the schemas they refer to. References which cannot be
+/// ordered this way, in recursive types, are wrapped in `z.lazy`. Typescript cannot infer the type
+/// of such a schema, so its type is generated as an interface instead, which annotates the schema.
+/// Names which are reserved words get a trailing `_`, as in the Typescript target.
+///
+/// Supported options:
+/// - `int64`: `bigint` (the default) or `number`, the type 64 bit integers are validated as, as
+///   in the Typescript target. A `number` can only represent integers up to 2^53 exactly, so is
+///   bounded to the safe integer range
+/// - `import_path`: the module Zod is imported from, `zod` by default
+use crate::compilation_target::{CompilationError, CompilationInfo, CompilationTarget};
+use crate::parser::{ASTNode, DataType};
+use crate::schema::{Declaration, EnumDefinition, Schema, StructDefinition};
+use crate::typescript::escape_type_name;
+
+const DEFAULT_IMPORT_PATH: &str = "zod";
+
+pub struct ZodGenerator;
+
+impl CompilationTarget for ZodGenerator {
+    fn generate_code(&self, ast: &ASTNode, compilation_info: &CompilationInfo) -> Result<String, CompilationError> {
+        let int64_type = compilation_info.options.get_choice("int64", &["bigint", "number"], "bigint")?;
+        let import_path = compilation_info.options.get("import_path").unwrap_or(DEFAULT_IMPORT_PATH);
+        let schema = Schema::from_ast(ast)?;
+
+        Ok(format!(
+            "{}\nimport {{ z }} from '{}';\n{}\n{}",
+            generate_preamble(compilation_info),
+            import_path,
+            generate_external_imports(compilation_info),
+            ZodSchemaGenerator::new(int64_type).generate(&schema)
+        ))
+    }
+}
+
+fn generate_preamble(compilation_info: &CompilationInfo) -> String {
+    compilation_info.preamble_comments.iter()
+        .map(|preamble| format!("// {}", preamble))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Imports the schemas declared in other files of the batch from their generated modules
+fn generate_external_imports(compilation_info: &CompilationInfo) -> String {
+    compilation_info
+        .external_modules()
+        .iter()
+        .map(|(module, names)| {
+            let names = names.iter().map(|name| escape_type_name(name)).collect::<Vec<String>>();
+            format!("import {{ {} }} from './{}';\n", names.join(", "), module)
+        })
+        .collect()
+}
+
+/// Helper struct, keeping track of which schemas have been declared so far
+struct ZodSchemaGenerator<'a> {
+    int64_type: &'a str,
+    /// Schemas declared by this file, as opposed to those imported from other files
+    local: Vec<&'a str>,
+    declared: Vec<&'a str>,
+}
+
+impl<'a> ZodSchemaGenerator<'a> {
+    fn new(int64_type: &'a str) -> Self {
+        Self {
+            int64_type,
+            local: Vec::new(),
+            declared: Vec::new(),
+        }
+    }
+
+    fn generate(mut self, schema: &'a Schema) -> String {
+        self.local = schema.declarations.iter().map(Declaration::name).collect();
+
+        schema
+            .dependency_order()
+            .into_iter()
+            .map(|declaration| {
+                let code = match declaration {
+                    Declaration::Struct(struct_definition) if self.is_recursive(struct_definition) => format!(
+                        "{}\n{}\n",
+                        self.generate_interface(struct_definition),
+                        self.generate_object(struct_definition, &format!(": z.ZodType<{}>", escape_type_name(&struct_definition.name)))
+                    ),
+                    Declaration::Struct(struct_definition) => {
+                        format!("{}\n{}", self.generate_object(struct_definition, ""), generate_inferred_type(declaration))
+                    }
+                    Declaration::Enum(enum_definition) => {
+                        format!("{}\n{}", generate_enum(enum_definition), generate_inferred_type(declaration))
+                    }
+                };
+                self.declared.push(declaration.name());
+                code
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Whether a struct refers to a schema which is declared after it, through `z.lazy`
+    fn is_recursive(&self, struct_definition: &StructDefinition) -> bool {
+        struct_definition
+            .members
+            .iter()
+            .flat_map(|member| member.data_type.user_defined_types())
+            .any(|name| self.local.contains(&name) && !self.declared.contains(&name))
+    }
+
+    fn generate_interface(&self, struct_definition: &StructDefinition) -> String {
+        let members = struct_definition
+            .members
+            .iter()
+            .map(|member| match &member.data_type {
+                DataType::Option(inner_type) => format!("    {}?: {};\n", member.name, self.generate_type_name(inner_type)),
+                data_type => format!("    {}: {};\n", member.name, self.generate_type_name(data_type)),
+            })
+            .collect::<String>();
+        format!("export interface {} {{\n{}}}", escape_type_name(&struct_definition.name), members)
+    }
+
+    /// Generates the Typescript type of the values a schema for a data type validates
+    fn generate_type_name(&self, data_type: &DataType) -> String {
+        match data_type {
+            DataType::U64 | DataType::I64 if self.int64_type == "bigint" => "bigint".to_owned(),
+            DataType::U8
+            | DataType::I8
+            | DataType::U16
+            | DataType::I16
+            | DataType::U32
+            | DataType::I32
+            | DataType::U64
+            | DataType::I64
+            | DataType::F32
+            | DataType::F64 => "number".to_owned(),
+            DataType::Char | DataType::String => "string".to_owned(),
+            DataType::Bool => "boolean".to_owned(),
+            DataType::Option(inner_type) => format!("{} | null", self.generate_type_name(inner_type)),
+            DataType::Array(inner_type) => match inner_type.as_ref() {
+                DataType::Option(_) => format!("({})[]", self.generate_type_name(inner_type)),
+                _ => format!("{}[]", self.generate_type_name(inner_type)),
+            },
+            DataType::UserDefined(name) => escape_type_name(name),
+        }
+    }
+
+    /// Generates the schema of a struct, with the type `annotation` of the constant if not empty
+    fn generate_object(&self, struct_definition: &StructDefinition, annotation: &str) -> String {
+        let members = struct_definition
+            .members
+            .iter()
+            .map(|member| match &member.data_type {
+                DataType::Option(inner_type) => {
+                    format!("    {}: {}.optional(),\n", member.name, self.generate_type(inner_type))
+                }
+                data_type => format!("    {}: {},\n", member.name, self.generate_type(data_type)),
+            })
+            .collect::<String>();
+        format!("export const {}{} = z.object({{\n{}}});", escape_type_name(&struct_definition.name), annotation, members)
+    }
+
+    /// Generates the schema for a data type. Options nested within another type are generated as
+    /// nullable, as JSON has no way to represent an absent array element
+    fn generate_type(&self, data_type: &DataType) -> String {
+        match data_type {
+            DataType::U8 => "z.number().int().min(0).max(255)".to_owned(),
+            DataType::I8 => "z.number().int().min(-128).max(127)".to_owned(),
+            DataType::U16 => "z.number().int().min(0).max(65535)".to_owned(),
+            DataType::I16 => "z.number().int().min(-32768).max(32767)".to_owned(),
+            DataType::U32 => "z.number().int().min(0).max(4294967295)".to_owned(),
+            DataType::I32 => "z.number().int().min(-2147483648).max(2147483647)".to_owned(),
+            DataType::U64 if self.int64_type == "bigint" => {
+                "z.bigint().min(0n).max(18446744073709551615n)".to_owned()
+            }
+            DataType::I64 if self.int64_type == "bigint" => {
+                "z.bigint().min(-9223372036854775808n).max(9223372036854775807n)".to_owned()
+            }
+            DataType::U64 => "z.number().int().min(0).max(Number.MAX_SAFE_INTEGER)".to_owned(),
+            DataType::I64 => {
+                "z.number().int().min(Number.MIN_SAFE_INTEGER).max(Number.MAX_SAFE_INTEGER)".to_owned()
+            }
+            DataType::F32 | DataType::F64 => "z.number()".to_owned(),
+            DataType::Char => "z.string().length(1)".to_owned(),
+            DataType::String => "z.string()".to_owned(),
+            DataType::Bool => "z.boolean()".to_owned(),
+            DataType::Option(inner_type) => format!("{}.nullable()", self.generate_type(inner_type)),
+            DataType::Array(inner_type) => format!("z.array({})", self.generate_type(inner_type)),
+            DataType::UserDefined(name) => {
+                if self.local.contains(&name.as_str()) && !self.declared.contains(&name.as_str()) {
+                    format!("z.lazy(() => {})", escape_type_name(name))
+                } else {
+                    escape_type_name(name)
+                }
+            }
+        }
+    }
+}
+
+fn generate_inferred_type(declaration: &Declaration) -> String {
+    let name = escape_type_name(declaration.name());
+    format!("export type {} = z.infer<typeof {}>;\n", name, name)
+}
+
+fn generate_enum(enum_definition: &EnumDefinition) -> String {
+    let members = enum_definition
+        .members
+        .iter()
+        .map(|member| format!("'{}'", member))
+        .collect::<Vec<String>>()
+        .join(", ");
+    format!("export const {} = z.enum([{}]);", escape_type_name(&enum_definition.name), members)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_tokens;
+    use crate::parser::parse_tokens;
+
+    const TEXT: &str = "struct order { buyer: class, initial: char, next: option(order) } struct class { id: u64, tags: array(option(string)), kind: enum default { in, out, } }";
+
+    const GENERATED_CODE: &str = "
+import { z } from 'zod';
+
+export const default_ = z.enum(['in', 'out']);
+export type default_ = z.infer<typeof default_>;
+
+export const class_ = z.object({
+    id: z.bigint().min(0n).max(18446744073709551615n),
+    tags: z.array(z.string().nullable()),
+    kind: default_,
+});
+export type class_ = z.infer<typeof class_>;
+
+export interface order {
+    buyer: class_;
+    initial: string;
+    next?: order;
+}
+export const order: z.ZodType<order> = z.object({
+    buyer: class_,
+    initial: z.string().length(1),
+    next: z.lazy(() => order).optional(),
+});
+";
+
+    #[test]
+    fn test_generate_zod() {
+        let ast = parse_tokens(lex_tokens(TEXT.to_owned()).expect("should lex")).expect("should parse");
+
+        assert_eq!(
+            ZodGenerator
+                .generate_code(&ast, &CompilationInfo::default())
+                .expect("should generate code"),
+            GENERATED_CODE
+        );
+    }
+
+    #[test]
+    fn test_generate_zod_int64_number() {
+        let ast = parse_tokens(lex_tokens("struct total { cents: i64 }".to_owned()).expect("should lex")).expect("should parse");
+        let mut compilation_info = CompilationInfo::default();
+        compilation_info.options.set("int64", "number");
+
+        assert!(ZodGenerator
+            .generate_code(&ast, &compilation_info)
+            .expect("should generate code")
+            .contains("    cents: z.number().int().min(Number.MIN_SAFE_INTEGER).max(Number.MAX_SAFE_INTEGER),\n"));
+    }
+}